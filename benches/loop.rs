@@ -28,9 +28,12 @@ fn criterion_benchmark(c: &mut Criterion) {
 		block_coinbase: Default::default(),
 		block_timestamp: Default::default(),
 		block_difficulty: Default::default(),
+		block_prev_randao: Default::default(),
 		block_gas_limit: Default::default(),
 		chain_id: U256::one(),
 		block_base_fee_per_gas: U256::zero(),
+		block_blob_base_fee: U256::zero(),
+		blob_hashes: Vec::new(),
 	};
 
 	let mut state = BTreeMap::new();
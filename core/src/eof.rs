@@ -0,0 +1,369 @@
+use crate::Opcode;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// Maximum number of code sections of a container.
+const MAX_CODE_SECTIONS: usize = 1024;
+
+/// Section kinds of the container header.
+const KIND_TYPES: u8 = 0x01;
+const KIND_CODE: u8 = 0x02;
+const KIND_DATA: u8 = 0x03;
+const TERMINATOR: u8 = 0x00;
+
+/// Type of a code section, as called by `CALLF`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CodeType {
+	/// Number of stack items the section takes.
+	pub inputs: u8,
+	/// Number of stack items the section returns.
+	pub outputs: u8,
+	/// Maximum stack height the section reaches, relative to its inputs.
+	pub max_stack_height: u16,
+}
+
+/// Reason a container is not valid EOF.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EofError {
+	/// The code does not start with the EOF magic.
+	InvalidMagic,
+	/// The container version is not supported.
+	InvalidVersion,
+	/// The header has missing, misplaced or empty sections.
+	InvalidHeader,
+	/// The sections do not add up to the container size.
+	InvalidSize,
+	/// The type section does not match the code sections.
+	InvalidTypes,
+	/// A code section uses an undefined or deprecated opcode.
+	UndefinedOpcode(Opcode),
+	/// A code section ends in the middle of an instruction.
+	TruncatedImmediate,
+	/// A code section does not end with a terminating instruction.
+	MissingTerminator,
+	/// A relative jump targets outside of its code section, or the immediate
+	/// of an instruction.
+	InvalidJumpDestination,
+	/// A `CALLF` targets a code section that does not exist.
+	InvalidSectionIndex,
+}
+
+/// Layout of a validated EVM Object Format container, with version 1 as
+/// defined by [EIP-3540](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3540.md),
+/// [EIP-3670](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3670.md),
+/// [EIP-4200](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-4200.md)
+/// and [EIP-4750](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-4750.md).
+///
+/// Sections are ranges of the whole container, so that program counters stay
+/// positions in the code as with legacy code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Eof {
+	types: Vec<CodeType>,
+	code_sections: Vec<Range<usize>>,
+	data: Range<usize>,
+}
+
+impl Eof {
+	/// Magic bytes that start every container.
+	pub const MAGIC: [u8; 2] = [0xef, 0x00];
+	/// Supported container version.
+	pub const VERSION: u8 = 0x01;
+
+	/// Whether the code is meant to be a container, that is whether it starts
+	/// with the EOF magic.
+	pub fn is_eof(code: &[u8]) -> bool {
+		code.starts_with(&Self::MAGIC)
+	}
+
+	/// Parse and validate a container.
+	pub fn new(code: &[u8]) -> Result<Self, EofError> {
+		let eof = Self::parse(code)?;
+		for index in 0..eof.code_sections.len() {
+			eof.validate_code(code, index)?;
+		}
+		Ok(eof)
+	}
+
+	/// Number of code sections.
+	pub fn code_sections(&self) -> usize {
+		self.code_sections.len()
+	}
+
+	/// Range of the code section at `index` in the container.
+	pub fn code_section(&self, index: usize) -> Option<Range<usize>> {
+		self.code_sections.get(index).cloned()
+	}
+
+	/// Type of the code section at `index`.
+	pub fn code_type(&self, index: usize) -> Option<CodeType> {
+		self.types.get(index).copied()
+	}
+
+	/// Range of the data section in the container.
+	pub fn data_section(&self) -> Range<usize> {
+		self.data.clone()
+	}
+
+	fn parse(code: &[u8]) -> Result<Self, EofError> {
+		if !Self::is_eof(code) {
+			return Err(EofError::InvalidMagic);
+		}
+		let mut reader = Reader { code, position: 2 };
+		if reader.u8()? != Self::VERSION {
+			return Err(EofError::InvalidVersion);
+		}
+
+		reader.kind(KIND_TYPES)?;
+		let types_size = reader.u16()?;
+		reader.kind(KIND_CODE)?;
+		let num_code_sections = reader.u16()?;
+		if num_code_sections == 0 || num_code_sections > MAX_CODE_SECTIONS {
+			return Err(EofError::InvalidHeader);
+		}
+		let mut code_sizes = Vec::with_capacity(num_code_sections);
+		for _ in 0..num_code_sections {
+			match reader.u16()? {
+				0 => return Err(EofError::InvalidHeader),
+				size => code_sizes.push(size),
+			}
+		}
+		reader.kind(KIND_DATA)?;
+		let data_size = reader.u16()?;
+		reader.kind(TERMINATOR)?;
+
+		if types_size != num_code_sections * 4 {
+			return Err(EofError::InvalidTypes);
+		}
+		let body = reader.position;
+		let size = body + types_size + code_sizes.iter().sum::<usize>() + data_size;
+		if size != code.len() {
+			return Err(EofError::InvalidSize);
+		}
+
+		let types = code[body..body + types_size]
+			.chunks(4)
+			.map(|t| CodeType {
+				inputs: t[0],
+				outputs: t[1],
+				max_stack_height: u16::from_be_bytes([t[2], t[3]]),
+			})
+			.collect::<Vec<_>>();
+		// The first section is entered without `CALLF`, so it cannot take
+		// or return anything.
+		if types[0].inputs != 0 || types[0].outputs != 0 {
+			return Err(EofError::InvalidTypes);
+		}
+		if types
+			.iter()
+			.any(|t| t.inputs > 0x7f || t.outputs > 0x7f || t.max_stack_height > 0x3ff)
+		{
+			return Err(EofError::InvalidTypes);
+		}
+
+		let mut start = body + types_size;
+		let code_sections = code_sizes
+			.into_iter()
+			.map(|size| {
+				start += size;
+				start - size..start
+			})
+			.collect();
+
+		Ok(Self {
+			types,
+			code_sections,
+			data: start..start + data_size,
+		})
+	}
+
+	fn validate_code(&self, code: &[u8], index: usize) -> Result<(), EofError> {
+		let code = &code[self.code_sections[index].clone()];
+
+		// Positions that are immediates rather than instructions.
+		let mut immediates = Vec::new();
+		immediates.resize(code.len(), false);
+		let mut jumps = Vec::new();
+		let mut last = Opcode::STOP;
+		let mut i = 0;
+		while i < code.len() {
+			let opcode = Opcode(code[i]);
+			if !is_defined(opcode) {
+				return Err(EofError::UndefinedOpcode(opcode));
+			}
+
+			let immediate_size = match opcode {
+				Opcode::RJUMP | Opcode::RJUMPI | Opcode::CALLF => 2,
+				Opcode::RJUMPV => match code.get(i + 1) {
+					Some(max_index) => 1 + (*max_index as usize + 1) * 2,
+					None => return Err(EofError::TruncatedImmediate),
+				},
+				_ => opcode.is_push().unwrap_or(0) as usize,
+			};
+			let next = i + 1 + immediate_size;
+			if next > code.len() {
+				return Err(EofError::TruncatedImmediate);
+			}
+			for immediate in &mut immediates[i + 1..next] {
+				*immediate = true;
+			}
+
+			match opcode {
+				Opcode::RJUMP | Opcode::RJUMPI => jumps.push(next as isize + read_i16(code, i + 1)),
+				Opcode::RJUMPV => jumps.extend(
+					(i + 2..next)
+						.step_by(2)
+						.map(|j| next as isize + read_i16(code, j)),
+				),
+				Opcode::CALLF if read_u16(code, i + 1) >= self.code_sections.len() => {
+					return Err(EofError::InvalidSectionIndex);
+				}
+				_ => (),
+			}
+
+			last = opcode;
+			i = next;
+		}
+
+		if !matches!(
+			last,
+			Opcode::STOP
+				| Opcode::RETURN
+				| Opcode::REVERT
+				| Opcode::INVALID
+				| Opcode::RETF
+				| Opcode::RJUMP
+		) {
+			return Err(EofError::MissingTerminator);
+		}
+
+		let is_instruction = |target: isize| {
+			target >= 0 && (target as usize) < code.len() && !immediates[target as usize]
+		};
+		if !jumps.into_iter().all(is_instruction) {
+			return Err(EofError::InvalidJumpDestination);
+		}
+
+		Ok(())
+	}
+}
+
+/// Reads the container header.
+struct Reader<'a> {
+	code: &'a [u8],
+	position: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn u8(&mut self) -> Result<u8, EofError> {
+		let value = *self
+			.code
+			.get(self.position)
+			.ok_or(EofError::InvalidHeader)?;
+		self.position += 1;
+		Ok(value)
+	}
+
+	fn u16(&mut self) -> Result<usize, EofError> {
+		Ok(((self.u8()? as usize) << 8) | self.u8()? as usize)
+	}
+
+	fn kind(&mut self, kind: u8) -> Result<(), EofError> {
+		if self.u8()? == kind {
+			Ok(())
+		} else {
+			Err(EofError::InvalidHeader)
+		}
+	}
+}
+
+/// Read a big-endian `u16` immediate at `position`.
+pub(crate) fn read_u16(code: &[u8], position: usize) -> usize {
+	((code[position] as usize) << 8) | code[position + 1] as usize
+}
+
+/// Read a big-endian `i16` immediate at `position`, the offset of a relative
+/// jump from the end of its instruction.
+pub(crate) fn read_i16(code: &[u8], position: usize) -> isize {
+	i16::from_be_bytes([code[position], code[position + 1]]) as isize
+}
+
+/// Whether the opcode may appear in EOF code. `JUMP`, `JUMPI` and `PC` are
+/// replaced by relative jumps, and `CALLCODE` and `SUICIDE` are deprecated.
+fn is_defined(opcode: Opcode) -> bool {
+	opcode.is_defined()
+		&& !matches!(
+			opcode,
+			Opcode::JUMP | Opcode::JUMPI | Opcode::PC | Opcode::CALLCODE | Opcode::SUICIDE
+		)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Eof, EofError};
+	use crate::{Capture, ExitSucceed, Machine, Opcode};
+	use alloc::rc::Rc;
+	use alloc::vec::Vec;
+
+	/// Container with a single code section and no data.
+	fn container(code: &str) -> Vec<u8> {
+		let code = hex::decode(code).unwrap();
+		let mut container = hex::decode("ef000101000402000100").unwrap();
+		container.push(code.len() as u8);
+		container.extend(hex::decode("03000000").unwrap());
+		container.extend(hex::decode("00000000").unwrap());
+		container.extend(code);
+		container
+	}
+
+	#[test]
+	fn test_validate_code() {
+		assert!(Eof::new(&container("00")).is_ok());
+		// RJUMP back onto itself.
+		assert!(Eof::new(&container("e0fffd")).is_ok());
+		// RJUMPV with two cases, the second into the immediate of PUSH1.
+		assert_eq!(
+			Eof::new(&container("6000e201fffe000100")),
+			Err(EofError::InvalidJumpDestination)
+		);
+		assert_eq!(
+			Eof::new(&container("6001")),
+			Err(EofError::MissingTerminator)
+		);
+		assert_eq!(
+			Eof::new(&container("00e1")),
+			Err(EofError::TruncatedImmediate)
+		);
+		assert_eq!(
+			Eof::new(&container("600056")),
+			Err(EofError::UndefinedOpcode(Opcode::JUMP))
+		);
+		assert_eq!(
+			Eof::new(&container("e3000100")),
+			Err(EofError::InvalidSectionIndex)
+		);
+		assert_eq!(Eof::new(&container("00")[..19]), Err(EofError::InvalidSize));
+	}
+
+	#[test]
+	fn test_callf() {
+		// Section 0 calls section 1 with 5 and returns the result. Section 1
+		// doubles its input after skipping an `INVALID` with `RJUMPI`.
+		let code = hex::decode(concat!(
+			"ef0001010008020002000d0008030000",
+			"00",
+			"00000002",
+			"01010002",
+			"6005e3000160005260206000f3",
+			"80e10001fe8001e4",
+		))
+		.unwrap();
+		let eof = Eof::new(&code).unwrap();
+		assert_eq!(eof.code_sections(), 2);
+
+		let mut vm = Machine::new_eof(Rc::new(code), eof, Rc::new(Vec::new()), 1024, 10000);
+		assert_eq!(vm.run(), Capture::Exit(ExitSucceed::Returned.into()));
+		let mut ret = [0u8; 32];
+		ret[31] = 10;
+		assert_eq!(vm.return_value(), ret);
+	}
+}
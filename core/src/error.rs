@@ -128,6 +128,8 @@ pub enum ExitError {
 	CreateContractLimit,
 	/// Starting byte must not begin with 0xef. See [EIP-3541](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3541.md).
 	InvalidCode,
+	/// Code starting with the EOF magic is not a valid container. See [EIP-3540](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3540.md).
+	InvalidEof,
 
 	/// An opcode accesses external information, but the request is off offset
 	/// limit (runtime).
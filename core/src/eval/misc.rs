@@ -1,6 +1,7 @@
 use super::Control;
-use crate::{ExitError, ExitFatal, ExitRevert, ExitSucceed, Machine};
-use core::cmp::min;
+use crate::eof::{read_i16, read_u16};
+use crate::{ExitError, ExitFatal, ExitRevert, ExitSucceed, Machine, Opcode, ReturnFrame};
+use core::cmp::{max, min};
 use primitive_types::{H256, U256};
 
 #[inline]
@@ -70,6 +71,24 @@ pub fn calldatacopy(state: &mut Machine) -> Control {
 	}
 }
 
+#[inline]
+pub fn mcopy(state: &mut Machine) -> Control {
+	pop_u256!(state, dst, src, len);
+
+	try_or_fail!(state.memory.resize_offset(max(dst, src), len));
+	if len == U256::zero() {
+		return Control::Continue(1);
+	}
+
+	let dst = as_usize_or_fail!(dst);
+	let src = as_usize_or_fail!(src);
+	let len = as_usize_or_fail!(len);
+	match state.memory.copy(dst, src, len) {
+		Ok(()) => Control::Continue(1),
+		Err(e) => Control::Exit(e.into()),
+	}
+}
+
 #[inline]
 pub fn pop(state: &mut Machine) -> Control {
 	pop_u256!(state, _val);
@@ -151,6 +170,12 @@ pub fn msize(state: &mut Machine) -> Control {
 	Control::Continue(1)
 }
 
+#[inline]
+pub fn push0(state: &mut Machine) -> Control {
+	push_u256!(state, U256::zero());
+	Control::Continue(1)
+}
+
 #[inline]
 pub fn push(state: &mut Machine, n: usize, position: usize) -> Control {
 	let end = min(position + 1 + n, state.code.len());
@@ -226,3 +251,111 @@ pub fn revert(state: &mut Machine) -> Control {
 	state.return_range = start..(start + len);
 	Control::Exit(ExitRevert::Reverted.into())
 }
+
+/// Maximum number of EOF code sections that can be called at once.
+const RETURN_STACK_LIMIT: usize = 1024;
+
+/// Leave an opcode that is only defined in EOF code to the runtime, like any
+/// other unknown opcode.
+#[inline]
+fn legacy(state: &mut Machine, opcode: Opcode, position: usize) -> Control {
+	state.position = Ok(position + 1);
+	Control::Trap(opcode)
+}
+
+#[inline]
+pub fn rjump(state: &mut Machine, position: usize) -> Control {
+	if state.eof.is_none() {
+		return legacy(state, Opcode::RJUMP, position);
+	}
+
+	let offset = read_i16(&state.code, position + 1);
+	Control::Jump((position as isize + 3 + offset) as usize)
+}
+
+#[inline]
+pub fn rjumpi(state: &mut Machine, position: usize) -> Control {
+	if state.eof.is_none() {
+		return legacy(state, Opcode::RJUMPI, position);
+	}
+
+	pop_u256!(state, value);
+	if value != U256::zero() {
+		let offset = read_i16(&state.code, position + 1);
+		Control::Jump((position as isize + 3 + offset) as usize)
+	} else {
+		Control::Continue(3)
+	}
+}
+
+#[inline]
+pub fn rjumpv(state: &mut Machine, position: usize) -> Control {
+	if state.eof.is_none() {
+		return legacy(state, Opcode::RJUMPV, position);
+	}
+
+	pop_u256!(state, case);
+	let max_index = state.code[position + 1] as usize;
+	let size = 2 + (max_index + 1) * 2;
+	if case <= U256::from(max_index) {
+		let offset = read_i16(&state.code, position + 2 + case.as_usize() * 2);
+		Control::Jump((position as isize + size as isize + offset) as usize)
+	} else {
+		Control::Continue(size)
+	}
+}
+
+#[inline]
+pub fn callf(state: &mut Machine, position: usize) -> Control {
+	let eof = match &state.eof {
+		Some(eof) => eof,
+		None => return legacy(state, Opcode::CALLF, position),
+	};
+
+	// Validation guarantees that the section exists.
+	let index = read_u16(&state.code, position + 1);
+	let start = eof.code_section(index).expect("validated").start;
+	let inputs = eof.code_type(index).expect("validated").inputs as usize;
+
+	if state.return_stack.len() >= RETURN_STACK_LIMIT {
+		return Control::Exit(ExitError::CallTooDeep.into());
+	}
+	if state.stack.len() < inputs {
+		return Control::Exit(ExitError::StackUnderflow.into());
+	}
+
+	state.return_stack.push(ReturnFrame {
+		section: state.section,
+		position: position + 3,
+		stack_base: state.stack.len() - inputs,
+	});
+	state.section = index;
+	Control::Jump(start)
+}
+
+#[inline]
+pub fn retf(state: &mut Machine, position: usize) -> Control {
+	let eof = match &state.eof {
+		Some(eof) => eof,
+		None => return legacy(state, Opcode::RETF, position),
+	};
+
+	let outputs = eof.code_type(state.section).expect("validated").outputs as usize;
+	// Returning from the first code section ends execution like `STOP`.
+	let frame = match state.return_stack.pop() {
+		Some(frame) => frame,
+		None => return Control::Exit(ExitSucceed::Stopped.into()),
+	};
+
+	// The callee must leave exactly its outputs above the stack of the caller.
+	let height = frame.stack_base + outputs;
+	if state.stack.len() < height {
+		return Control::Exit(ExitError::StackUnderflow.into());
+	}
+	if state.stack.len() > height {
+		return Control::Exit(ExitError::StackOverflow.into());
+	}
+
+	state.section = frame.section;
+	Control::Jump(frame.position)
+}
@@ -83,6 +83,7 @@ fn eval_table<H: InterpreterHandler>(
 		table_elem!(POP, state, self::misc::pop(state));
 		table_elem!(PC, state, position, self::misc::pc(state, position));
 		table_elem!(MSIZE, state, self::misc::msize(state));
+		table_elem!(PUSH0, state, self::misc::push0(state));
 		table_elem!(PUSH1, state, position, self::misc::push1(state, position));
 		table_elem!(PUSH2, state, position, self::misc::push2(state, position));
 		table_elem!(PUSH3, state, position, self::misc::push(state, 3, position));
@@ -242,6 +243,7 @@ fn eval_table<H: InterpreterHandler>(
 		table_elem!(MSTORE8, state, self::misc::mstore8(state));
 		table_elem!(CODECOPY, state, self::misc::codecopy(state));
 		table_elem!(CALLDATACOPY, state, self::misc::calldatacopy(state));
+		table_elem!(MCOPY, state, self::misc::mcopy(state));
 		table_elem!(DUP1, state, self::misc::dup(state, 1));
 		table_elem!(DUP2, state, self::misc::dup(state, 2));
 		table_elem!(DUP3, state, self::misc::dup(state, 3));
@@ -281,6 +283,11 @@ fn eval_table<H: InterpreterHandler>(
 		table_elem!(JUMPDEST, Control::Continue(1));
 		table_elem!(JUMP, state, self::misc::jump(state));
 		table_elem!(JUMPI, state, self::misc::jumpi(state));
+		table_elem!(RJUMP, state, position, self::misc::rjump(state, position));
+		table_elem!(RJUMPI, state, position, self::misc::rjumpi(state, position));
+		table_elem!(RJUMPV, state, position, self::misc::rjumpv(state, position));
+		table_elem!(CALLF, state, position, self::misc::callf(state, position));
+		table_elem!(RETF, state, position, self::misc::retf(state, position));
 		table
 	};
 	let mut pc = position;
@@ -300,6 +307,11 @@ fn eval_table<H: InterpreterHandler>(
 				return Control::Exit(ExitReason::Error(e));
 			}
 		};
+		if state.disabled.contains(op) {
+			state.position = Ok(pc + 1);
+			handler.after_eval();
+			return Control::Trap(op);
+		}
 		let control = TABLE[op.as_usize()](state, op, pc);
 
 		#[cfg(feature = "tracing")]
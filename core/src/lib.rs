@@ -7,6 +7,7 @@
 extern crate alloc;
 extern crate core;
 
+mod eof;
 mod error;
 mod eval;
 mod memory;
@@ -15,9 +16,10 @@ mod stack;
 mod utils;
 mod valids;
 
+pub use crate::eof::{CodeType, Eof, EofError};
 pub use crate::error::{Capture, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed, Trap};
 pub use crate::memory::Memory;
-pub use crate::opcode::Opcode;
+pub use crate::opcode::{Opcode, OpcodeSet};
 pub use crate::stack::Stack;
 pub use crate::valids::Valids;
 
@@ -38,11 +40,31 @@ pub struct Machine {
 	/// Return value.
 	return_range: Range<U256>,
 	/// Code validity maps.
-	valids: Valids,
+	valids: Rc<Valids>,
 	/// Memory.
 	memory: Memory,
 	/// Stack.
 	stack: Stack,
+	/// Container layout, if the code is EOF.
+	eof: Option<Eof>,
+	/// Index of the EOF code section being executed.
+	section: usize,
+	/// Frames of the EOF code sections called with `CALLF`.
+	return_stack: Vec<ReturnFrame>,
+	/// Opcodes left to the runtime like unknown ones, because the fork lacks
+	/// them.
+	disabled: OpcodeSet,
+}
+
+/// Where to continue after `RETF`.
+#[derive(Clone, Copy, Debug)]
+struct ReturnFrame {
+	/// Code section of the caller.
+	section: usize,
+	/// Position of the caller after `CALLF`.
+	position: usize,
+	/// Stack height of the caller below the inputs of the callee.
+	stack_base: usize,
 }
 
 /// EVM interpreter handler.
@@ -85,6 +107,18 @@ impl Machine {
 		&self.position
 	}
 
+	/// Container layout of the code, if it is EOF.
+	pub fn eof(&self) -> Option<&Eof> {
+		self.eof.as_ref()
+	}
+
+	/// Leave `disabled` to the runtime instead of evaluating them, so that
+	/// the machine only runs the opcodes of a fork. All opcodes are enabled by
+	/// default.
+	pub fn set_disabled(&mut self, disabled: OpcodeSet) {
+		self.disabled = disabled;
+	}
+
 	/// Create a new machine with given code and data.
 	pub fn new(
 		code: Rc<Vec<u8>>,
@@ -92,8 +126,21 @@ impl Machine {
 		stack_limit: usize,
 		memory_limit: usize,
 	) -> Self {
-		let valids = Valids::new(&code[..]);
+		let valids = Rc::new(Valids::new(&code[..]));
+
+		Self::new_with_valids(code, valids, data, stack_limit, memory_limit)
+	}
 
+	/// Create a new machine with given code, its jump destinations analysed
+	/// beforehand as `valids`, and data. This lets frames running the same
+	/// code share a single analysis.
+	pub fn new_with_valids(
+		code: Rc<Vec<u8>>,
+		valids: Rc<Valids>,
+		data: Rc<Vec<u8>>,
+		stack_limit: usize,
+		memory_limit: usize,
+	) -> Self {
 		Self {
 			data,
 			code,
@@ -102,6 +149,37 @@ impl Machine {
 			valids,
 			memory: Memory::new(memory_limit),
 			stack: Stack::new(stack_limit),
+			eof: None,
+			section: 0,
+			return_stack: Vec::new(),
+			disabled: OpcodeSet::new(),
+		}
+	}
+
+	/// Create a new machine with given EOF code, validated as `eof`, and data.
+	/// Execution starts at the first code section, and there are no jump
+	/// destinations to analyse since EOF code only has relative jumps.
+	pub fn new_eof(
+		code: Rc<Vec<u8>>,
+		eof: Eof,
+		data: Rc<Vec<u8>>,
+		stack_limit: usize,
+		memory_limit: usize,
+	) -> Self {
+		let position = eof.code_section(0).map_or(0, |section| section.start);
+
+		Self {
+			data,
+			code,
+			position: Ok(position),
+			return_range: U256::zero()..U256::zero(),
+			valids: Rc::new(Valids::new(&[])),
+			memory: Memory::new(memory_limit),
+			stack: Stack::new(stack_limit),
+			eof: Some(eof),
+			section: 0,
+			return_stack: Vec::new(),
+			disabled: OpcodeSet::new(),
 		}
 	}
 
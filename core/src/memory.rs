@@ -150,6 +150,13 @@ impl Memory {
 		Ok(())
 	}
 
+	/// Copy `len` bytes of the memory from `src` to `dst`, where the regions
+	/// may overlap. The offsets are considered trusted, as in `get`.
+	pub fn copy(&mut self, dst: usize, src: usize, len: usize) -> Result<(), ExitFatal> {
+		let value = self.get(src, len);
+		self.set(dst, &value, Some(len))
+	}
+
 	/// Copy `data` into the memory, of given `len`.
 	pub fn copy_large(
 		&mut self,
@@ -88,6 +88,11 @@ impl Opcode {
 	pub const MSIZE: Opcode = Opcode(0x59);
 	/// `JUMPDEST`
 	pub const JUMPDEST: Opcode = Opcode(0x5b);
+	/// `MCOPY`
+	pub const MCOPY: Opcode = Opcode(0x5e);
+
+	/// `PUSH0`
+	pub const PUSH0: Opcode = Opcode(0x5f);
 
 	/// `PUSHn`
 	pub const PUSH1: Opcode = Opcode(0x60);
@@ -166,6 +171,17 @@ impl Opcode {
 
 	/// `INVALID`
 	pub const INVALID: Opcode = Opcode(0xfe);
+
+	/// `RJUMP`
+	pub const RJUMP: Opcode = Opcode(0xe0);
+	/// `RJUMPI`
+	pub const RJUMPI: Opcode = Opcode(0xe1);
+	/// `RJUMPV`
+	pub const RJUMPV: Opcode = Opcode(0xe2);
+	/// `CALLF`
+	pub const CALLF: Opcode = Opcode(0xe3);
+	/// `RETF`
+	pub const RETF: Opcode = Opcode(0xe4);
 }
 
 // External opcodes
@@ -180,6 +196,10 @@ impl Opcode {
 	pub const SELFBALANCE: Opcode = Opcode(0x47);
 	/// `BASEFEE`
 	pub const BASEFEE: Opcode = Opcode(0x48);
+	/// `BLOBHASH`
+	pub const BLOBHASH: Opcode = Opcode(0x49);
+	/// `BLOBBASEFEE`
+	pub const BLOBBASEFEE: Opcode = Opcode(0x4a);
 	/// `ORIGIN`
 	pub const ORIGIN: Opcode = Opcode(0x32);
 	/// `CALLER`
@@ -208,12 +228,18 @@ impl Opcode {
 	pub const NUMBER: Opcode = Opcode(0x43);
 	/// `DIFFICULTY`
 	pub const DIFFICULTY: Opcode = Opcode(0x44);
+	/// `PREVRANDAO`, which replaces `DIFFICULTY` after the merge
+	pub const PREVRANDAO: Opcode = Opcode(0x44);
 	/// `GASLIMIT`
 	pub const GASLIMIT: Opcode = Opcode(0x45);
 	/// `SLOAD`
 	pub const SLOAD: Opcode = Opcode(0x54);
 	/// `SSTORE`
 	pub const SSTORE: Opcode = Opcode(0x55);
+	/// `TLOAD`
+	pub const TLOAD: Opcode = Opcode(0x5c);
+	/// `TSTORE`
+	pub const TSTORE: Opcode = Opcode(0x5d);
 	/// `GAS`
 	pub const GAS: Opcode = Opcode(0x5a);
 	/// `LOGn`
@@ -251,6 +277,22 @@ impl Opcode {
 		}
 	}
 
+	/// Whether any fork defines the opcode, in legacy or EOF code.
+	pub const fn is_defined(&self) -> bool {
+		matches!(
+			self.0,
+			0x00..=0x0b
+				| 0x10..=0x1d
+				| 0x20
+				| 0x30..=0x4a
+				| 0x50..=0xa4
+				| 0xe0..=0xe4
+				| 0xf0..=0xf5
+				| 0xfa | 0xfd
+				| 0xfe | 0xff
+		)
+	}
+
 	#[inline]
 	pub const fn as_u8(&self) -> u8 {
 		self.0
@@ -261,3 +303,58 @@ impl Opcode {
 		self.0 as usize
 	}
 }
+
+/// A set of opcodes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpcodeSet([u64; 4]);
+
+impl OpcodeSet {
+	/// The empty set.
+	pub const fn new() -> Self {
+		Self([0; 4])
+	}
+
+	/// Add an opcode to the set.
+	pub fn insert(&mut self, opcode: Opcode) {
+		self.0[opcode.as_usize() / 64] |= 1 << (opcode.as_usize() % 64);
+	}
+
+	/// Whether the set contains the opcode.
+	#[inline]
+	pub const fn contains(&self, opcode: Opcode) -> bool {
+		self.0[opcode.as_usize() / 64] & (1 << (opcode.as_usize() % 64)) != 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Opcode, OpcodeSet};
+	use crate::{Capture, ExitSucceed, Machine};
+	use alloc::rc::Rc;
+	use alloc::vec::Vec;
+
+	#[test]
+	fn test_disabled_opcodes() {
+		// PUSH0, PUSH0, MCOPY, STOP
+		let code = Rc::new(vec![0x5f, 0x5f, 0x5f, 0x5e, 0x00]);
+		let mut machine = Machine::new(code.clone(), Rc::new(Vec::new()), 1024, usize::MAX);
+		assert_eq!(machine.run(), Capture::Exit(ExitSucceed::Stopped.into()));
+
+		let mut disabled = OpcodeSet::new();
+		disabled.insert(Opcode::MCOPY);
+		assert!(disabled.contains(Opcode::MCOPY));
+		assert!(!disabled.contains(Opcode::PUSH0));
+		let mut machine = Machine::new(code, Rc::new(Vec::new()), 1024, usize::MAX);
+		machine.set_disabled(disabled);
+		assert_eq!(machine.run(), Capture::Trap(Opcode::MCOPY));
+		assert_eq!(machine.stack().len(), 3);
+	}
+
+	#[test]
+	fn test_is_defined() {
+		assert!(Opcode::PUSH0.is_defined());
+		assert!(Opcode::RJUMP.is_defined());
+		assert!(!Opcode(0x0c).is_defined());
+		assert!(!Opcode(0xef).is_defined());
+	}
+}
@@ -1,21 +1,24 @@
 use crate::Opcode;
+use alloc::vec;
 use alloc::vec::Vec;
 
-/// Mapping of valid jump destination from code.
+/// Mapping of valid jump destination from code, one bit per code byte.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Valids(Vec<bool>);
+pub struct Valids {
+	bits: Vec<u64>,
+	len: usize,
+}
 
 impl Valids {
 	/// Create a new valid mapping from given code bytes.
 	pub fn new(code: &[u8]) -> Self {
-		let mut valids: Vec<bool> = Vec::with_capacity(code.len());
-		valids.resize(code.len(), false);
+		let mut bits = vec![0u64; code.len() / 64 + 1];
 
 		let mut i = 0;
 		while i < code.len() {
 			let opcode = Opcode(code[i]);
 			if opcode == Opcode::JUMPDEST {
-				valids[i] = true;
+				bits[i / 64] |= 1 << (i % 64);
 				i += 1;
 			} else if let Some(v) = opcode.is_push() {
 				i += v as usize + 1;
@@ -24,14 +27,17 @@ impl Valids {
 			}
 		}
 
-		Valids(valids)
+		Valids {
+			bits,
+			len: code.len(),
+		}
 	}
 
 	/// Get the length of the valid mapping. This is the same as the
 	/// code bytes.
 	#[inline]
 	pub fn len(&self) -> usize {
-		self.0.len()
+		self.len
 	}
 
 	/// Returns true if the valids list is empty
@@ -43,14 +49,30 @@ impl Valids {
 	/// Returns `true` if the position is a valid jump destination. If
 	/// not, returns `false`.
 	pub fn is_valid(&self, position: usize) -> bool {
-		if position >= self.0.len() {
+		if position >= self.len {
 			return false;
 		}
 
-		if !self.0[position] {
-			return false;
-		}
+		self.bits[position / 64] & (1 << (position % 64)) != 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_valids() {
+		// JUMPDEST, PUSH1 0x5b, JUMPDEST, then JUMPDEST at 64 and 129.
+		let mut code = vec![0x5b, 0x60, 0x5b, 0x5b];
+		code.resize(130, 0x00);
+		code[64] = 0x5b;
+		code[129] = 0x5b;
 
-		true
+		let valids = Valids::new(&code);
+		assert_eq!(valids.len(), 130);
+		let positions = (0..140).filter(|i| valids.is_valid(*i)).collect::<Vec<_>>();
+		assert_eq!(positions, vec![0, 3, 64, 129]);
+		assert!(Valids::new(&[]).is_empty());
 	}
 }
@@ -0,0 +1,296 @@
+use evm::backend::{Log, MemoryAccount};
+use evm::{Config, ExitReason};
+use evm_fuzzer::{execute, normalize, Case, MAX_GAS_LIMIT};
+use evm_runtime::tracing::{Event, EventListener};
+use primitive_types::{H160, H256, U256};
+use revm::db::InMemoryDB;
+use revm::interpreter::Interpreter;
+use revm::primitives::{self, AccountInfo, Address, Bytecode, ExecutionResult, SpecId, TransactTo};
+use revm::{inspector_handle_register, Database, Evm, EvmContext, Inspector};
+use std::collections::BTreeMap;
+
+/// An executed instruction: context address, program counter and opcode.
+type Step = (H160, usize, u8);
+
+/// Records the instructions of either implementation. `STOP` is left out,
+/// as revm also steps the implicit `STOP` past the end of the code.
+#[derive(Default)]
+struct Tracer {
+	steps: Vec<Step>,
+}
+
+impl Tracer {
+	fn push(&mut self, step: Step) {
+		if step.2 != 0x00 {
+			self.steps.push(step);
+		}
+	}
+}
+
+impl EventListener for Tracer {
+	fn event(&mut self, event: Event) {
+		if let Event::Step {
+			address,
+			opcode,
+			position: Ok(position),
+			..
+		} = event
+		{
+			self.push((address, *position, opcode.as_u8()));
+		}
+	}
+}
+
+impl<DB: Database> Inspector<DB> for Tracer {
+	fn step(&mut self, interp: &mut Interpreter, _: &mut EvmContext<DB>) {
+		self.push((
+			h160(interp.contract.address),
+			interp.program_counter(),
+			interp.current_opcode(),
+		));
+	}
+}
+
+fn h160(address: Address) -> H160 {
+	H160::from_slice(address.as_slice())
+}
+
+fn address(address: H160) -> Address {
+	Address::from_slice(address.as_bytes())
+}
+
+fn from_revm(value: primitives::U256) -> U256 {
+	U256::from_big_endian(&value.to_be_bytes::<32>())
+}
+
+fn to_revm(value: U256) -> primitives::U256 {
+	let mut bytes = [0u8; 32];
+	value.to_big_endian(&mut bytes);
+	primitives::U256::from_be_bytes(bytes)
+}
+
+fn h256_to_revm(value: H256) -> primitives::U256 {
+	primitives::U256::from_be_bytes(value.to_fixed_bytes())
+}
+
+fn h256_from_revm(value: primitives::U256) -> H256 {
+	H256::from(value.to_be_bytes::<32>())
+}
+
+/// Result of running a case through revm: `None` if it rejected the
+/// transaction.
+struct Reference {
+	result: Option<ExecutionResult>,
+	state: BTreeMap<H160, MemoryAccount>,
+	steps: Vec<Step>,
+}
+
+fn run_revm(case: &Case) -> Reference {
+	let mut db = InMemoryDB::default();
+	for (&account_address, account) in &case.state {
+		let code = Bytecode::new_raw(account.code.clone().into());
+		let info = AccountInfo::new(
+			to_revm(account.balance),
+			account.nonce.as_u64(),
+			code.hash_slow(),
+			code,
+		);
+		db.insert_account_info(address(account_address), info);
+		for (&index, &value) in &account.storage {
+			db.insert_account_storage(
+				address(account_address),
+				h256_to_revm(index),
+				h256_to_revm(value),
+			)
+			.expect("in memory database is infallible");
+		}
+	}
+
+	let mut tracer = Tracer::default();
+	let mut evm = Evm::builder()
+		.with_db(db)
+		.with_external_context(&mut tracer)
+		.with_spec_id(SpecId::LONDON)
+		.modify_block_env(|block| {
+			block.number = primitives::U256::from(1);
+			block.timestamp = primitives::U256::from(1);
+			block.gas_limit = primitives::U256::from(MAX_GAS_LIMIT);
+			block.basefee = primitives::U256::ZERO;
+			block.difficulty = primitives::U256::ZERO;
+		})
+		.modify_tx_env(|tx| {
+			tx.caller = address(case.caller);
+			tx.transact_to = TransactTo::Call(address(case.target));
+			tx.value = to_revm(case.value);
+			tx.data = case.data.clone().into();
+			tx.gas_limit = case.gas_limit;
+			tx.gas_price = primitives::U256::ZERO;
+		})
+		.append_handler_register(inspector_handle_register)
+		.build();
+	let result = evm.transact_commit().ok();
+
+	let db = evm.db();
+	let state = db
+		.accounts
+		.iter()
+		.map(|(&account_address, account)| {
+			let code = account
+				.info
+				.code
+				.clone()
+				.or_else(|| db.contracts.get(&account.info.code_hash).cloned())
+				.map(|code| code.original_bytes().to_vec())
+				.unwrap_or_default();
+			let account = MemoryAccount {
+				nonce: account.info.nonce.into(),
+				balance: from_revm(account.info.balance),
+				storage: account
+					.storage
+					.iter()
+					.map(|(&index, &value)| (h256_from_revm(index), h256_from_revm(value)))
+					.collect(),
+				code,
+			};
+			(h160(account_address), account)
+		})
+		.collect();
+	drop(evm);
+
+	Reference {
+		result,
+		state: normalize(&state),
+		steps: tracer.steps,
+	}
+}
+
+fn revm_logs(result: &ExecutionResult) -> Vec<Log> {
+	result
+		.logs()
+		.iter()
+		.map(|log| Log {
+			address: h160(log.address),
+			topics: log
+				.topics()
+				.iter()
+				.map(|topic| H256::from_slice(topic.as_slice()))
+				.collect(),
+			data: log.data.data.to_vec(),
+		})
+		.collect()
+}
+
+/// The first difference between the outcomes, if any.
+fn compare(
+	case: &Case,
+	config: &Config,
+	ours: &evm_fuzzer::Outcome,
+	theirs: &Reference,
+) -> Result<(), String> {
+	let result = match (&theirs.result, case.pays_intrinsic_gas(config)) {
+		(Some(result), true) => result,
+		(None, false) => return Ok(()),
+		(Some(_), false) => return Err("revm executed a call without its intrinsic gas".into()),
+		(None, true) => return Err("revm rejected the call".into()),
+	};
+
+	let same_kind = match (&ours.reason, result) {
+		(ExitReason::Succeed(_), ExecutionResult::Success { .. }) => true,
+		(ExitReason::Revert(_), ExecutionResult::Revert { .. }) => true,
+		(ExitReason::Error(_), ExecutionResult::Halt { .. }) => true,
+		_ => false,
+	};
+	if !same_kind {
+		return Err(format!("exit reason {:?}, revm {:?}", ours.reason, result));
+	}
+	let output = match result {
+		ExecutionResult::Success { output, .. } => output.data().to_vec(),
+		ExecutionResult::Revert { output, .. } => output.to_vec(),
+		ExecutionResult::Halt { .. } => Vec::new(),
+	};
+	if ours.output != output {
+		return Err(format!("output {:?}, revm {:?}", ours.output, output));
+	}
+	if ours.used_gas != result.gas_used() {
+		return Err(format!(
+			"used gas {}, revm {}",
+			ours.used_gas,
+			result.gas_used()
+		));
+	}
+	let logs = revm_logs(result);
+	if ours.logs != logs {
+		return Err(format!("logs {:?}, revm {:?}", ours.logs, logs));
+	}
+	if ours.state != theirs.state {
+		return Err(format!("state {:?}, revm {:?}", ours.state, theirs.state));
+	}
+	Ok(())
+}
+
+fn handle_data(sequence: &[u8]) {
+	let config = Config::london();
+	let case = Case::decode(sequence);
+
+	let mut tracer = Tracer::default();
+	let ours = evm_runtime::tracing::using(&mut tracer, || execute(&case, &config));
+	let theirs = run_revm(&case);
+	#[cfg(not(fuzzing))]
+	{
+		println!(
+			"Result: {:?}, used gas: {}, revm: {:?}",
+			ours.reason,
+			ours.used_gas,
+			theirs.result.as_ref().map(ExecutionResult::gas_used)
+		);
+	}
+
+	if let Err(difference) = compare(&case, &config, &ours, &theirs) {
+		let index = tracer
+			.steps
+			.iter()
+			.zip(&theirs.steps)
+			.position(|(ours, theirs)| ours != theirs)
+			.unwrap_or_else(|| tracer.steps.len().min(theirs.steps.len()));
+		let divergence = match (tracer.steps.get(index), theirs.steps.get(index)) {
+			(None, None) => "same instructions executed".to_string(),
+			(ours, theirs) => format!(
+				"first diverging step #{}: ours {:?}, revm {:?}",
+				index, ours, theirs
+			),
+		};
+		panic!("{}\n{}\n{:?}", difference, divergence, case);
+	}
+}
+
+fn main() {
+	#[cfg(fuzzing)]
+	{
+		use honggfuzz::fuzz;
+
+		loop {
+			fuzz!(|data: &[u8]| {
+				handle_data(data);
+			});
+		}
+	}
+	#[cfg(not(fuzzing))]
+	{
+		use std::env;
+		use std::fs;
+
+		let args: Vec<_> = env::args().collect();
+		let md = fs::metadata(&args[1]).unwrap();
+		let all_files = match md.is_dir() {
+			true => fs::read_dir(&args[1])
+				.unwrap()
+				.map(|x| x.unwrap().path().to_str().unwrap().to_string())
+				.collect::<Vec<String>>(),
+			false => args[1..].to_vec(),
+		};
+		for argument in all_files {
+			println!("Now doing file {:?}", argument);
+			handle_data(&fs::read(argument).unwrap());
+		}
+	}
+}
@@ -0,0 +1,113 @@
+use evm::backend::MemoryAccount;
+use evm::{Config, ExitReason};
+use evm_fuzzer::{execute, normalize, Case};
+use primitive_types::{H160, H256, U256};
+use std::collections::BTreeMap;
+
+fn total_balance(state: &BTreeMap<H160, MemoryAccount>) -> U256 {
+	state
+		.values()
+		.fold(U256::zero(), |total, account| total + account.balance)
+}
+
+fn handle_data(sequence: &[u8]) {
+	let config = Config::london();
+	let case = Case::decode(sequence);
+	let pre_state = normalize(&case.state);
+
+	let outcome = execute(&case, &config);
+	#[cfg(not(fuzzing))]
+	{
+		println!(
+			"Result: {:?}, used gas: {}",
+			outcome.reason, outcome.used_gas
+		);
+	}
+	assert!(
+		outcome.used_gas <= case.gas_limit,
+		"used {} gas of {}",
+		outcome.used_gas,
+		case.gas_limit
+	);
+	if let ExitReason::Fatal(_) = outcome.reason {
+		return;
+	}
+
+	assert!(total_balance(&outcome.state) <= total_balance(&pre_state));
+	if !outcome.reason.is_succeed() {
+		// Only the nonce of the caller changes, and only if the call ran.
+		let mut expected = pre_state;
+		if case.pays_intrinsic_gas(&config) {
+			if let Some(account) = expected.get_mut(&case.caller) {
+				account.nonce += U256::one();
+			}
+		}
+		assert_eq!(outcome.state, expected, "reverted call changed the state");
+		return;
+	}
+
+	// Slots keep the last value written by a committed frame, and writes of
+	// frames that reverted are not visible.
+	let storage = |address: &H160, index: &H256, state: &BTreeMap<H160, MemoryAccount>| {
+		state
+			.get(address)
+			.and_then(|account| account.storage.get(index))
+			.copied()
+			.unwrap_or_default()
+	};
+	let committed = &outcome.committed;
+	for ((address, index), value) in &committed.storage {
+		if !committed.reset.contains(address) {
+			assert_eq!(
+				storage(address, index, &outcome.state),
+				*value,
+				"committed write to {:?} {:?} lost",
+				address,
+				index
+			);
+		}
+	}
+	for key @ (address, index) in &outcome.reverted {
+		if !committed.storage.contains_key(key) && !committed.reset.contains(address) {
+			assert_eq!(
+				storage(address, index, &outcome.state),
+				storage(address, index, &pre_state),
+				"reverted write to {:?} {:?} visible",
+				address,
+				index
+			);
+		}
+	}
+}
+
+fn main() {
+	#[cfg(fuzzing)]
+	{
+		use honggfuzz::fuzz;
+
+		loop {
+			fuzz!(|data: &[u8]| {
+				handle_data(data);
+			});
+		}
+	}
+	#[cfg(not(fuzzing))]
+	{
+		use std::env;
+		use std::fs;
+
+		let args: Vec<_> = env::args().collect();
+		let md = fs::metadata(&args[1]).unwrap();
+		let all_files = match md.is_dir() {
+			true => fs::read_dir(&args[1])
+				.unwrap()
+				.map(|x| x.unwrap().path().to_str().unwrap().to_string())
+				.collect::<Vec<String>>(),
+			false => args[1..].to_vec(),
+		};
+		for argument in all_files {
+			println!("Now doing file {:?}", argument);
+			handle_data(&fs::read(argument).unwrap());
+		}
+	}
+}
@@ -0,0 +1,279 @@
+//! A [`StackState`] that records which storage writes survive the call frame
+//! that made them, so that targets can check reverted inner calls as well as
+//! the outermost one.
+
+use evm::backend::{Backend, Basic};
+use evm::executor::stack::{StackState, StackSubstateMetadata};
+use evm::{ExitError, Transfer};
+use primitive_types::{H160, H256, U256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Storage writes of a call frame and of the frames it committed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Writes {
+	/// Last value written to each slot.
+	pub storage: BTreeMap<(H160, H256), H256>,
+	/// Accounts whose storage was reset or which were deleted, so that their
+	/// slots may no longer hold the values written.
+	pub reset: BTreeSet<H160>,
+}
+
+impl Writes {
+	fn merge(&mut self, other: Writes) {
+		self.storage.extend(other.storage);
+		self.reset.extend(other.reset);
+	}
+}
+
+/// Wraps a [`StackState`], keeping the storage writes of each substate.
+pub struct Journal<S> {
+	state: S,
+	frames: Vec<Writes>,
+	reverted: BTreeSet<(H160, H256)>,
+}
+
+impl<S> Journal<S> {
+	pub fn new(state: S) -> Self {
+		Self {
+			state,
+			frames: vec![Writes::default()],
+			reverted: BTreeSet::new(),
+		}
+	}
+
+	/// The wrapped state, the writes committed to it and the slots written by
+	/// reverted or failed frames.
+	pub fn into_parts(mut self) -> (S, Writes, BTreeSet<(H160, H256)>) {
+		let committed = self.frames.swap_remove(0);
+		(self.state, committed, self.reverted)
+	}
+
+	fn frame(&mut self) -> &mut Writes {
+		self.frames
+			.last_mut()
+			.expect("the root frame is never exited")
+	}
+
+	fn discard(&mut self) {
+		if let Some(frame) = self.frames.pop() {
+			self.reverted.extend(frame.storage.into_keys());
+		}
+	}
+}
+
+impl<S: Backend> Backend for Journal<S> {
+	fn gas_price(&self) -> U256 {
+		self.state.gas_price()
+	}
+	fn origin(&self) -> H160 {
+		self.state.origin()
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		self.state.block_hash(number)
+	}
+	fn block_number(&self) -> U256 {
+		self.state.block_number()
+	}
+	fn block_coinbase(&self) -> H160 {
+		self.state.block_coinbase()
+	}
+	fn block_timestamp(&self) -> U256 {
+		self.state.block_timestamp()
+	}
+	fn block_difficulty(&self) -> U256 {
+		self.state.block_difficulty()
+	}
+	fn block_prev_randao(&self) -> H256 {
+		self.state.block_prev_randao()
+	}
+	fn block_gas_limit(&self) -> U256 {
+		self.state.block_gas_limit()
+	}
+	fn block_base_fee_per_gas(&self) -> U256 {
+		self.state.block_base_fee_per_gas()
+	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.state.block_blob_base_fee()
+	}
+	fn blob_hash(&self, index: usize) -> Option<H256> {
+		self.state.blob_hash(index)
+	}
+	fn chain_id(&self) -> U256 {
+		self.state.chain_id()
+	}
+	fn exists(&self, address: H160) -> bool {
+		self.state.exists(address)
+	}
+	fn basic(&self, address: H160) -> Basic {
+		self.state.basic(address)
+	}
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.state.code(address)
+	}
+	fn code_hash(&self, address: H160) -> H256 {
+		self.state.code_hash(address)
+	}
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.state.storage(address, index)
+	}
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.state.original_storage(address, index)
+	}
+}
+
+impl<'config, S: StackState<'config>> StackState<'config> for Journal<S> {
+	fn metadata(&self) -> &StackSubstateMetadata<'config> {
+		self.state.metadata()
+	}
+	fn metadata_mut(&mut self) -> &mut StackSubstateMetadata<'config> {
+		self.state.metadata_mut()
+	}
+
+	fn enter(&mut self, gas_limit: u64, is_static: bool) {
+		self.frames.push(Writes::default());
+		self.state.enter(gas_limit, is_static)
+	}
+	fn exit_commit(&mut self) -> Result<(), ExitError> {
+		if let Some(frame) = self.frames.pop() {
+			self.frame().merge(frame);
+		}
+		self.state.exit_commit()
+	}
+	fn exit_revert(&mut self) -> Result<(), ExitError> {
+		self.discard();
+		self.state.exit_revert()
+	}
+	fn exit_discard(&mut self) -> Result<(), ExitError> {
+		self.discard();
+		self.state.exit_discard()
+	}
+	fn clear_transaction(&mut self) {
+		self.state.clear_transaction()
+	}
+
+	fn is_empty(&self, address: H160) -> bool {
+		self.state.is_empty(address)
+	}
+	fn deleted(&self, address: H160) -> bool {
+		self.state.deleted(address)
+	}
+	fn created(&self, address: H160) -> bool {
+		self.state.created(address)
+	}
+	fn transient_storage(&self, address: H160, key: H256) -> H256 {
+		self.state.transient_storage(address, key)
+	}
+	fn is_cold(&self, address: H160) -> bool {
+		self.state.is_cold(address)
+	}
+	fn is_storage_cold(&self, address: H160, key: H256) -> bool {
+		self.state.is_storage_cold(address, key)
+	}
+
+	fn inc_nonce(&mut self, address: H160) {
+		self.state.inc_nonce(address)
+	}
+	fn set_storage(&mut self, address: H160, key: H256, value: H256) {
+		self.frame().storage.insert((address, key), value);
+		self.state.set_storage(address, key, value)
+	}
+	fn set_transient_storage(&mut self, address: H160, key: H256, value: H256) {
+		self.state.set_transient_storage(address, key, value)
+	}
+	fn reset_storage(&mut self, address: H160) {
+		self.frame().reset.insert(address);
+		self.state.reset_storage(address)
+	}
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) {
+		self.state.log(address, topics, data)
+	}
+	fn set_deleted(&mut self, address: H160) {
+		self.frame().reset.insert(address);
+		self.state.set_deleted(address)
+	}
+	fn set_created(&mut self, address: H160) {
+		self.state.set_created(address)
+	}
+	fn set_code(&mut self, address: H160, code: Vec<u8>) {
+		self.state.set_code(address, code)
+	}
+	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+		self.state.transfer(transfer)
+	}
+	fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError> {
+		self.state.withdraw(address, value)
+	}
+	fn deposit(&mut self, address: H160, value: U256) {
+		self.state.deposit(address, value)
+	}
+	fn reset_balance(&mut self, address: H160) {
+		self.state.reset_balance(address)
+	}
+	fn touch(&mut self, address: H160) {
+		self.state.touch(address)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{account_address, execute, Case};
+	use evm::backend::MemoryAccount;
+	use evm::Config;
+	use primitive_types::{H160, H256, U256};
+	use std::collections::BTreeMap;
+
+	#[test]
+	fn test_reverted_inner_call() {
+		let account = |code: Vec<u8>| MemoryAccount {
+			nonce: U256::one(),
+			balance: U256::zero(),
+			storage: BTreeMap::new(),
+			code,
+		};
+		let caller = H160::repeat_byte(0xca);
+		let mut state = BTreeMap::new();
+		// CALL 0x1001, then SSTORE(0, 2)
+		state.insert(
+			account_address(0),
+			account(vec![
+				0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x61, 0x10, 0x01, 0x61,
+				0xff, 0xff, 0xf1, 0x50, 0x60, 0x02, 0x60, 0x00, 0x55, 0x00,
+			]),
+		);
+		// SSTORE(0, 1), then REVERT
+		state.insert(
+			account_address(1),
+			account(vec![
+				0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd,
+			]),
+		);
+		state.insert(
+			caller,
+			MemoryAccount {
+				balance: U256::from(u64::MAX),
+				..account(Vec::new())
+			},
+		);
+		let case = Case {
+			caller,
+			state,
+			target: account_address(0),
+			value: U256::zero(),
+			gas_limit: 100_000,
+			data: Vec::new(),
+		};
+
+		let outcome = execute(&case, &Config::london());
+		assert!(outcome.reason.is_succeed());
+		let slot = H256::zero();
+		assert_eq!(
+			outcome.committed.storage.into_iter().collect::<Vec<_>>(),
+			vec![((account_address(0), slot), H256::from_low_u64_be(2))]
+		);
+		assert_eq!(
+			outcome.reverted.into_iter().collect::<Vec<_>>(),
+			vec![(account_address(1), slot)]
+		);
+		assert!(outcome.state[&account_address(1)].storage.is_empty());
+	}
+}
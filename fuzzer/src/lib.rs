@@ -0,0 +1,202 @@
+//! Decoding of fuzz inputs into transactions against a small state, shared
+//! by the fuzz targets that run the whole executor.
+
+use arbitrary::{Arbitrary, Unstructured};
+use evm::backend::{ApplyBackend, Log, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+use evm::executor::Executor;
+use evm::gasometer::{self, Gasometer};
+use evm::precompiles::StandardPrecompiles;
+use evm::{Config, ExitReason};
+use primitive_types::{H160, H256, U256};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub mod journal;
+pub mod program;
+
+pub use crate::journal::{Journal, Writes};
+pub use crate::program::Program;
+
+pub const MAX_ACCOUNTS: u8 = 4;
+pub const MAX_GAS_LIMIT: u64 = 1_000_000;
+pub const MAX_DATA_LEN: usize = 128;
+
+pub fn account_address(index: u8) -> H160 {
+	H160::from_low_u64_be(0x1000 + u64::from(index))
+}
+
+/// A call from an externally owned account to one of up to four accounts
+/// at `0x1000..0x1004` that can call each other.
+#[derive(Clone, Debug)]
+pub struct Case {
+	pub caller: H160,
+	pub state: BTreeMap<H160, MemoryAccount>,
+	pub target: H160,
+	pub value: U256,
+	pub gas_limit: u64,
+	pub data: Vec<u8>,
+}
+
+impl Case {
+	/// Decode the callee, value, gas limit and call data, followed by the
+	/// accounts (balance, code and storage). Code is mostly a generated
+	/// [`Program`], and otherwise raw bytes.
+	pub fn decode(data: &[u8]) -> Self {
+		let mut u = Unstructured::new(data);
+		// Running out of input yields zeros, so decoding cannot fail.
+		Self::arbitrary(&mut u).expect("decoding an exhausted input is infallible")
+	}
+
+	/// Block the call is executed in, with a zero gas price.
+	pub fn vicinity(&self) -> MemoryVicinity {
+		MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: self.caller,
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::one(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::one(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::from(MAX_GAS_LIMIT),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		}
+	}
+
+	/// Whether the gas limit covers the intrinsic cost, without which the
+	/// call is not executed at all.
+	pub fn pays_intrinsic_gas(&self, config: &Config) -> bool {
+		Gasometer::new(self.gas_limit, config)
+			.record_transaction(gasometer::call_transaction_cost(&self.data, &[]))
+			.is_ok()
+	}
+}
+
+impl<'a> Arbitrary<'a> for Case {
+	fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+		// Transaction fields come first, as the programs take up whatever
+		// input is left.
+		let target = account_address(u.int_in_range(0..=MAX_ACCOUNTS - 1)?);
+		let value = U256::from(u8::arbitrary(u)?);
+		let gas_limit = u.int_in_range(0..=MAX_GAS_LIMIT)?;
+		let data_len = u.int_in_range(0..=MAX_DATA_LEN)?;
+		let data = u.bytes(data_len.min(u.len()))?.to_vec();
+
+		let count = u.int_in_range(1..=MAX_ACCOUNTS)?;
+		let mut state = BTreeMap::new();
+		for index in 0..count {
+			let balance = U256::from(u8::arbitrary(u)?) << 64;
+			let code = if u.ratio(7, 8)? {
+				Program::arbitrary(u)?.code
+			} else {
+				let len = usize::from(u8::arbitrary(u)?) * 4;
+				u.bytes(len.min(u.len()))?.to_vec()
+			};
+			let mut storage = BTreeMap::new();
+			for _ in 0..u.int_in_range(0..=3)? {
+				storage.insert(
+					H256::from_low_u64_be(u64::from(u8::arbitrary(u)?)),
+					H256::from_low_u64_be(u64::from(u8::arbitrary(u)?)),
+				);
+			}
+
+			let account = MemoryAccount {
+				nonce: U256::one(),
+				balance,
+				storage,
+				code,
+			};
+			state.insert(account_address(index), account);
+		}
+
+		let caller = H160::repeat_byte(0xca);
+		state.insert(
+			caller,
+			MemoryAccount {
+				nonce: U256::zero(),
+				balance: U256::from(u64::MAX),
+				storage: BTreeMap::new(),
+				code: Vec::new(),
+			},
+		);
+
+		Ok(Self {
+			caller,
+			state,
+			target,
+			value,
+			gas_limit,
+			data,
+		})
+	}
+}
+
+/// Result of running a [`Case`].
+#[derive(Clone, Debug)]
+pub struct Outcome {
+	pub reason: ExitReason,
+	pub output: Vec<u8>,
+	pub used_gas: u64,
+	pub logs: Vec<Log>,
+	/// The committed state, see [`normalize`].
+	pub state: BTreeMap<H160, MemoryAccount>,
+	/// Storage writes that were committed.
+	pub committed: Writes,
+	/// Slots written by frames that reverted or failed, including inner
+	/// calls of a successful transaction.
+	pub reverted: BTreeSet<(H160, H256)>,
+}
+
+/// Run a case through `StackExecutor` with the standard precompiles.
+pub fn execute(case: &Case, config: &Config) -> Outcome {
+	let vicinity = case.vicinity();
+	let mut backend = MemoryBackend::new(&vicinity, case.state.clone());
+
+	let metadata = StackSubstateMetadata::new(case.gas_limit, config);
+	let state = Journal::new(MemoryStackState::new(metadata, &backend));
+	let precompiles = StandardPrecompiles::new(config);
+	let mut executor = StackExecutor::new_with_precompiles(state, config, &precompiles);
+
+	let (reason, output) = executor.transact_call(
+		case.caller,
+		case.target,
+		case.value,
+		case.data.clone(),
+		case.gas_limit,
+		Vec::new(),
+	);
+	let used_gas = executor.used_gas();
+
+	let (state, committed, reverted) = executor.into_state().into_parts();
+	let (values, logs) = state.deconstruct();
+	let logs = logs.into_iter().collect::<Vec<_>>();
+	backend.apply(values, logs.clone(), !config.empty_considered_exists);
+
+	Outcome {
+		reason,
+		output,
+		used_gas,
+		logs,
+		state: normalize(backend.state()),
+		committed,
+		reverted,
+	}
+}
+
+/// Accounts as they are committed: without empty accounts or zero slots.
+pub fn normalize(state: &BTreeMap<H160, MemoryAccount>) -> BTreeMap<H160, MemoryAccount> {
+	state
+		.iter()
+		.filter(|(_, account)| {
+			!(account.nonce.is_zero() && account.balance.is_zero() && account.code.is_empty())
+		})
+		.map(|(address, account)| {
+			let mut account = account.clone();
+			account.storage.retain(|_, value| !value.is_zero());
+			(*address, account)
+		})
+		.collect()
+}
@@ -0,0 +1,430 @@
+//! Structured EVM programs, generated so that execution gets past the first
+//! few instructions.
+//!
+//! A program is a sequence of blocks, each starting with a `JUMPDEST`. Every
+//! block starts and ends with an empty stack and only pushes what its
+//! instructions consume, so jumps between blocks never underflow. Memory
+//! offsets and sizes, call gas and addresses are kept small and realistic.
+
+use crate::{account_address, MAX_ACCOUNTS};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use evm::Opcode;
+
+const MAX_BLOCKS: usize = 8;
+const MAX_INSTRUCTIONS: usize = 16;
+
+/// Bytecode of a generated program.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Program {
+	pub code: Vec<u8>,
+}
+
+impl<'a> Arbitrary<'a> for Program {
+	fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+		let blocks = u.int_in_range(1..=MAX_BLOCKS)?;
+		let mut builder = Builder {
+			blocks,
+			..Builder::default()
+		};
+		for _ in 0..blocks {
+			builder.block(u)?;
+		}
+		Ok(builder.finish())
+	}
+}
+
+/// Instructions popping `.1` items and pushing `.2`, whose inputs are not
+/// used as memory offsets.
+const SIMPLE: &[(Opcode, usize, usize)] = &[
+	(Opcode::ADD, 2, 1),
+	(Opcode::MUL, 2, 1),
+	(Opcode::SUB, 2, 1),
+	(Opcode::DIV, 2, 1),
+	(Opcode::SDIV, 2, 1),
+	(Opcode::MOD, 2, 1),
+	(Opcode::SMOD, 2, 1),
+	(Opcode::ADDMOD, 3, 1),
+	(Opcode::MULMOD, 3, 1),
+	(Opcode::EXP, 2, 1),
+	(Opcode::SIGNEXTEND, 2, 1),
+	(Opcode::LT, 2, 1),
+	(Opcode::GT, 2, 1),
+	(Opcode::SLT, 2, 1),
+	(Opcode::SGT, 2, 1),
+	(Opcode::EQ, 2, 1),
+	(Opcode::ISZERO, 1, 1),
+	(Opcode::AND, 2, 1),
+	(Opcode::OR, 2, 1),
+	(Opcode::XOR, 2, 1),
+	(Opcode::NOT, 1, 1),
+	(Opcode::BYTE, 2, 1),
+	(Opcode::SHL, 2, 1),
+	(Opcode::SHR, 2, 1),
+	(Opcode::SAR, 2, 1),
+	(Opcode::ADDRESS, 0, 1),
+	(Opcode::BALANCE, 1, 1),
+	(Opcode::ORIGIN, 0, 1),
+	(Opcode::CALLER, 0, 1),
+	(Opcode::CALLVALUE, 0, 1),
+	(Opcode::CALLDATALOAD, 1, 1),
+	(Opcode::CALLDATASIZE, 0, 1),
+	(Opcode::CODESIZE, 0, 1),
+	(Opcode::GASPRICE, 0, 1),
+	(Opcode::EXTCODESIZE, 1, 1),
+	(Opcode::RETURNDATASIZE, 0, 1),
+	(Opcode::EXTCODEHASH, 1, 1),
+	(Opcode::BLOCKHASH, 1, 1),
+	(Opcode::COINBASE, 0, 1),
+	(Opcode::TIMESTAMP, 0, 1),
+	(Opcode::NUMBER, 0, 1),
+	(Opcode::DIFFICULTY, 0, 1),
+	(Opcode::GASLIMIT, 0, 1),
+	(Opcode::CHAINID, 0, 1),
+	(Opcode::SELFBALANCE, 0, 1),
+	(Opcode::BASEFEE, 0, 1),
+	(Opcode::POP, 1, 0),
+	(Opcode::PC, 0, 1),
+	(Opcode::MSIZE, 0, 1),
+	(Opcode::GAS, 0, 1),
+];
+
+/// Number of bytes of each generated `PUSH`, weighted towards the widths
+/// compilers emit.
+const PUSH_WIDTHS: &[usize] = &[1, 1, 1, 1, 2, 2, 4, 20, 32];
+
+/// Call gas, from none to more than is ever available.
+const CALL_GAS: &[u32] = &[0, 2300, 10_000, 100_000, 0xffffff];
+
+#[derive(Default)]
+struct Builder {
+	code: Vec<u8>,
+	/// Stack height in the current block.
+	height: usize,
+	blocks: usize,
+	/// Offsets of the `JUMPDEST` of each block generated so far.
+	jumpdests: Vec<usize>,
+	/// Offsets of `PUSH2` immediates to patch with the offset of a block.
+	labels: Vec<(usize, usize)>,
+}
+
+impl Builder {
+	fn op(&mut self, opcode: Opcode) {
+		self.code.push(opcode.as_u8());
+	}
+
+	fn push(&mut self, value: &[u8]) {
+		debug_assert!(!value.is_empty() && value.len() <= 32);
+		self.code
+			.push(Opcode::PUSH1.as_u8() + value.len() as u8 - 1);
+		self.code.extend_from_slice(value);
+		self.height += 1;
+	}
+
+	fn push_u64(&mut self, value: u64) {
+		let bytes = value.to_be_bytes();
+		let start = bytes.iter().position(|b| *b != 0).unwrap_or(7);
+		self.push(&bytes[start..]);
+	}
+
+	fn push_arbitrary(&mut self, u: &mut Unstructured) -> Result<()> {
+		let width = *u.choose(PUSH_WIDTHS)?;
+		let value = u.bytes(width.min(u.len()))?;
+		if value.is_empty() {
+			self.push_u64(0);
+		} else {
+			self.push(value);
+		}
+		Ok(())
+	}
+
+	/// Push a small memory offset or size.
+	fn push_small(&mut self, u: &mut Unstructured) -> Result<()> {
+		let value = *u.choose(&[0, 1, 4, 20, 31, 32, 64, 100, 256])?;
+		self.push_u64(value);
+		Ok(())
+	}
+
+	/// Push an address that exists in the fuzzed state, a precompile or the
+	/// caller.
+	fn push_address(&mut self, u: &mut Unstructured) -> Result<()> {
+		match u.int_in_range(0..=3)? {
+			0 => self.push_u64(u.int_in_range(1..=9)?),
+			1 => self.op_with(Opcode::CALLER, 0, 1),
+			_ => {
+				let index = u.int_in_range(0..=MAX_ACCOUNTS - 1)?;
+				self.push(account_address(index).as_bytes());
+			}
+		}
+		Ok(())
+	}
+
+	/// Emit an instruction that pops and pushes the given number of items,
+	/// pushing its inputs first if the stack is short.
+	fn op_with(&mut self, opcode: Opcode, inputs: usize, outputs: usize) {
+		while self.height < inputs {
+			self.push_u64(0);
+		}
+		self.op(opcode);
+		self.height = self.height - inputs + outputs;
+	}
+
+	fn pop_all(&mut self) {
+		while self.height > 0 {
+			self.op(Opcode::POP);
+			self.height -= 1;
+		}
+	}
+
+	/// A `PUSH2` of the offset of a block from `first` on, patched in
+	/// `finish`.
+	fn push_label(&mut self, u: &mut Unstructured, first: usize) -> Result<()> {
+		let block = u.int_in_range(first..=self.blocks - 1)?;
+		self.push(&[0, 0]);
+		self.labels.push((self.code.len() - 2, block));
+		Ok(())
+	}
+
+	fn block(&mut self, u: &mut Unstructured) -> Result<()> {
+		self.jumpdests.push(self.code.len());
+		self.op(Opcode::JUMPDEST);
+
+		let instructions = u.int_in_range(0..=MAX_INSTRUCTIONS)?;
+		for _ in 0..instructions {
+			self.instruction(u)?;
+		}
+		self.terminator(u)
+	}
+
+	fn instruction(&mut self, u: &mut Unstructured) -> Result<()> {
+		match u.int_in_range(0..=19)? {
+			0..=5 => self.push_arbitrary(u)?,
+			6..=10 => {
+				let (opcode, inputs, outputs) = *u.choose(SIMPLE)?;
+				self.op_with(opcode, inputs, outputs);
+			}
+			11 => {
+				let depth = u.int_in_range(1..=4)?;
+				self.op_with(
+					Opcode(Opcode::DUP1.as_u8() + depth - 1),
+					depth as usize,
+					depth as usize + 1,
+				);
+			}
+			12 => {
+				let depth = u.int_in_range(1..=4)?;
+				self.op_with(
+					Opcode(Opcode::SWAP1.as_u8() + depth - 1),
+					depth as usize + 1,
+					depth as usize + 1,
+				);
+			}
+			13 => {
+				let opcode = *u.choose(&[Opcode::MLOAD, Opcode::MSTORE, Opcode::MSTORE8])?;
+				if opcode != Opcode::MLOAD && self.height == 0 {
+					self.push_arbitrary(u)?;
+				}
+				self.push_small(u)?;
+				let inputs = if opcode == Opcode::MLOAD { 1 } else { 2 };
+				self.op_with(opcode, inputs, 2 - inputs);
+			}
+			14 => {
+				let opcode = *u.choose(&[Opcode::SLOAD, Opcode::SSTORE])?;
+				if opcode == Opcode::SSTORE && self.height == 0 {
+					self.push_arbitrary(u)?;
+				}
+				self.push_u64(u.int_in_range(0..=3)?);
+				let inputs = if opcode == Opcode::SLOAD { 1 } else { 2 };
+				self.op_with(opcode, inputs, 2 - inputs);
+			}
+			15 => {
+				let opcode = *u.choose(&[
+					Opcode::SHA3,
+					Opcode::CALLDATACOPY,
+					Opcode::CODECOPY,
+					Opcode::RETURNDATACOPY,
+					Opcode::LOG0,
+					Opcode::LOG1,
+					Opcode::LOG2,
+				])?;
+				self.memory_op(u, opcode)?;
+			}
+			16 | 17 => self.call(u)?,
+			18 => self.create(u)?,
+			_ => {
+				self.pop_all();
+				self.push_arbitrary(u)?;
+				self.push_label(u, 0)?;
+				self.op_with(Opcode::JUMPI, 2, 0);
+			}
+		}
+		Ok(())
+	}
+
+	/// Instructions reading memory at a small offset and size from the top of
+	/// the stack.
+	fn memory_op(&mut self, u: &mut Unstructured, opcode: Opcode) -> Result<()> {
+		let topics = match opcode {
+			Opcode::LOG1 => 1,
+			Opcode::LOG2 => 2,
+			_ => 0,
+		};
+		for _ in 0..topics {
+			self.push_arbitrary(u)?;
+		}
+		let (inputs, outputs) = match opcode {
+			Opcode::SHA3 => (2, 1),
+			Opcode::CALLDATACOPY | Opcode::CODECOPY | Opcode::RETURNDATACOPY => {
+				self.push_small(u)?;
+				(3, 0)
+			}
+			_ => (2 + topics, 0),
+		};
+		self.push_small(u)?;
+		self.push_small(u)?;
+		self.op_with(opcode, inputs, outputs);
+		Ok(())
+	}
+
+	fn call(&mut self, u: &mut Unstructured) -> Result<()> {
+		let opcode = *u.choose(&[
+			Opcode::CALL,
+			Opcode::CALLCODE,
+			Opcode::DELEGATECALL,
+			Opcode::STATICCALL,
+		])?;
+
+		self.push_small(u)?;
+		self.push_small(u)?;
+		self.push_small(u)?;
+		self.push_small(u)?;
+		let inputs = if opcode == Opcode::CALL || opcode == Opcode::CALLCODE {
+			self.push_u64(u.int_in_range(0..=2)?);
+			7
+		} else {
+			6
+		};
+		self.push_address(u)?;
+		if u.ratio(1, 4)? {
+			self.op_with(Opcode::GAS, 0, 1);
+		} else {
+			self.push_u64(u64::from(*u.choose(CALL_GAS)?));
+		}
+		self.op_with(opcode, inputs, 1);
+		Ok(())
+	}
+
+	/// Store a constructor in memory and create a contract from it.
+	fn create(&mut self, u: &mut Unstructured) -> Result<()> {
+		let constructor = match u.int_in_range(0..=3)? {
+			// Deploy code starting with an arbitrary byte.
+			0 | 1 => vec![
+				Opcode::PUSH1.as_u8(),
+				u8::arbitrary(u)?,
+				Opcode::PUSH1.as_u8(),
+				0,
+				Opcode::MSTORE8.as_u8(),
+				Opcode::PUSH1.as_u8(),
+				u.int_in_range(0..=32)?,
+				Opcode::PUSH1.as_u8(),
+				0,
+				Opcode::RETURN.as_u8(),
+			],
+			2 => vec![
+				Opcode::PUSH1.as_u8(),
+				0,
+				Opcode::DUP1.as_u8(),
+				Opcode::REVERT.as_u8(),
+			],
+			_ => {
+				let len = u.int_in_range(0..=32)?;
+				u.bytes(len.min(u.len()))?.to_vec()
+			}
+		};
+		// Store it with a single `MSTORE`.
+		let len = constructor.len();
+		let mut word = [0u8; 32];
+		word[..len].copy_from_slice(&constructor);
+		self.push(&word);
+		self.push_u64(0);
+		self.op_with(Opcode::MSTORE, 2, 0);
+
+		let opcode = *u.choose(&[Opcode::CREATE, Opcode::CREATE2])?;
+		if opcode == Opcode::CREATE2 {
+			self.push_arbitrary(u)?;
+		}
+		self.push_u64(len as u64);
+		self.push_u64(0);
+		self.push_u64(u.int_in_range(0..=2)?);
+		let inputs = if opcode == Opcode::CREATE2 { 4 } else { 3 };
+		self.op_with(opcode, inputs, 1);
+		Ok(())
+	}
+
+	fn terminator(&mut self, u: &mut Unstructured) -> Result<()> {
+		self.pop_all();
+		match u.int_in_range(0..=9)? {
+			0..=3 => (),
+			// Only conditional jumps go backwards, so that most programs
+			// terminate before running out of gas.
+			4 | 5 if self.jumpdests.len() < self.blocks => {
+				self.push_label(u, self.jumpdests.len())?;
+				self.op_with(Opcode::JUMP, 1, 0);
+			}
+			6 | 7 => {
+				let opcode = *u.choose(&[Opcode::RETURN, Opcode::REVERT])?;
+				self.push_small(u)?;
+				self.push_small(u)?;
+				self.op_with(opcode, 2, 0);
+			}
+			8 => {
+				self.push_address(u)?;
+				self.op_with(Opcode::SUICIDE, 1, 0);
+			}
+			_ => self.op(*u.choose(&[Opcode::STOP, Opcode::INVALID])?),
+		}
+		self.height = 0;
+		Ok(())
+	}
+
+	fn finish(mut self) -> Program {
+		for (offset, block) in self.labels {
+			let target = self.jumpdests[block] as u16;
+			self.code[offset..offset + 2].copy_from_slice(&target.to_be_bytes());
+		}
+		Program { code: self.code }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_jumps_target_jumpdests() {
+		let data = (0..4096u32)
+			.map(|i| (i * 7919 % 251) as u8)
+			.collect::<Vec<_>>();
+		let mut u = Unstructured::new(&data);
+		for _ in 0..16 {
+			let program = Program::arbitrary(&mut u).unwrap();
+			let code = &program.code;
+			assert_eq!(code.first(), Some(&Opcode::JUMPDEST.as_u8()));
+
+			let mut position = 0;
+			while position < code.len() {
+				let opcode = Opcode(code[position]);
+				if let Some(width) = opcode.is_push() {
+					let width = usize::from(width);
+					// Labels are `PUSH2` immediately followed by a jump.
+					if width == 2 && matches!(code.get(position + 3), Some(&0x56) | Some(&0x57)) {
+						let target =
+							usize::from(code[position + 1]) << 8 | usize::from(code[position + 2]);
+						assert_eq!(code[target], Opcode::JUMPDEST.as_u8());
+					}
+					position += width;
+				}
+				position += 1;
+			}
+		}
+	}
+}
@@ -19,3 +19,5 @@ pub const G_SHA3WORD: u32 = 6;
 pub const G_COPY: u32 = 3;
 pub const G_BLOCKHASH: u32 = 20;
 pub const G_CODEDEPOSIT: u32 = 200;
+pub const G_INITCODE_WORD: u32 = 2;
+pub const G_RJUMPI: u32 = 4;
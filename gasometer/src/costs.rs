@@ -58,17 +58,40 @@ pub fn sstore_refund(original: H256, current: H256, new: H256, config: &Config)
 	}
 }
 
-pub fn create2_cost(len: U256) -> Result<u64, ExitError> {
-	let base = U256::from(G_CREATE);
-	// ceil(len / 32.0)
-	let sha_addup_base = len / U256::from(32)
+/// Gas charged per word of init code, failing if it exceeds the size limit
+/// (EIP-3860).
+fn initcode_cost(len: U256, config: &Config) -> Result<U256, ExitError> {
+	match config.max_initcode_size {
+		Some(limit) if len > U256::from(limit) => Err(ExitError::CreateContractLimit),
+		Some(_) => Ok(U256::from(G_INITCODE_WORD) * words(len)),
+		None => Ok(U256::zero()),
+	}
+}
+
+/// ceil(len / 32.0)
+fn words(len: U256) -> U256 {
+	len / U256::from(32)
 		+ if len % U256::from(32) == U256::zero() {
 			U256::zero()
 		} else {
 			U256::one()
-		};
+		}
+}
+
+pub fn create_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
+	let gas = U256::from(G_CREATE) + initcode_cost(len, config)?;
+
+	if gas > U256::from(u64::MAX) {
+		return Err(ExitError::OutOfGas);
+	}
+
+	Ok(gas.as_u64())
+}
+
+pub fn create2_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
+	let base = U256::from(G_CREATE) + initcode_cost(len, config)?;
 	let sha_addup = U256::from(G_SHA3WORD)
-		.checked_mul(sha_addup_base)
+		.checked_mul(words(len))
 		.ok_or(ExitError::OutOfGas)?;
 	let gas = base.checked_add(sha_addup).ok_or(ExitError::OutOfGas)?;
 
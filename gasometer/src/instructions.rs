@@ -0,0 +1,162 @@
+use crate::{consts, static_opcode_cost};
+use evm_core::Opcode;
+use evm_runtime::Config;
+
+/// Availability and static cost of an opcode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Instruction {
+	/// Whether the opcode is part of the fork. Disabled opcodes are not priced
+	/// here: the stack executor charges the cost of a custom opcode registered
+	/// for them, which `Handler::other` then runs, and otherwise fails with
+	/// `OutOfGas`, consuming all gas.
+	pub enabled: bool,
+	/// Cost of the opcode, when it does not depend on its operands or the
+	/// state. Others are priced by `dynamic_opcode_cost`.
+	pub static_cost: Option<u64>,
+}
+
+impl Instruction {
+	const DISABLED: Self = Self {
+		enabled: false,
+		static_cost: None,
+	};
+	const DYNAMIC: Self = Self {
+		enabled: true,
+		static_cost: None,
+	};
+
+	const fn fixed(cost: u32) -> Self {
+		Self {
+			enabled: true,
+			static_cost: Some(cost as u64),
+		}
+	}
+
+	/// Instruction of `opcode` in the fork of `config`.
+	pub fn new(opcode: Opcode, config: &Config) -> Self {
+		let fixed = |enabled: bool, cost: u32| {
+			if enabled {
+				Self::fixed(cost)
+			} else {
+				Self::DISABLED
+			}
+		};
+		let dynamic = |enabled: bool| {
+			if enabled {
+				Self::DYNAMIC
+			} else {
+				Self::DISABLED
+			}
+		};
+
+		match opcode {
+			Opcode::RETURN
+			| Opcode::MLOAD
+			| Opcode::MSTORE
+			| Opcode::MSTORE8
+			| Opcode::EXTCODESIZE
+			| Opcode::BALANCE
+			| Opcode::BLOCKHASH
+			| Opcode::CALLCODE
+			| Opcode::STATICCALL
+			| Opcode::SHA3
+			| Opcode::EXTCODECOPY
+			| Opcode::CALLDATACOPY
+			| Opcode::CODECOPY
+			| Opcode::EXP
+			| Opcode::SLOAD
+			| Opcode::SSTORE
+			| Opcode::LOG0
+			| Opcode::LOG1
+			| Opcode::LOG2
+			| Opcode::LOG3
+			| Opcode::LOG4
+			| Opcode::CREATE
+			| Opcode::SUICIDE
+			| Opcode::CALL => Self::DYNAMIC,
+
+			Opcode::CHAINID => fixed(config.has_chain_id, consts::G_BASE),
+			Opcode::SHL | Opcode::SHR | Opcode::SAR => {
+				fixed(config.has_bitwise_shifting, consts::G_VERYLOW)
+			}
+			Opcode::SELFBALANCE => fixed(config.has_self_balance, consts::G_LOW),
+			Opcode::BASEFEE => fixed(config.has_base_fee, consts::G_BASE),
+			Opcode::PUSH0 => fixed(config.has_push0, consts::G_BASE),
+			Opcode::BLOBHASH => fixed(config.has_blob_hash, consts::G_VERYLOW),
+			Opcode::BLOBBASEFEE => fixed(config.has_blob_base_fee, consts::G_BASE),
+			Opcode::RETURNDATASIZE => fixed(config.has_return_data, consts::G_BASE),
+			Opcode::RJUMP => fixed(config.has_eof, consts::G_BASE),
+			Opcode::RJUMPI | Opcode::RJUMPV => fixed(config.has_eof, consts::G_RJUMPI),
+			Opcode::CALLF => fixed(config.has_eof, consts::G_LOW),
+			Opcode::RETF => fixed(config.has_eof, consts::G_VERYLOW),
+			Opcode::TLOAD if config.has_transient_storage => Self {
+				enabled: true,
+				static_cost: Some(config.gas_storage_read_warm),
+			},
+			Opcode::TLOAD => Self::DISABLED,
+
+			Opcode::REVERT => dynamic(config.has_revert),
+			Opcode::RETURNDATACOPY => dynamic(config.has_return_data),
+			Opcode::EXTCODEHASH => dynamic(config.has_ext_code_hash),
+			Opcode::DELEGATECALL => dynamic(config.has_delegate_call),
+			Opcode::CREATE2 => dynamic(config.has_create2),
+			Opcode::MCOPY => dynamic(config.has_mcopy),
+			Opcode::TSTORE => dynamic(config.has_transient_storage),
+
+			_ => static_opcode_cost(opcode).map_or(Self::DISABLED, Self::fixed),
+		}
+	}
+}
+
+/// Instructions of a fork, built from a [`Config`] once instead of checking
+/// its flags for every opcode executed.
+#[derive(Clone, Debug)]
+pub struct InstructionTable {
+	instructions: [Instruction; 256],
+}
+
+impl InstructionTable {
+	/// Instructions enabled by `config`, with their static costs.
+	pub fn new(config: &Config) -> Self {
+		let mut table = Self {
+			instructions: [Instruction::DISABLED; 256],
+		};
+		for opcode in 0..=255 {
+			table.set(Opcode(opcode), Instruction::new(Opcode(opcode), config));
+		}
+		table
+	}
+
+	/// Instruction of an opcode.
+	#[inline]
+	pub fn get(&self, opcode: Opcode) -> Instruction {
+		self.instructions[opcode.as_usize()]
+	}
+
+	/// Replace the instruction of an opcode, for example to reprice it.
+	pub fn set(&mut self, opcode: Opcode, instruction: Instruction) {
+		self.instructions[opcode.as_usize()] = instruction;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_fork_instructions() {
+		let london = InstructionTable::new(&Config::london());
+		let shanghai = InstructionTable::new(&Config::shanghai());
+		assert!(!london.get(Opcode::PUSH0).enabled);
+		assert_eq!(shanghai.get(Opcode::PUSH0).static_cost, Some(2));
+		assert_eq!(london.get(Opcode::ADD), shanghai.get(Opcode::ADD));
+		assert_eq!(london.get(Opcode::SLOAD).static_cost, None);
+
+		let frontier = InstructionTable::new(&Config::frontier());
+		assert!(!frontier.get(Opcode::DELEGATECALL).enabled);
+		assert!(london.get(Opcode::DELEGATECALL).enabled);
+		assert!(!london.get(Opcode::INVALID).enabled);
+		assert!(!Instruction::new(Opcode::TLOAD, &Config::london()).enabled);
+		assert!(Instruction::new(Opcode::TLOAD, &Config::cancun()).enabled);
+	}
+}
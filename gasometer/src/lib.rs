@@ -24,9 +24,12 @@ macro_rules! event {
 
 mod consts;
 mod costs;
+mod instructions;
 mod memory;
 mod utils;
 
+pub use crate::instructions::{Instruction, InstructionTable};
+
 use alloc::vec::Vec;
 use core::cmp::max;
 use evm_core::{ExitError, Opcode, Stack};
@@ -244,12 +247,20 @@ impl<'config> Gasometer<'config> {
 				non_zero_data_len,
 				access_list_address_len,
 				access_list_storage_len,
+				initcode_cost,
 			} => {
+				let initcode_cost = if self.config.max_initcode_size.is_some() {
+					initcode_cost
+				} else {
+					0
+				};
+
 				self.config.gas_transaction_create
 					+ zero_data_len as u64 * self.config.gas_transaction_zero_data
 					+ non_zero_data_len as u64 * self.config.gas_transaction_non_zero_data
 					+ access_list_address_len as u64 * self.config.gas_access_list_address
 					+ access_list_storage_len as u64 * self.config.gas_access_list_storage_key
+					+ initcode_cost
 			}
 		};
 
@@ -300,11 +311,14 @@ pub fn create_transaction_cost(data: &[u8], access_list: &[(H160, Vec<H256>)]) -
 	let non_zero_data_len = data.len() - zero_data_len;
 	let (access_list_address_len, access_list_storage_len) = count_access_list(access_list);
 
+	let initcode_cost = consts::G_INITCODE_WORD as u64 * data.chunks(32).len() as u64;
+
 	TransactionCost::Create {
 		zero_data_len,
 		non_zero_data_len,
 		access_list_address_len,
 		access_list_storage_len,
+		initcode_cost,
 	}
 }
 
@@ -439,7 +453,8 @@ pub fn static_opcode_cost(opcode: Opcode) -> Option<u32> {
 	TABLE[opcode.as_usize()]
 }
 
-/// Calculate the opcode cost.
+/// Calculate the cost of an opcode without a static cost in the
+/// [`InstructionTable`]. Opcodes the fork of `config` lacks are `Invalid`.
 #[allow(clippy::nonminimal_bool)]
 pub fn dynamic_opcode_cost<H: Handler>(
 	address: H160,
@@ -451,24 +466,19 @@ pub fn dynamic_opcode_cost<H: Handler>(
 ) -> Result<(GasCost, StorageTarget, Option<MemoryCost>), ExitError> {
 	let mut storage_target = StorageTarget::None;
 	let gas_cost = match opcode {
+		_ if !Instruction::new(opcode, config).enabled => GasCost::Invalid,
+
 		Opcode::RETURN => GasCost::Zero,
 
 		Opcode::MLOAD | Opcode::MSTORE | Opcode::MSTORE8 => GasCost::VeryLow,
 
-		Opcode::REVERT if config.has_revert => GasCost::Zero,
-		Opcode::REVERT => GasCost::Invalid,
-
-		Opcode::CHAINID if config.has_chain_id => GasCost::Base,
-		Opcode::CHAINID => GasCost::Invalid,
-
-		Opcode::SHL | Opcode::SHR | Opcode::SAR if config.has_bitwise_shifting => GasCost::VeryLow,
-		Opcode::SHL | Opcode::SHR | Opcode::SAR => GasCost::Invalid,
+		Opcode::REVERT => GasCost::Zero,
 
-		Opcode::SELFBALANCE if config.has_self_balance => GasCost::Low,
-		Opcode::SELFBALANCE => GasCost::Invalid,
+		Opcode::MCOPY => GasCost::VeryLowCopy {
+			len: stack.peek(2)?,
+		},
 
-		Opcode::BASEFEE if config.has_base_fee => GasCost::Base,
-		Opcode::BASEFEE => GasCost::Invalid,
+		Opcode::TSTORE if !is_static => GasCost::WarmStorageRead,
 
 		Opcode::EXTCODESIZE => {
 			let target = stack.peek_h256(0)?.into();
@@ -486,15 +496,13 @@ pub fn dynamic_opcode_cost<H: Handler>(
 		}
 		Opcode::BLOCKHASH => GasCost::BlockHash,
 
-		Opcode::EXTCODEHASH if config.has_ext_code_hash => {
+		Opcode::EXTCODEHASH => {
 			let target = stack.peek_h256(0)?.into();
 			storage_target = StorageTarget::Address(target);
 			GasCost::ExtCodeHash {
 				target_is_cold: handler.is_cold(target, None),
 			}
 		}
-		Opcode::EXTCODEHASH => GasCost::Invalid,
-
 		Opcode::CALLCODE => {
 			let target = stack.peek_h256(1)?.into();
 			storage_target = StorageTarget::Address(target);
@@ -539,7 +547,7 @@ pub fn dynamic_opcode_cost<H: Handler>(
 			}
 		}
 
-		Opcode::DELEGATECALL if config.has_delegate_call => {
+		Opcode::DELEGATECALL => {
 			let target = stack.peek_h256(1)?.into();
 			storage_target = StorageTarget::Address(target);
 			GasCost::DelegateCall {
@@ -548,13 +556,10 @@ pub fn dynamic_opcode_cost<H: Handler>(
 				target_exists: handler.exists(target),
 			}
 		}
-		Opcode::DELEGATECALL => GasCost::Invalid,
 
-		Opcode::RETURNDATASIZE if config.has_return_data => GasCost::Base,
-		Opcode::RETURNDATACOPY if config.has_return_data => GasCost::VeryLowCopy {
+		Opcode::RETURNDATACOPY => GasCost::VeryLowCopy {
 			len: stack.peek(2)?,
 		},
-		Opcode::RETURNDATASIZE | Opcode::RETURNDATACOPY => GasCost::Invalid,
 
 		Opcode::SSTORE if !is_static => {
 			let index = stack.peek_h256(0)?;
@@ -588,8 +593,10 @@ pub fn dynamic_opcode_cost<H: Handler>(
 			n: 4,
 			len: stack.peek(1)?,
 		},
-		Opcode::CREATE if !is_static => GasCost::Create,
-		Opcode::CREATE2 if !is_static && config.has_create2 => GasCost::Create2 {
+		Opcode::CREATE if !is_static => GasCost::Create {
+			len: stack.peek(2)?,
+		},
+		Opcode::CREATE2 if !is_static => GasCost::Create2 {
 			len: stack.peek(2)?,
 		},
 		Opcode::SUICIDE if !is_static => {
@@ -649,6 +656,17 @@ pub fn dynamic_opcode_cost<H: Handler>(
 			len: U256::from(1),
 		}),
 
+		Opcode::MCOPY => Some(
+			MemoryCost {
+				offset: stack.peek(0)?,
+				len: stack.peek(2)?,
+			}
+			.join(MemoryCost {
+				offset: stack.peek(1)?,
+				len: stack.peek(2)?,
+			}),
+		),
+
 		Opcode::CREATE | Opcode::CREATE2 => Some(MemoryCost {
 			offset: stack.peek(1)?,
 			len: stack.peek(2)?,
@@ -796,14 +814,15 @@ impl<'config> Inner<'config> {
 			GasCost::Log { n, len } => costs::log_cost(n, len)?,
 			GasCost::VeryLowCopy { len } => costs::verylowcopy_cost(len)?,
 			GasCost::Exp { power } => costs::exp_cost(power, self.config)?,
-			GasCost::Create => consts::G_CREATE as u64,
-			GasCost::Create2 { len } => costs::create2_cost(len)?,
+			GasCost::Create { len } => costs::create_cost(len, self.config)?,
+			GasCost::Create2 { len } => costs::create2_cost(len, self.config)?,
 			GasCost::SLoad { target_is_cold } => costs::sload_cost(target_is_cold, self.config),
 
 			GasCost::Zero => consts::G_ZERO as u64,
 			GasCost::Base => consts::G_BASE as u64,
 			GasCost::VeryLow => consts::G_VERYLOW as u64,
 			GasCost::Low => consts::G_LOW as u64,
+			GasCost::WarmStorageRead => self.config.gas_storage_read_warm,
 			GasCost::Invalid => return Err(ExitError::OutOfGas),
 
 			GasCost::ExtCodeSize { target_is_cold } => {
@@ -854,6 +873,8 @@ pub enum GasCost {
 	VeryLow,
 	/// Low gas cost.
 	Low,
+	/// Gas cost of reading warm storage, for transient storage.
+	WarmStorageRead,
 	/// Fail the gasometer.
 	Invalid,
 
@@ -967,7 +988,10 @@ pub enum GasCost {
 		power: U256,
 	},
 	/// Gas cost for `CREATE`.
-	Create,
+	Create {
+		/// Length.
+		len: U256,
+	},
 	/// Gas cost for `CREATE2`.
 	Create2 {
 		/// Length.
@@ -1024,6 +1048,8 @@ pub enum TransactionCost {
 		access_list_address_len: usize,
 		/// Total number of storage keys in transaction access list (see EIP-2930)
 		access_list_storage_len: usize,
+		/// Cost of the init code words, charged from Shanghai (see EIP-3860)
+		initcode_cost: u64,
 	},
 }
 
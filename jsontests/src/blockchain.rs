@@ -0,0 +1,919 @@
+//! `BlockchainTests`: RLP encoded blocks imported on top of a genesis block,
+//! with the expected head and post-state. Blocks without a decoded header in
+//! the fixture are expected to be rejected.
+//!
+//! Headers are checked against their parent and against the result of
+//! executing their transactions. Difficulty and proof of work are not
+//! verified, so only tests using the `NoProof` seal engine are run.
+
+use crate::state::Account;
+use crate::transaction::{self, Transaction, TransactionKind};
+use crate::utils::{self, Hex};
+use crate::Summary;
+use evm::backend::{MemoryAccount, MemoryVicinity};
+use evm::executor::stack::{
+	blob_base_fee, next_base_fee, next_excess_blob_gas, ELASTICITY_MULTIPLIER, INITIAL_BASE_FEE,
+	MAX_BLOB_GAS_PER_BLOCK,
+};
+use evm::{Activation, ChainSpec, Config, Fork};
+use primitive_types::{H160, H256, U256};
+use rlp::{Decodable, DecoderError, Rlp, RlpStream};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockchainTest {
+	/// A fork name, or a transition such as `BerlinToLondonAt5`.
+	pub network: String,
+	#[serde(rename = "genesisRLP")]
+	pub genesis_rlp: Hex<Vec<u8>>,
+	pub pre: BTreeMap<Hex<H160>, Account>,
+	#[serde(default)]
+	pub post_state: Option<BTreeMap<Hex<H160>, Account>>,
+	#[serde(default)]
+	pub post_state_hash: Option<Hex<H256>>,
+	pub lastblockhash: Hex<H256>,
+	pub blocks: Vec<BlockFixture>,
+	#[serde(default)]
+	pub seal_engine: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockFixture {
+	/// Not necessarily valid hex or RLP for invalid blocks.
+	pub rlp: String,
+	/// Only present for blocks expected to be valid.
+	#[serde(default)]
+	pub block_header: Option<IgnoredAny>,
+}
+
+/// A decoded block header.
+#[derive(Clone, Debug)]
+pub struct Header {
+	pub hash: H256,
+	pub parent_hash: H256,
+	pub ommers_hash: H256,
+	pub beneficiary: H160,
+	pub state_root: H256,
+	pub transactions_root: H256,
+	pub receipts_root: H256,
+	pub logs_bloom: Vec<u8>,
+	pub difficulty: U256,
+	pub number: U256,
+	pub gas_limit: U256,
+	pub gas_used: U256,
+	pub timestamp: U256,
+	pub extra_data: Vec<u8>,
+	pub mix_hash: H256,
+	/// From London.
+	pub base_fee: Option<U256>,
+	/// From Shanghai.
+	pub withdrawals_root: Option<H256>,
+	/// From Cancun.
+	pub blob_gas_used: Option<U256>,
+	/// From Cancun.
+	pub excess_blob_gas: Option<U256>,
+	/// From Cancun.
+	pub parent_beacon_block_root: Option<H256>,
+}
+
+impl Header {
+	pub fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+		let count = rlp.item_count()?;
+		if !(15..=20).contains(&count) {
+			return Err(DecoderError::RlpIncorrectListLen);
+		}
+
+		Ok(Self {
+			hash: utils::keccak(rlp.as_raw()),
+			parent_hash: rlp.val_at(0)?,
+			ommers_hash: rlp.val_at(1)?,
+			beneficiary: rlp.val_at(2)?,
+			state_root: rlp.val_at(3)?,
+			transactions_root: rlp.val_at(4)?,
+			receipts_root: rlp.val_at(5)?,
+			logs_bloom: rlp.val_at(6)?,
+			difficulty: rlp.val_at(7)?,
+			number: rlp.val_at(8)?,
+			gas_limit: rlp.val_at(9)?,
+			gas_used: rlp.val_at(10)?,
+			timestamp: rlp.val_at(11)?,
+			extra_data: rlp.val_at(12)?,
+			mix_hash: rlp.val_at(13)?,
+			base_fee: optional_at(rlp, 15)?,
+			withdrawals_root: optional_at(rlp, 16)?,
+			blob_gas_used: optional_at(rlp, 17)?,
+			excess_blob_gas: optional_at(rlp, 18)?,
+			parent_beacon_block_root: optional_at(rlp, 19)?,
+		})
+	}
+}
+
+/// Item `index` of a list, `None` past its end.
+fn optional_at<T: Decodable>(rlp: &Rlp, index: usize) -> Result<Option<T>, DecoderError> {
+	if index < rlp.item_count()? {
+		rlp.val_at(index).map(Some)
+	} else {
+		Ok(None)
+	}
+}
+
+/// Forks of a fixture network, active from a block number or timestamp on.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+	spec: ChainSpec,
+}
+
+impl Schedule {
+	/// Parse a fixture network, if all of its forks are supported. Transitions
+	/// are at a block number, as in `BerlinToLondonAt5`, or at a timestamp, as
+	/// in `MergeToShanghaiAtTime15k`.
+	pub fn parse(network: &str) -> Option<Self> {
+		let spec = match network.split_once("To") {
+			Some((from, to)) => {
+				let (to, at) = to.rsplit_once("At")?;
+				let activation = match at.strip_prefix("Time") {
+					Some(time) => {
+						let (time, scale) = match time.strip_suffix('k') {
+							Some(time) => (time, 1000),
+							None => (time, 1),
+						};
+						Activation::Timestamp(time.parse::<u64>().ok()?.checked_mul(scale)?)
+					}
+					None => Activation::Block(at.parse().ok()?),
+				};
+				let (from, to) = (utils::fork(from)?, utils::fork(to)?);
+				if to <= from {
+					return None;
+				}
+				ChainSpec::new(from).with_fork(to, activation)
+			}
+			None => ChainSpec::new(utils::fork(network)?),
+		};
+		Some(Self { spec })
+	}
+
+	/// Fork of a block.
+	pub fn fork(&self, header: &Header) -> Fork {
+		self.spec.fork(
+			saturating_u64(header.number),
+			saturating_u64(header.timestamp),
+		)
+	}
+
+	/// Configuration of a block.
+	pub fn config(&self, header: &Header) -> &Config {
+		self.spec.config(
+			saturating_u64(header.number),
+			saturating_u64(header.timestamp),
+		)
+	}
+}
+
+fn saturating_u64(value: U256) -> u64 {
+	if value > U256::from(u64::MAX) {
+		u64::MAX
+	} else {
+		value.as_u64()
+	}
+}
+
+struct ImportedBlock {
+	header: Header,
+	state: BTreeMap<H160, MemoryAccount>,
+	total_difficulty: U256,
+}
+
+/// Imported blocks, of which the one with the highest total difficulty is
+/// the head.
+struct Chain<'a> {
+	schedule: &'a Schedule,
+	blocks: BTreeMap<H256, ImportedBlock>,
+	head: H256,
+}
+
+impl<'a> Chain<'a> {
+	fn new(schedule: &'a Schedule, genesis: Header, state: BTreeMap<H160, MemoryAccount>) -> Self {
+		let head = genesis.hash;
+		let genesis = ImportedBlock {
+			total_difficulty: genesis.difficulty,
+			header: genesis,
+			state,
+		};
+
+		Self {
+			schedule,
+			blocks: vec![(head, genesis)].into_iter().collect(),
+			head,
+		}
+	}
+
+	fn head(&self) -> &ImportedBlock {
+		&self.blocks[&self.head]
+	}
+
+	/// Hashes of `hash` and its ancestors, as `BLOCKHASH` sees them.
+	fn block_hashes(&self, mut hash: H256) -> Vec<H256> {
+		let mut hashes = Vec::new();
+		while hashes.len() < 256 {
+			match self.blocks.get(&hash) {
+				Some(block) => {
+					hashes.push(hash);
+					hash = block.header.parent_hash;
+				}
+				None => break,
+			}
+		}
+		hashes
+	}
+
+	fn import(&mut self, bytes: &[u8]) -> Result<(), String> {
+		let rlp = Rlp::new(bytes);
+		let header = Header::decode(&rlp.at(0).map_err(|e| e.to_string())?)
+			.map_err(|e| format!("header: {}", e))?;
+		if self.blocks.contains_key(&header.hash) {
+			return Ok(());
+		}
+		let parent = self
+			.blocks
+			.get(&header.parent_hash)
+			.ok_or("unknown parent")?;
+
+		let fork = self.schedule.fork(&header);
+		let config = self.schedule.config(&header);
+		let parent_config = self.schedule.config(&parent.header);
+		validate_header(&header, &parent.header, config, parent_config)?;
+
+		// Withdrawals arrived with `PUSH0` in Shanghai.
+		let items = if config.has_push0 { 4 } else { 3 };
+		if rlp.item_count().map_err(|e| e.to_string())? != items {
+			return Err(format!("block is not a list of {} items", items));
+		}
+		let ommers = decode_ommers(&rlp, &header)?;
+		let transactions = decode_transactions(&rlp, &header, config)?;
+		let withdrawals = decode_withdrawals(&rlp, &header)?;
+
+		let block = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: self.block_hashes(header.parent_hash),
+			block_number: header.number,
+			block_coinbase: header.beneficiary,
+			block_timestamp: header.timestamp,
+			block_difficulty: header.difficulty,
+			block_prev_randao: header.mix_hash,
+			block_gas_limit: header.gas_limit,
+			block_base_fee_per_gas: header.base_fee.unwrap_or_default(),
+			block_blob_base_fee: header
+				.excess_blob_gas
+				.map(blob_base_fee)
+				.unwrap_or_default(),
+			blob_hashes: Vec::new(),
+		};
+		let mut state = parent.state.clone();
+		if let Some(root) = header.parent_beacon_block_root {
+			transaction::system_call(
+				config,
+				&block,
+				&mut state,
+				transaction::BEACON_ROOTS_ADDRESS,
+				root.as_bytes().to_vec(),
+			);
+		}
+		let mut cumulative_gas = U256::zero();
+		let mut blob_gas = U256::zero();
+		let mut receipts = Vec::new();
+		let mut logs = Vec::new();
+		for (i, transaction) in transactions.iter().enumerate() {
+			let receipt = transaction::transact(
+				config,
+				&block,
+				&mut state,
+				transaction,
+				header.gas_limit - cumulative_gas,
+			)
+			.map_err(|e| format!("transaction {}: {}", i, e))?;
+			cumulative_gas += U256::from(receipt.used_gas);
+			blob_gas += transaction.blob_gas();
+
+			let mut stream = RlpStream::new_list(4);
+			if config.has_revert {
+				// EIP-658, together with `REVERT` in Byzantium.
+				stream.append(&u8::from(receipt.succeeded));
+			} else {
+				stream.append(&utils::state_root(&state));
+			}
+			stream.append(&cumulative_gas);
+			stream.append(&utils::logs_bloom(&receipt.logs));
+			utils::append_logs(&mut stream, &receipt.logs);
+			receipts.push(typed_envelope(transaction.kind, stream.out().to_vec()));
+			logs.extend(receipt.logs);
+		}
+
+		let reward = utils::block_reward(fork);
+		let mut miner_reward = reward;
+		for ommer in &ommers {
+			miner_reward += reward / 32;
+			let distance = header.number - ommer.number;
+			let ommer_reward = reward * (U256::from(8) - distance) / 8;
+			reward_account(&mut state, ommer.beneficiary, ommer_reward);
+		}
+		reward_account(&mut state, header.beneficiary, miner_reward);
+		for (address, amount) in withdrawals {
+			reward_account(&mut state, address, amount);
+		}
+
+		if header.gas_used != cumulative_gas {
+			return Err(format!(
+				"gas used {}, expected {}",
+				cumulative_gas, header.gas_used
+			));
+		}
+		if let Some(blob_gas_used) = header.blob_gas_used {
+			if blob_gas_used != blob_gas {
+				return Err(format!(
+					"blob gas used {}, expected {}",
+					blob_gas, blob_gas_used
+				));
+			}
+		}
+		let receipts_root = triehash::ordered_trie_root::<utils::KeccakHasher, _>(receipts);
+		if header.receipts_root != receipts_root {
+			return Err(format!(
+				"receipts root {:?}, expected {:?}",
+				receipts_root, header.receipts_root
+			));
+		}
+		if header.logs_bloom != utils::logs_bloom(&logs) {
+			return Err("logs bloom mismatch".into());
+		}
+		let state_root = utils::state_root(&state);
+		if header.state_root != state_root {
+			return Err(format!(
+				"state root {:?}, expected {:?}",
+				state_root, header.state_root
+			));
+		}
+
+		let imported = ImportedBlock {
+			total_difficulty: parent.total_difficulty + header.difficulty,
+			header,
+			state,
+		};
+		// After the merge, the latest valid block is the head.
+		if imported.total_difficulty > self.head().total_difficulty || config.has_prevrandao {
+			self.head = imported.header.hash;
+		}
+		self.blocks.insert(imported.header.hash, imported);
+		Ok(())
+	}
+}
+
+fn validate_header(
+	header: &Header,
+	parent: &Header,
+	config: &Config,
+	parent_config: &Config,
+) -> Result<(), String> {
+	if header.number != parent.number + 1 {
+		return Err("number is not the parent's plus one".into());
+	}
+	if header.timestamp <= parent.timestamp {
+		return Err("timestamp not after the parent's".into());
+	}
+	if header.extra_data.len() > 32 {
+		return Err("extra data too long".into());
+	}
+	if header.gas_used > header.gas_limit {
+		return Err("gas used above gas limit".into());
+	}
+	if config.has_prevrandao && !header.difficulty.is_zero() {
+		return Err("difficulty after the merge".into());
+	}
+	if header.withdrawals_root.is_some() != config.has_push0 {
+		return Err("withdrawals root before Shanghai or missing after".into());
+	}
+	// Blob transactions and the beacon root arrived with `BLOBHASH` in Cancun.
+	let cancun = [
+		header.blob_gas_used.is_some(),
+		header.excess_blob_gas.is_some(),
+		header.parent_beacon_block_root.is_some(),
+	];
+	if cancun
+		.iter()
+		.any(|present| *present != config.has_blob_hash)
+	{
+		return Err("blob gas or beacon root before Cancun or missing after".into());
+	}
+	if let Some(excess_blob_gas) = header.excess_blob_gas {
+		// Both are zero at the fork.
+		let expected = next_excess_blob_gas(
+			parent.excess_blob_gas.unwrap_or_default(),
+			parent.blob_gas_used.unwrap_or_default(),
+		);
+		if excess_blob_gas != expected {
+			return Err(format!(
+				"excess blob gas {}, expected {}",
+				excess_blob_gas, expected
+			));
+		}
+	}
+	if header.blob_gas_used > Some(U256::from(MAX_BLOB_GAS_PER_BLOCK)) {
+		return Err("blob gas used above the maximum".into());
+	}
+
+	// EIP-1559 doubles the gas limit at the fork, keeping the gas target.
+	let parent_gas_limit = if config.has_base_fee && !parent_config.has_base_fee {
+		parent.gas_limit * 2
+	} else {
+		parent.gas_limit
+	};
+	let difference = if header.gas_limit > parent_gas_limit {
+		header.gas_limit - parent_gas_limit
+	} else {
+		parent_gas_limit - header.gas_limit
+	};
+	if difference >= parent_gas_limit / 1024
+		|| header.gas_limit < U256::from(5000)
+		|| header.gas_limit > U256::from(i64::MAX)
+	{
+		return Err("gas limit out of bounds".into());
+	}
+
+	let base_fee = if config.has_base_fee {
+		Some(expected_base_fee(parent, parent_config))
+	} else {
+		None
+	};
+	if header.base_fee != base_fee {
+		return Err(format!(
+			"base fee {:?}, expected {:?}",
+			header.base_fee, base_fee
+		));
+	}
+
+	Ok(())
+}
+
+/// Base fee of the child of `parent`, as defined by EIP-1559.
+fn expected_base_fee(parent: &Header, parent_config: &Config) -> U256 {
+	match parent.base_fee {
+		Some(base_fee) if parent_config.has_base_fee => next_base_fee(
+			base_fee,
+			parent.gas_used,
+			parent.gas_limit / ELASTICITY_MULTIPLIER,
+		),
+		_ => U256::from(INITIAL_BASE_FEE),
+	}
+}
+
+fn decode_ommers(block: &Rlp, header: &Header) -> Result<Vec<Header>, String> {
+	let ommers = block.at(2).map_err(|e| e.to_string())?;
+	if utils::keccak(ommers.as_raw()) != header.ommers_hash {
+		return Err("ommers hash mismatch".into());
+	}
+
+	ommers
+		.iter()
+		.map(|ommer| {
+			let ommer = Header::decode(&ommer).map_err(|e| format!("ommer: {}", e))?;
+			if ommer.number >= header.number || header.number - ommer.number > U256::from(6) {
+				return Err("ommer too old or too new".into());
+			}
+			Ok(ommer)
+		})
+		.collect()
+}
+
+/// Recipients and amounts in Wei of the withdrawals of a block, empty before
+/// Shanghai.
+fn decode_withdrawals(block: &Rlp, header: &Header) -> Result<Vec<(H160, U256)>, String> {
+	let root = match header.withdrawals_root {
+		Some(root) => root,
+		None => return Ok(Vec::new()),
+	};
+	let withdrawals = block.at(3).map_err(|e| e.to_string())?;
+	let encoded = withdrawals
+		.iter()
+		.map(|item| item.as_raw().to_vec())
+		.collect::<Vec<_>>();
+	if triehash::ordered_trie_root::<utils::KeccakHasher, _>(encoded) != root {
+		return Err("withdrawals root mismatch".into());
+	}
+
+	// Index, validator index, address and amount in Gwei.
+	withdrawals
+		.iter()
+		.map(|item| {
+			if item.item_count()? != 4 {
+				return Err(DecoderError::RlpIncorrectListLen);
+			}
+			let amount: U256 = item.val_at(3)?;
+			Ok((item.val_at(2)?, amount * U256::exp10(9)))
+		})
+		.collect::<Result<_, DecoderError>>()
+		.map_err(|e| format!("withdrawal: {}", e))
+}
+
+fn decode_transactions(
+	block: &Rlp,
+	header: &Header,
+	config: &Config,
+) -> Result<Vec<Transaction>, String> {
+	let mut encoded = Vec::new();
+	let mut transactions = Vec::new();
+	for (i, item) in block.at(1).map_err(|e| e.to_string())?.iter().enumerate() {
+		let transaction = decode_transaction(&item, config)
+			.map_err(|e| format!("transaction {}: {}", i, e))?
+			.ok_or_else(|| format!("transaction {}: invalid signature", i))?;
+		encoded.push(if item.is_list() {
+			item.as_raw().to_vec()
+		} else {
+			item.data().map_err(|e| e.to_string())?.to_vec()
+		});
+		transactions.push(transaction);
+	}
+
+	if triehash::ordered_trie_root::<utils::KeccakHasher, _>(encoded) != header.transactions_root {
+		return Err("transactions root mismatch".into());
+	}
+	Ok(transactions)
+}
+
+/// Decode a transaction and recover its sender, returning `None` if the
+/// signature is invalid.
+fn decode_transaction(item: &Rlp, config: &Config) -> Result<Option<Transaction>, DecoderError> {
+	let homestead = config.has_delegate_call;
+
+	if item.is_list() {
+		if item.item_count()? != 9 {
+			return Err(DecoderError::RlpIncorrectListLen);
+		}
+		// EIP-155 replay protection arrived with EIP-161 in Spurious Dragon.
+		let v: u64 = item.val_at(6)?;
+		let (chain_id, recovery_id) = match v {
+			27 | 28 => (None, v - 27),
+			v if v >= 35 && !config.empty_considered_exists => (Some((v - 35) / 2), (v - 35) % 2),
+			_ => return Ok(None),
+		};
+		if matches!(chain_id, Some(chain_id) if chain_id != 1) {
+			return Ok(None);
+		}
+
+		let mut stream = RlpStream::new_list(if chain_id.is_some() { 9 } else { 6 });
+		for i in 0..6 {
+			stream.append_raw(item.at(i)?.as_raw(), 1);
+		}
+		if let Some(chain_id) = chain_id {
+			stream.append(&chain_id);
+			stream.append(&0u8);
+			stream.append(&0u8);
+		}
+		let caller = match utils::recover_signer(
+			utils::keccak(&stream.out()),
+			recovery_id as u8,
+			item.val_at(7)?,
+			item.val_at(8)?,
+			homestead,
+		) {
+			Some(caller) => caller,
+			None => return Ok(None),
+		};
+
+		return Ok(Some(Transaction {
+			kind: TransactionKind::Legacy,
+			caller,
+			nonce: item.val_at(0)?,
+			gas_limit: item.val_at(2)?,
+			max_fee: item.val_at(1)?,
+			priority_fee: None,
+			to: decode_to(&item.at(3)?)?,
+			value: item.val_at(4)?,
+			data: item.val_at(5)?,
+			access_list: Vec::new(),
+			max_fee_per_blob_gas: None,
+			blob_hashes: Vec::new(),
+		}));
+	}
+
+	let data = item.data()?;
+	let (kind, fields) = match data.split_first() {
+		Some((1, payload)) => (TransactionKind::AccessList, payload),
+		Some((2, payload)) => (TransactionKind::DynamicFee, payload),
+		Some((3, payload)) => (TransactionKind::Blob, payload),
+		_ => return Err(DecoderError::Custom("unknown transaction type")),
+	};
+	let rlp = Rlp::new(fields);
+	let blob = kind == TransactionKind::Blob;
+	// Dynamic fee and blob transactions have a separate tip after the chain id
+	// and nonce. Blob transactions end with a blob fee cap and blob hashes.
+	let offset = usize::from(kind == TransactionKind::DynamicFee || blob);
+	let count = 11 + offset + if blob { 2 } else { 0 };
+	if rlp.item_count()? != count {
+		return Err(DecoderError::RlpIncorrectListLen);
+	}
+	if rlp.val_at::<U256>(0)? != U256::one() {
+		return Ok(None);
+	}
+
+	let mut stream = RlpStream::new_list(count - 3);
+	for i in 0..count - 3 {
+		stream.append_raw(rlp.at(i)?.as_raw(), 1);
+	}
+	let mut signed = vec![data[0]];
+	signed.extend_from_slice(&stream.out());
+	let recovery_id: u8 = rlp.val_at(count - 3)?;
+	let caller = match utils::recover_signer(
+		utils::keccak(&signed),
+		recovery_id,
+		rlp.val_at(count - 2)?,
+		rlp.val_at(count - 1)?,
+		homestead,
+	) {
+		Some(caller) => caller,
+		None => return Ok(None),
+	};
+
+	let access_list = rlp
+		.at(7 + offset)?
+		.iter()
+		.map(|item| Ok((item.val_at(0)?, item.list_at(1)?)))
+		.collect::<Result<Vec<(H160, Vec<H256>)>, DecoderError>>()?;
+	Ok(Some(Transaction {
+		kind,
+		caller,
+		nonce: rlp.val_at(1)?,
+		gas_limit: rlp.val_at(3 + offset)?,
+		max_fee: rlp.val_at(2 + offset)?,
+		priority_fee: if offset == 1 {
+			Some(rlp.val_at(2)?)
+		} else {
+			None
+		},
+		to: decode_to(&rlp.at(4 + offset)?)?,
+		value: rlp.val_at(5 + offset)?,
+		data: rlp.val_at(6 + offset)?,
+		access_list,
+		max_fee_per_blob_gas: if blob { Some(rlp.val_at(9)?) } else { None },
+		blob_hashes: if blob { rlp.list_at(10)? } else { Vec::new() },
+	}))
+}
+
+/// Recipient of a transaction, empty for contract creation.
+fn decode_to(rlp: &Rlp) -> Result<Option<H160>, DecoderError> {
+	if rlp.is_empty() {
+		Ok(None)
+	} else {
+		rlp.as_val().map(Some)
+	}
+}
+
+/// EIP-2718 encoding of a typed receipt or transaction.
+fn typed_envelope(kind: TransactionKind, payload: Vec<u8>) -> Vec<u8> {
+	match kind {
+		TransactionKind::Legacy => payload,
+		TransactionKind::AccessList => [&[1], &payload[..]].concat(),
+		TransactionKind::DynamicFee => [&[2], &payload[..]].concat(),
+		TransactionKind::Blob => [&[3], &payload[..]].concat(),
+	}
+}
+
+fn reward_account(state: &mut BTreeMap<H160, MemoryAccount>, address: H160, reward: U256) {
+	if reward.is_zero() {
+		return;
+	}
+	let account = state.entry(address).or_insert_with(|| MemoryAccount {
+		nonce: U256::zero(),
+		balance: U256::zero(),
+		storage: BTreeMap::new(),
+		code: Vec::new(),
+	});
+	account.balance += reward;
+}
+
+/// Import the blocks of a test and compare the resulting head and state.
+pub fn run_test(name: &str, test: &BlockchainTest, summary: &mut Summary) {
+	let fork_summary = summary.forks.entry(test.network.clone()).or_default();
+	let schedule = match Schedule::parse(&test.network) {
+		Some(schedule) if test.seal_engine.as_deref().unwrap_or("NoProof") == "NoProof" => schedule,
+		_ => {
+			fork_summary.skipped += 1;
+			return;
+		}
+	};
+
+	match check(test, &schedule) {
+		Ok(()) => fork_summary.passed += 1,
+		Err(e) => {
+			fork_summary.failed += 1;
+			summary
+				.failures
+				.push(format!("{} {}: {}", name, test.network, e));
+		}
+	}
+}
+
+fn check(test: &BlockchainTest, schedule: &Schedule) -> Result<(), String> {
+	let genesis =
+		Header::decode(&Rlp::new(&test.genesis_rlp.0)).map_err(|e| format!("genesis: {}", e))?;
+	let pre = test
+		.pre
+		.iter()
+		.map(|(address, account)| (address.0, account.to_memory()))
+		.collect();
+	if utils::state_root(&pre) != genesis.state_root {
+		return Err("genesis state root mismatch".into());
+	}
+
+	let mut chain = Chain::new(schedule, genesis, pre);
+	for (i, block) in test.blocks.iter().enumerate() {
+		let result = hex::decode(block.rlp.trim_start_matches("0x"))
+			.map_err(|e| e.to_string())
+			.and_then(|bytes| chain.import(&bytes));
+		match (result, block.block_header.is_some()) {
+			(Ok(()), false) => return Err(format!("block {}: invalid block imported", i)),
+			(Err(e), true) => return Err(format!("block {}: {}", i, e)),
+			_ => (),
+		}
+	}
+
+	let head = chain.head();
+	if head.header.hash != test.lastblockhash.0 {
+		return Err(format!(
+			"head {:?}, expected {}",
+			head.header.hash, test.lastblockhash
+		));
+	}
+	let expected = match (&test.post_state, test.post_state_hash) {
+		(Some(post), _) => utils::state_root(
+			&post
+				.iter()
+				.map(|(address, account)| (address.0, account.to_memory()))
+				.collect(),
+		),
+		(None, Some(hash)) => hash.0,
+		(None, None) => return Err("no post-state".into()),
+	};
+	if utils::state_root(&head.state) != expected {
+		return Err("post-state mismatch".into());
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_decode_eip155_transaction() {
+		// The example of EIP-155, signed with the key 0x4646...46.
+		let bytes = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+		let transaction = decode_transaction(&Rlp::new(&bytes), &Config::istanbul())
+			.unwrap()
+			.unwrap();
+
+		assert_eq!(
+			transaction.caller,
+			utils::address_of(H256::repeat_byte(0x46)).unwrap()
+		);
+		assert_eq!(transaction.nonce, U256::from(9));
+		assert_eq!(transaction.to, Some(H160::repeat_byte(0x35)));
+		assert!(decode_transaction(&Rlp::new(&bytes), &Config::frontier())
+			.unwrap()
+			.is_none());
+	}
+
+	/// Header of an empty block after `parent`, with the given withdrawals
+	/// root from Shanghai, decoded and encoded.
+	fn header(
+		parent: Option<&Header>,
+		timestamp: u64,
+		state_root: H256,
+		withdrawals_root: Option<H256>,
+	) -> (Header, Vec<u8>) {
+		let empty_root = triehash::ordered_trie_root::<utils::KeccakHasher, Vec<Vec<u8>>>(vec![]);
+		let mut stream = RlpStream::new_list(16 + usize::from(withdrawals_root.is_some()));
+		stream.append(&parent.map(|p| p.hash).unwrap_or_default());
+		stream.append(&utils::keccak(&rlp::EMPTY_LIST_RLP));
+		stream.append(&H160::zero());
+		stream.append(&state_root);
+		stream.append(&empty_root);
+		stream.append(&empty_root);
+		stream.append(&vec![0u8; 256]);
+		stream.append(&U256::zero());
+		stream.append(&parent.map(|p| p.number + 1).unwrap_or_default());
+		stream.append(&U256::from(30_000_000));
+		stream.append(&U256::zero());
+		stream.append(&timestamp);
+		stream.append_empty_data();
+		stream.append(&H256::zero());
+		stream.append(&vec![0u8; 8]);
+		stream.append(&match parent {
+			Some(parent) => expected_base_fee(parent, &Config::merge()),
+			None => U256::from(INITIAL_BASE_FEE),
+		});
+		if let Some(root) = withdrawals_root {
+			stream.append(&root);
+		}
+		let bytes = stream.out().to_vec();
+		(Header::decode(&Rlp::new(&bytes)).unwrap(), bytes)
+	}
+
+	fn block(header: &[u8], withdrawals: Option<&[Vec<u8>]>) -> Vec<u8> {
+		let mut stream = RlpStream::new_list(3 + usize::from(withdrawals.is_some()));
+		stream.append_raw(header, 1);
+		stream.begin_list(0);
+		stream.begin_list(0);
+		if let Some(withdrawals) = withdrawals {
+			stream.begin_list(withdrawals.len());
+			for withdrawal in withdrawals {
+				stream.append_raw(withdrawal, 1);
+			}
+		}
+		stream.out().to_vec()
+	}
+
+	#[test]
+	fn test_shanghai_withdrawals() {
+		let schedule = Schedule::parse("MergeToShanghaiAtTime15k").unwrap();
+		let recipient = H160::repeat_byte(0x42);
+		let mut withdrawal = RlpStream::new_list(4);
+		withdrawal
+			.append(&0u8)
+			.append(&7u8)
+			.append(&recipient)
+			.append(&3u8);
+		let withdrawals = vec![withdrawal.out().to_vec()];
+		let withdrawals_root =
+			triehash::ordered_trie_root::<utils::KeccakHasher, _>(withdrawals.clone());
+
+		let mut state = BTreeMap::new();
+		let (genesis, _) = header(None, 0, utils::state_root(&state), None);
+		let (merge, merge_rlp) = header(Some(&genesis), 14_999, utils::state_root(&state), None);
+		reward_account(&mut state, recipient, U256::from(3_000_000_000u64));
+		let state_root = utils::state_root(&state);
+		let (shanghai, shanghai_rlp) =
+			header(Some(&merge), 15_000, state_root, Some(withdrawals_root));
+		assert_eq!(schedule.fork(&merge), Fork::Merge);
+		assert_eq!(schedule.fork(&shanghai), Fork::Shanghai);
+
+		let mut chain = Chain::new(&schedule, genesis, BTreeMap::new());
+		chain.import(&block(&merge_rlp, None)).unwrap();
+		// Shanghai blocks have a list of withdrawals.
+		assert!(chain.import(&block(&shanghai_rlp, None)).is_err());
+		chain
+			.import(&block(&shanghai_rlp, Some(&withdrawals)))
+			.unwrap();
+		assert_eq!(chain.head, shanghai.hash);
+		assert_eq!(chain.head().state, state);
+	}
+	#[test]
+	fn test_beacon_root_system_call() {
+		let root = H256::repeat_byte(0xbe);
+		let mut state = BTreeMap::new();
+		// SSTORE(0, CALLDATALOAD(0))
+		state.insert(
+			transaction::BEACON_ROOTS_ADDRESS,
+			MemoryAccount {
+				nonce: U256::one(),
+				balance: U256::zero(),
+				storage: BTreeMap::new(),
+				code: vec![0x60, 0x00, 0x35, 0x60, 0x00, 0x55, 0x00],
+			},
+		);
+		let block = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::one(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::one(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::from(30_000_000),
+			block_base_fee_per_gas: U256::from(7),
+			block_blob_base_fee: U256::one(),
+			blob_hashes: Vec::new(),
+		};
+
+		transaction::system_call(
+			&Config::cancun(),
+			&block,
+			&mut state,
+			transaction::BEACON_ROOTS_ADDRESS,
+			root.as_bytes().to_vec(),
+		);
+		assert_eq!(
+			state[&transaction::BEACON_ROOTS_ADDRESS].storage[&H256::zero()],
+			root
+		);
+		// The system address is neither created nor charged.
+		assert!(!state.contains_key(&transaction::SYSTEM_ADDRESS));
+	}
+}
@@ -0,0 +1,92 @@
+//! # Ethereum JSON tests
+//!
+//! Runs the fixtures of the [Ethereum tests](https://github.com/ethereum/tests)
+//! repository against the executor and reports the results per fork.
+
+pub mod blockchain;
+pub mod state;
+pub mod transaction;
+pub mod utils;
+
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Pass and fail counts of one fork.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ForkSummary {
+	pub passed: usize,
+	pub failed: usize,
+	/// Cases of forks without a configuration.
+	pub skipped: usize,
+}
+
+/// Results of a test run.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+	pub forks: BTreeMap<String, ForkSummary>,
+	/// One line per failed case or unreadable file.
+	pub failures: Vec<String>,
+}
+
+impl Summary {
+	/// Whether any case failed or any file could not be read.
+	pub fn is_success(&self) -> bool {
+		self.failures.is_empty()
+	}
+}
+
+impl fmt::Display for Summary {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for failure in &self.failures {
+			writeln!(f, "FAILED {}", failure)?;
+		}
+		for (fork, summary) in &self.forks {
+			writeln!(
+				f,
+				"{}: {} passed, {} failed, {} skipped",
+				fork, summary.passed, summary.failed, summary.skipped
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// Run every test of every `.json` file under `path`, which may also be a
+/// single file, with `run_test`.
+pub fn run_path<T: DeserializeOwned>(
+	path: &Path,
+	summary: &mut Summary,
+	run_test: fn(&str, &T, &mut Summary),
+) {
+	if path.is_dir() {
+		let mut entries = match fs::read_dir(path) {
+			Ok(entries) => entries
+				.filter_map(Result::ok)
+				.map(|e| e.path())
+				.collect::<Vec<_>>(),
+			Err(e) => return summary.failures.push(format!("{}: {}", path.display(), e)),
+		};
+		entries.sort();
+		for entry in entries {
+			if entry.is_dir() || entry.extension() == Some("json".as_ref()) {
+				run_path(&entry, summary, run_test);
+			}
+		}
+		return;
+	}
+
+	let tests = fs::read_to_string(path)
+		.map_err(|e| e.to_string())
+		.and_then(|s| serde_json::from_str::<BTreeMap<String, T>>(&s).map_err(|e| e.to_string()));
+	match tests {
+		Ok(tests) => {
+			for (name, test) in &tests {
+				run_test(name, test, summary);
+			}
+		}
+		Err(e) => summary.failures.push(format!("{}: {}", path.display(), e)),
+	}
+}
@@ -0,0 +1,31 @@
+use evm_jsontests::{blockchain, run_path, state, Summary};
+use std::env;
+use std::path::Path;
+use std::process;
+
+fn main() {
+	let args = env::args().skip(1).collect::<Vec<_>>();
+	let run: fn(&Path, &mut Summary) = match args.first().map(String::as_str) {
+		Some("state") => |path, summary| run_path(path, summary, state::run_test),
+		Some("blockchain") => |path, summary| run_path(path, summary, blockchain::run_test),
+		_ => usage(),
+	};
+	if args.len() < 2 {
+		usage();
+	}
+
+	let mut summary = Summary::default();
+	for path in &args[1..] {
+		run(Path::new(path), &mut summary);
+	}
+
+	print!("{}", summary);
+	if !summary.is_success() {
+		process::exit(1);
+	}
+}
+
+fn usage() -> ! {
+	eprintln!("usage: jsontests <state|blockchain> <fixture file or directory>...");
+	process::exit(2);
+}
@@ -0,0 +1,344 @@
+//! `GeneralStateTests`: one transaction applied to a pre-state, with the
+//! expected post-state root and logs hash for each fork and each combination
+//! of the transaction's data, gas limit and value.
+
+use crate::transaction::{self, TransactionKind};
+use crate::utils::{self, Hex};
+use crate::Summary;
+use evm::backend::{MemoryAccount, MemoryVicinity};
+use evm::executor::stack::blob_base_fee;
+use evm::Config;
+use primitive_types::{H160, H256, U256};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StateTest {
+	pub env: Env,
+	pub pre: BTreeMap<Hex<H160>, Account>,
+	pub transaction: Transaction,
+	pub post: BTreeMap<String, Vec<PostState>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Env {
+	pub current_coinbase: Hex<H160>,
+	pub current_difficulty: Hex<U256>,
+	#[serde(default)]
+	pub current_random: Option<Hex<H256>>,
+	pub current_gas_limit: Hex<U256>,
+	pub current_number: Hex<U256>,
+	pub current_timestamp: Hex<U256>,
+	#[serde(default)]
+	pub current_base_fee: Option<Hex<U256>>,
+	#[serde(default)]
+	pub current_excess_blob_gas: Option<Hex<U256>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Account {
+	pub balance: Hex<U256>,
+	pub code: Hex<Vec<u8>>,
+	pub nonce: Hex<U256>,
+	pub storage: BTreeMap<Hex<H256>, Hex<H256>>,
+}
+
+impl Account {
+	pub fn to_memory(&self) -> MemoryAccount {
+		MemoryAccount {
+			nonce: self.nonce.0,
+			balance: self.balance.0,
+			storage: self.storage.iter().map(|(k, v)| (k.0, v.0)).collect(),
+			code: self.code.0.clone(),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+	pub data: Vec<Hex<Vec<u8>>>,
+	pub gas_limit: Vec<Hex<U256>>,
+	pub value: Vec<Hex<U256>>,
+	#[serde(default)]
+	pub gas_price: Option<Hex<U256>>,
+	#[serde(default)]
+	pub max_fee_per_gas: Option<Hex<U256>>,
+	#[serde(default)]
+	pub max_priority_fee_per_gas: Option<Hex<U256>>,
+	pub nonce: Hex<U256>,
+	pub secret_key: Hex<H256>,
+	/// Empty for contract creation.
+	pub to: String,
+	#[serde(default)]
+	pub access_lists: Vec<Option<Vec<AccessListItem>>>,
+	#[serde(default)]
+	pub max_fee_per_blob_gas: Option<Hex<U256>>,
+	/// Present for blob transactions only, possibly empty.
+	#[serde(default)]
+	pub blob_versioned_hashes: Option<Vec<Hex<H256>>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+	pub address: Hex<H160>,
+	pub storage_keys: Vec<Hex<H256>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostState {
+	pub hash: Hex<H256>,
+	pub logs: Hex<H256>,
+	pub indexes: Indexes,
+	/// Reason the transaction is invalid, if it is expected to be rejected.
+	#[serde(default)]
+	pub expect_exception: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Indexes {
+	pub data: usize,
+	pub gas: usize,
+	pub value: usize,
+}
+
+/// Run every post-state of a test.
+pub fn run_test(name: &str, test: &StateTest, summary: &mut Summary) {
+	for (fork, posts) in &test.post {
+		let fork_summary = summary.forks.entry(fork.clone()).or_default();
+		let config = match utils::config(fork) {
+			Some(config) => config,
+			None => {
+				fork_summary.skipped += posts.len();
+				continue;
+			}
+		};
+
+		for (i, post) in posts.iter().enumerate() {
+			match check(test, &config, post) {
+				Ok(()) => fork_summary.passed += 1,
+				Err(e) => {
+					fork_summary.failed += 1;
+					summary
+						.failures
+						.push(format!("{} {} #{}: {}", name, fork, i, e));
+				}
+			}
+		}
+	}
+}
+
+fn check(test: &StateTest, config: &Config, post: &PostState) -> Result<(), String> {
+	let (root, logs, error) = execute(test, config, post.indexes)?;
+	match (&post.expect_exception, error) {
+		(Some(exception), None) => {
+			return Err(format!("transaction accepted, expected {}", exception));
+		}
+		(None, Some(error)) => return Err(format!("transaction rejected: {}", error)),
+		_ => (),
+	}
+	if root != post.hash.0 {
+		return Err(format!("state root {:?}, expected {}", root, post.hash));
+	}
+	if logs != post.logs.0 {
+		return Err(format!("logs hash {:?}, expected {}", logs, post.logs));
+	}
+	Ok(())
+}
+
+/// Apply the transaction selected by `indexes` to the pre-state, returning
+/// the post-state root, the logs hash and why the transaction was rejected,
+/// if it was.
+pub fn execute(
+	test: &StateTest,
+	config: &Config,
+	indexes: Indexes,
+) -> Result<(H256, H256, Option<String>), String> {
+	let tx = &test.transaction;
+	let env = &test.env;
+
+	let data = tx
+		.data
+		.get(indexes.data)
+		.ok_or("data index out of range")?
+		.0
+		.clone();
+	let gas_limit = tx
+		.gas_limit
+		.get(indexes.gas)
+		.ok_or("gas index out of range")?
+		.0;
+	let value = tx
+		.value
+		.get(indexes.value)
+		.ok_or("value index out of range")?
+		.0;
+	let access_list = tx
+		.access_lists
+		.get(indexes.data)
+		.cloned()
+		.flatten()
+		.unwrap_or_default()
+		.into_iter()
+		.map(|item| {
+			(
+				item.address.0,
+				item.storage_keys.into_iter().map(|k| k.0).collect(),
+			)
+		})
+		.collect::<Vec<(H160, Vec<H256>)>>();
+	let to = match tx.to.as_str() {
+		"" => None,
+		to => Some(H160::from_slice(
+			&hex::decode(to.trim_start_matches("0x")).map_err(|e| e.to_string())?,
+		)),
+	};
+
+	let transaction = transaction::Transaction {
+		kind: if tx.blob_versioned_hashes.is_some() {
+			TransactionKind::Blob
+		} else if tx.max_fee_per_gas.is_some() {
+			TransactionKind::DynamicFee
+		} else if !tx.access_lists.is_empty() {
+			TransactionKind::AccessList
+		} else {
+			TransactionKind::Legacy
+		},
+		caller: utils::address_of(tx.secret_key.0).ok_or("invalid secret key")?,
+		nonce: tx.nonce.0,
+		gas_limit,
+		max_fee: tx
+			.gas_price
+			.or(tx.max_fee_per_gas)
+			.ok_or("missing gas price")?
+			.0,
+		priority_fee: tx.max_priority_fee_per_gas.map(|p| p.0),
+		to,
+		value,
+		data,
+		access_list,
+		max_fee_per_blob_gas: tx.max_fee_per_blob_gas.map(|f| f.0),
+		blob_hashes: tx
+			.blob_versioned_hashes
+			.iter()
+			.flatten()
+			.map(|hash| hash.0)
+			.collect(),
+	};
+
+	let block = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: transaction.caller,
+		chain_id: U256::one(),
+		block_hashes: block_hashes(env.current_number.0),
+		block_number: env.current_number.0,
+		block_coinbase: env.current_coinbase.0,
+		block_timestamp: env.current_timestamp.0,
+		block_difficulty: env.current_difficulty.0,
+		block_prev_randao: env.current_random.map(|r| r.0).unwrap_or_default(),
+		block_gas_limit: env.current_gas_limit.0,
+		block_base_fee_per_gas: env.current_base_fee.map(|f| f.0).unwrap_or_default(),
+		block_blob_base_fee: env
+			.current_excess_blob_gas
+			.map(|excess| blob_base_fee(excess.0))
+			.unwrap_or_default(),
+		blob_hashes: Vec::new(),
+	};
+	let mut state = test
+		.pre
+		.iter()
+		.map(|(address, account)| (address.0, account.to_memory()))
+		.collect();
+	// Invalid transactions are not included, leaving the state unchanged.
+	let (logs, error) = match transaction::transact(
+		config,
+		&block,
+		&mut state,
+		&transaction,
+		env.current_gas_limit.0,
+	) {
+		Ok(receipt) => (receipt.logs, None),
+		Err(e) => (Vec::new(), Some(e)),
+	};
+
+	Ok((utils::state_root(&state), utils::logs_hash(&logs), error))
+}
+
+/// Hashes of the 256 blocks before `number`, most recent first. State tests
+/// have no chain, so like the reference implementation the hash of block `n`
+/// is the Keccak-256 of `n` in decimal.
+fn block_hashes(number: U256) -> Vec<H256> {
+	let first = number.saturating_sub(U256::from(256));
+	let mut n = number;
+	let mut hashes = Vec::new();
+	while n > first {
+		n -= U256::one();
+		hashes.push(utils::keccak(n.to_string().as_bytes()));
+	}
+	hashes
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_block_hashes() {
+		let hashes = block_hashes(U256::from(2));
+		assert_eq!(hashes, vec![utils::keccak(b"1"), utils::keccak(b"0")]);
+		assert_eq!(block_hashes(U256::from(300)).len(), 256);
+		assert!(block_hashes(U256::zero()).is_empty());
+	}
+
+	#[test]
+	fn test_expect_exception() {
+		let test: StateTest = serde_json::from_value(serde_json::json!({
+			"env": {
+				"currentCoinbase": "0x2adc25665018aa1fe0e6bc666dac8fc2697ff9ba",
+				"currentDifficulty": "0x020000",
+				"currentGasLimit": "0x05f5e100",
+				"currentNumber": "0x01",
+				"currentTimestamp": "0x03e8",
+			},
+			"pre": {
+				"0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b": {
+					"balance": "0x0de0b6b3a7640000",
+					"code": "0x",
+					"nonce": "0x00",
+					"storage": {},
+				},
+			},
+			"transaction": {
+				"data": ["0x"],
+				"gasLimit": ["0x5208", "0x5207"],
+				"gasPrice": "0x0a",
+				"nonce": "0x00",
+				"secretKey": "0x45a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8",
+				"to": "0x1000000000000000000000000000000000000000",
+				"value": ["0x01"],
+			},
+			"post": {},
+		}))
+		.unwrap();
+		let indexes = |gas| Indexes {
+			data: 0,
+			gas,
+			value: 0,
+		};
+
+		let (_, _, error) = execute(&test, &Config::istanbul(), indexes(0)).unwrap();
+		assert_eq!(error, None);
+
+		let pre = test
+			.pre
+			.iter()
+			.map(|(address, account)| (address.0, account.to_memory()))
+			.collect();
+		let (root, _, error) = execute(&test, &Config::istanbul(), indexes(1)).unwrap();
+		assert!(error.is_some());
+		assert_eq!(root, utils::state_root(&pre));
+	}
+}
@@ -0,0 +1,284 @@
+//! Validation and execution of a single transaction, shared by the state and
+//! blockchain tests.
+
+use evm::backend::{ApplyBackend, Backend, Log, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata, GAS_PER_BLOB};
+use evm::executor::Executor;
+use evm::gasometer::{self, Gasometer};
+use evm::precompiles::StandardPrecompiles;
+use evm::Config;
+use primitive_types::{H160, H256, U256};
+use std::collections::BTreeMap;
+use std::mem;
+
+/// Envelope of a transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransactionKind {
+	Legacy,
+	/// EIP-2930, type 1.
+	AccessList,
+	/// EIP-1559, type 2.
+	DynamicFee,
+	/// EIP-4844, type 3.
+	Blob,
+}
+
+/// A transaction with its sender already recovered.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+	pub kind: TransactionKind,
+	pub caller: H160,
+	pub nonce: U256,
+	pub gas_limit: U256,
+	/// Gas price of legacy and access list transactions, fee cap of dynamic
+	/// fee ones.
+	pub max_fee: U256,
+	/// Tip cap of dynamic fee transactions.
+	pub priority_fee: Option<U256>,
+	/// `None` for contract creation.
+	pub to: Option<H160>,
+	pub value: U256,
+	pub data: Vec<u8>,
+	pub access_list: Vec<(H160, Vec<H256>)>,
+	/// Fee cap per unit of blob gas of blob transactions.
+	pub max_fee_per_blob_gas: Option<U256>,
+	/// Versioned hashes of the blobs of blob transactions.
+	pub blob_hashes: Vec<H256>,
+}
+
+impl Transaction {
+	/// Price paid per unit of gas in a block with `base_fee`.
+	pub fn gas_price(&self, base_fee: U256) -> U256 {
+		match self.priority_fee {
+			Some(priority_fee) => self.max_fee.min(base_fee.saturating_add(priority_fee)),
+			None => self.max_fee,
+		}
+	}
+
+	/// Blob gas used by the transaction.
+	pub fn blob_gas(&self) -> U256 {
+		U256::from(GAS_PER_BLOB) * self.blob_hashes.len()
+	}
+
+	/// Check the transaction against the sender account and the block before
+	/// executing it. `available_gas` is the gas left in the block.
+	pub fn validate(
+		&self,
+		config: &Config,
+		backend: &MemoryBackend,
+		available_gas: U256,
+	) -> Result<(), String> {
+		let base_fee = backend.block_base_fee_per_gas();
+		let account = backend.basic(self.caller);
+
+		match self.kind {
+			TransactionKind::DynamicFee if !config.has_base_fee => {
+				return Err("dynamic fee transaction before London".into())
+			}
+			TransactionKind::AccessList if !config.increase_state_access_gas => {
+				return Err("access list transaction before Berlin".into())
+			}
+			TransactionKind::Blob if !config.has_blob_hash => {
+				return Err("blob transaction before Cancun".into())
+			}
+			_ => (),
+		}
+		if self.kind == TransactionKind::Blob {
+			if self.to.is_none() {
+				return Err("blob transaction creating a contract".into());
+			}
+			if self.blob_hashes.is_empty() {
+				return Err("blob transaction without blobs".into());
+			}
+			// Version 1, KZG commitments.
+			if self.blob_hashes.iter().any(|hash| hash[0] != 1) {
+				return Err("unknown blob version".into());
+			}
+			if self.max_fee_per_blob_gas.unwrap_or_default() < backend.block_blob_base_fee() {
+				return Err("blob fee cap below blob base fee".into());
+			}
+		}
+		if self.nonce != account.nonce {
+			return Err(format!("nonce {}, expected {}", self.nonce, account.nonce));
+		}
+		if !backend.code(self.caller).is_empty() {
+			return Err("sender has code".into());
+		}
+		if let (None, Some(limit)) = (self.to, config.max_initcode_size) {
+			if self.data.len() > limit {
+				return Err("initcode too large".into());
+			}
+		}
+		if self.gas_limit > available_gas || self.gas_limit > U256::from(u64::MAX) {
+			return Err("gas limit above block gas limit".into());
+		}
+		if self.max_fee < base_fee {
+			return Err("fee cap below base fee".into());
+		}
+		if matches!(self.priority_fee, Some(priority_fee) if priority_fee > self.max_fee) {
+			return Err("tip above fee cap".into());
+		}
+		let blob_fee = self
+			.blob_gas()
+			.checked_mul(self.max_fee_per_blob_gas.unwrap_or_default());
+		let cost = self
+			.gas_limit
+			.checked_mul(self.max_fee)
+			.and_then(|fee| fee.checked_add(blob_fee?))
+			.and_then(|fee| fee.checked_add(self.value));
+		if !matches!(cost, Some(cost) if cost <= account.balance) {
+			return Err("insufficient balance".into());
+		}
+
+		let intrinsic = match self.to {
+			Some(_) => gasometer::call_transaction_cost(&self.data, &self.access_list),
+			None => gasometer::create_transaction_cost(&self.data, &self.access_list),
+		};
+		Gasometer::new(self.gas_limit.as_u64(), config)
+			.record_transaction(intrinsic)
+			.map_err(|_| "intrinsic gas above gas limit".to_string())
+	}
+}
+
+/// Sender of system calls.
+pub const SYSTEM_ADDRESS: H160 = H160([
+	0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	0xff, 0xff, 0xff, 0xfe,
+]);
+/// Contract keeping the roots of the recent beacon blocks, see
+/// [EIP-4788](https://eips.ethereum.org/EIPS/eip-4788).
+pub const BEACON_ROOTS_ADDRESS: H160 = H160([
+	0x00, 0x0f, 0x3d, 0xf6, 0xd7, 0x32, 0x80, 0x7e, 0xf1, 0x31, 0x9f, 0xb7, 0xb8, 0xbb, 0x85, 0x22,
+	0xd0, 0xbe, 0xac, 0x02,
+]);
+const SYSTEM_CALL_GAS: u64 = 30_000_000;
+
+/// Call the contract at `address` from [`SYSTEM_ADDRESS`] at the start of a
+/// block. The call is free and leaves the system address untouched. Accounts
+/// without code are not called.
+pub fn system_call(
+	config: &Config,
+	block: &MemoryVicinity,
+	state: &mut BTreeMap<H160, MemoryAccount>,
+	address: H160,
+	data: Vec<u8>,
+) {
+	match state.get(&address) {
+		Some(account) if !account.code.is_empty() => (),
+		_ => return,
+	}
+	let system = state.get(&SYSTEM_ADDRESS).cloned();
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: SYSTEM_ADDRESS,
+		..block.clone()
+	};
+	let mut backend = MemoryBackend::new(&vicinity, mem::take(state));
+
+	let metadata = StackSubstateMetadata::new(SYSTEM_CALL_GAS, config);
+	let state_ = MemoryStackState::new(metadata, &backend);
+	let precompiles = StandardPrecompiles::new(config);
+	let mut executor = StackExecutor::new_with_precompiles(state_, config, &precompiles);
+	executor.transact_call(
+		SYSTEM_ADDRESS,
+		address,
+		U256::zero(),
+		data,
+		SYSTEM_CALL_GAS,
+		Vec::new(),
+	);
+	let (values, _) = executor.into_state().deconstruct();
+	backend.apply(values, Vec::new(), !config.empty_considered_exists);
+
+	*state = mem::take(backend.state_mut());
+	match system {
+		Some(account) => state.insert(SYSTEM_ADDRESS, account),
+		None => state.remove(&SYSTEM_ADDRESS),
+	};
+}
+
+/// Result of an executed transaction.
+#[derive(Clone, Debug)]
+pub struct Receipt {
+	pub succeeded: bool,
+	pub used_gas: u64,
+	pub logs: Vec<Log>,
+}
+
+/// Validate and execute `transaction` in the block described by `block`,
+/// buying its gas and paying the fees to the coinbase. Invalid transactions
+/// leave `state` unchanged.
+pub fn transact(
+	config: &Config,
+	block: &MemoryVicinity,
+	state: &mut BTreeMap<H160, MemoryAccount>,
+	transaction: &Transaction,
+	available_gas: U256,
+) -> Result<Receipt, String> {
+	let base_fee = block.block_base_fee_per_gas;
+	let gas_price = transaction.gas_price(base_fee);
+	let vicinity = MemoryVicinity {
+		gas_price,
+		origin: transaction.caller,
+		blob_hashes: transaction.blob_hashes.clone(),
+		..block.clone()
+	};
+	let mut backend = MemoryBackend::new(&vicinity, mem::take(state));
+
+	let result = transaction
+		.validate(config, &backend, available_gas)
+		.map(|()| execute(config, &mut backend, transaction, gas_price));
+	*state = mem::take(backend.state_mut());
+	result
+}
+
+fn execute(
+	config: &Config,
+	backend: &mut MemoryBackend,
+	transaction: &Transaction,
+	gas_price: U256,
+) -> Receipt {
+	let caller = transaction.caller;
+	let gas_limit = transaction.gas_limit.as_u64();
+	let metadata = StackSubstateMetadata::new(gas_limit, config);
+	let state = MemoryStackState::new(metadata, backend);
+	let precompiles = StandardPrecompiles::new(config);
+	let mut executor = StackExecutor::new_with_precompiles(state, config, &precompiles);
+
+	// Validation checked the balance. Blob gas is burned.
+	let total_fee = gas_price * gas_limit;
+	let blob_fee = transaction.blob_gas() * backend.block_blob_base_fee();
+	executor
+		.state_mut()
+		.withdraw(caller, total_fee + blob_fee)
+		.expect("balance was validated");
+
+	let data = transaction.data.clone();
+	let access_list = transaction.access_list.clone();
+	let (reason, _) = match transaction.to {
+		Some(to) => {
+			executor.transact_call(caller, to, transaction.value, data, gas_limit, access_list)
+		}
+		None => executor.transact_create(caller, transaction.value, data, gas_limit, access_list),
+	};
+
+	let used_gas = executor.used_gas();
+	let coinbase = backend.block_coinbase();
+	let base_fee = backend.block_base_fee_per_gas();
+	executor
+		.state_mut()
+		.deposit(caller, total_fee - U256::from(used_gas) * gas_price);
+	executor
+		.state_mut()
+		.deposit(coinbase, U256::from(used_gas) * (gas_price - base_fee));
+
+	let (values, logs) = executor.into_state().deconstruct();
+	let logs = logs.into_iter().collect::<Vec<_>>();
+	backend.apply(values, logs.clone(), !config.empty_considered_exists);
+
+	Receipt {
+		succeeded: reason.is_succeed(),
+		used_gas,
+		logs,
+	}
+}
@@ -0,0 +1,218 @@
+use evm::backend::{Log, MemoryAccount};
+use evm::{Config, Fork};
+use primitive_types::{H160, H256, U256};
+use rlp::RlpStream;
+use serde::de::{Deserialize, Deserializer, Error};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter;
+
+/// A value written as a hex string in the fixtures. Quantities may also be
+/// decimal.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Hex<T>(pub T);
+
+fn strip(s: &str) -> &str {
+	s.strip_prefix("0x").unwrap_or(s)
+}
+
+impl<'de> Deserialize<'de> for Hex<U256> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		let value = match s.strip_prefix("0x") {
+			Some("") => Some(U256::zero()),
+			Some(digits) => U256::from_str_radix(digits, 16).ok(),
+			None => U256::from_dec_str(&s).ok(),
+		};
+		value
+			.map(Hex)
+			.ok_or_else(|| D::Error::custom(format!("invalid number: {}", s)))
+	}
+}
+
+impl<'de> Deserialize<'de> for Hex<Vec<u8>> {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		let s = String::deserialize(deserializer)?;
+		hex::decode(strip(&s))
+			.map(Hex)
+			.map_err(|_| D::Error::custom(format!("invalid bytes: {}", s)))
+	}
+}
+
+macro_rules! impl_hex_hash {
+	($hash:ident, $len:expr) => {
+		impl<'de> Deserialize<'de> for Hex<$hash> {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				let Hex(bytes) = Hex::<Vec<u8>>::deserialize(deserializer)?;
+				if bytes.len() > $len {
+					return Err(D::Error::custom("hash too long"));
+				}
+				// Storage keys may omit leading zeros.
+				let mut hash = $hash::default();
+				hash.as_bytes_mut()[$len - bytes.len()..].copy_from_slice(&bytes);
+				Ok(Hex(hash))
+			}
+		}
+	};
+}
+
+impl_hex_hash!(H160, 20);
+impl_hex_hash!(H256, 32);
+
+impl<T: fmt::Debug> fmt::Display for Hex<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:?}", self.0)
+	}
+}
+
+pub fn keccak(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+/// Keccak-256 as a trie hasher.
+pub struct KeccakHasher;
+
+impl hash_db::Hasher for KeccakHasher {
+	type Out = H256;
+	type StdHasher = hash256_std_hasher::Hash256StdHasher;
+	const LENGTH: usize = 32;
+
+	fn hash(data: &[u8]) -> H256 {
+		keccak(data)
+	}
+}
+
+/// Root of the secure trie of all accounts.
+pub fn state_root(state: &BTreeMap<H160, MemoryAccount>) -> H256 {
+	let accounts = state.iter().map(|(address, account)| {
+		let storage = account
+			.storage
+			.iter()
+			.filter(|(_, value)| !value.is_zero())
+			.map(|(index, value)| (index, rlp::encode(&U256::from_big_endian(value.as_bytes()))));
+		let storage_root = triehash::sec_trie_root::<KeccakHasher, _, _, _>(storage);
+
+		let mut stream = RlpStream::new_list(4);
+		stream.append(&account.nonce);
+		stream.append(&account.balance);
+		stream.append(&storage_root);
+		stream.append(&keccak(&account.code));
+		(address, stream.out())
+	});
+
+	triehash::sec_trie_root::<KeccakHasher, _, _, _>(accounts)
+}
+
+/// Append the RLP list of logs, as receipts encode them.
+pub fn append_logs(stream: &mut RlpStream, logs: &[Log]) {
+	stream.begin_list(logs.len());
+	for log in logs {
+		stream.begin_list(3);
+		stream.append(&log.address);
+		stream.append_list(&log.topics);
+		stream.append(&log.data);
+	}
+}
+
+/// Hash of the RLP list of logs, as state tests expect.
+pub fn logs_hash(logs: &[Log]) -> H256 {
+	let mut stream = RlpStream::new();
+	append_logs(&mut stream, logs);
+	keccak(&stream.out())
+}
+
+/// The 2048 bit bloom filter of the addresses and topics of logs.
+pub fn logs_bloom(logs: &[Log]) -> Vec<u8> {
+	let mut bloom = vec![0u8; 256];
+	let items = logs.iter().flat_map(|log| {
+		iter::once(log.address.as_bytes()).chain(log.topics.iter().map(H256::as_bytes))
+	});
+	for item in items {
+		let hash = keccak(item);
+		for i in 0..3 {
+			let bit = (usize::from(hash[2 * i]) << 8 | usize::from(hash[2 * i + 1])) & 2047;
+			bloom[255 - bit / 8] |= 1 << (bit % 8);
+		}
+	}
+	bloom
+}
+
+/// Address of the account owning a secret key.
+pub fn address_of(secret_key: H256) -> Option<H160> {
+	let secret_key = libsecp256k1::SecretKey::parse(secret_key.as_fixed_bytes()).ok()?;
+	let public_key = libsecp256k1::PublicKey::from_secret_key(&secret_key).serialize();
+	Some(H160::from(keccak(&public_key[1..])))
+}
+
+/// Sender of a transaction signed over `hash`. From Homestead, signatures
+/// with `s` in the upper half of the curve order are rejected.
+pub fn recover_signer(
+	hash: H256,
+	recovery_id: u8,
+	r: U256,
+	s: U256,
+	homestead: bool,
+) -> Option<H160> {
+	let half_order = U256::from_str_radix(
+		"7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0",
+		16,
+	)
+	.expect("valid constant");
+	if r.is_zero() || s.is_zero() || recovery_id > 1 || (homestead && s > half_order) {
+		return None;
+	}
+
+	let mut signature = [0u8; 64];
+	r.to_big_endian(&mut signature[..32]);
+	s.to_big_endian(&mut signature[32..]);
+	let signature = libsecp256k1::Signature::parse_standard(&signature).ok()?;
+	let recovery_id = libsecp256k1::RecoveryId::parse(recovery_id).ok()?;
+	let message = libsecp256k1::Message::parse(hash.as_fixed_bytes());
+	let public_key = libsecp256k1::recover(&message, &signature, &recovery_id)
+		.ok()?
+		.serialize();
+	Some(H160::from(keccak(&public_key[1..])))
+}
+
+/// Mining reward of a block of `fork`.
+pub fn block_reward(fork: Fork) -> U256 {
+	let ether = U256::exp10(18);
+	match fork {
+		Fork::Frontier | Fork::Homestead | Fork::TangerineWhistle | Fork::SpuriousDragon => {
+			ether * 5
+		}
+		// EIP-649
+		Fork::Byzantium => ether * 3,
+		// EIP-1234
+		Fork::Constantinople | Fork::Petersburg | Fork::Istanbul | Fork::Berlin | Fork::London => {
+			ether * 2
+		}
+		Fork::Merge | Fork::Shanghai | Fork::Cancun => U256::zero(),
+	}
+}
+
+/// Fork by its fixture name, if supported.
+pub fn fork(name: &str) -> Option<Fork> {
+	match name {
+		"Frontier" => Some(Fork::Frontier),
+		"Homestead" => Some(Fork::Homestead),
+		"EIP150" | "TangerineWhistle" => Some(Fork::TangerineWhistle),
+		"EIP158" | "SpuriousDragon" => Some(Fork::SpuriousDragon),
+		"Byzantium" => Some(Fork::Byzantium),
+		"Constantinople" => Some(Fork::Constantinople),
+		"ConstantinopleFix" | "Petersburg" => Some(Fork::Petersburg),
+		"Istanbul" => Some(Fork::Istanbul),
+		"Berlin" => Some(Fork::Berlin),
+		"London" => Some(Fork::London),
+		"Merge" | "Paris" => Some(Fork::Merge),
+		"Shanghai" => Some(Fork::Shanghai),
+		"Cancun" => Some(Fork::Cancun),
+		_ => None,
+	}
+}
+
+/// Configuration of a fork by its fixture name, if supported.
+pub fn config(name: &str) -> Option<Config> {
+	fork(name).map(Fork::config)
+}
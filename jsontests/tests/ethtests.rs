@@ -0,0 +1,30 @@
+use evm_jsontests::{blockchain, run_path, state, Summary};
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+/// Runs the fixtures checked out at `res/ethtests`, for example with
+/// `git clone https://github.com/ethereum/tests res/ethtests`.
+fn run<T: DeserializeOwned>(dir: &str, run_test: fn(&str, &T, &mut Summary)) {
+	let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+		.join("res/ethtests")
+		.join(dir);
+	if !path.exists() {
+		eprintln!("skipping, {} not found", path.display());
+		return;
+	}
+
+	let mut summary = Summary::default();
+	run_path(&path, &mut summary, run_test);
+	print!("{}", summary);
+	assert!(summary.is_success());
+}
+
+#[test]
+fn general_state_tests() {
+	run("GeneralStateTests", state::run_test);
+}
+
+#[test]
+fn blockchain_tests() {
+	run("BlockchainTests", blockchain::run_test);
+}
@@ -91,6 +91,19 @@ pub fn base_fee<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	Control::Continue
 }
 
+pub fn blob_base_fee<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
+	push_u256!(runtime, handler.block_blob_base_fee());
+
+	Control::Continue
+}
+
+pub fn blob_hash<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
+	pop_u256!(runtime, index);
+	push_h256!(runtime, handler.blob_hash(index));
+
+	Control::Continue
+}
+
 pub fn extcodesize<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	pop_h256!(runtime, address);
 	push_u256!(runtime, handler.code_size(address.into()));
@@ -182,7 +195,11 @@ pub fn number<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 }
 
 pub fn difficulty<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
-	push_u256!(runtime, handler.block_difficulty());
+	if runtime.config.has_prevrandao {
+		push_h256!(runtime, handler.block_prev_randao());
+	} else {
+		push_u256!(runtime, handler.block_difficulty());
+	}
 	Control::Continue
 }
 
@@ -220,6 +237,35 @@ pub fn sstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H>
 	}
 }
 
+pub fn tload<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
+	pop_h256!(runtime, index);
+	let value = handler.transient_storage(runtime.context.address, index);
+	push_h256!(runtime, value);
+
+	event!(TLoad {
+		address: runtime.context.address,
+		index,
+		value
+	});
+
+	Control::Continue
+}
+
+pub fn tstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H> {
+	pop_h256!(runtime, index, value);
+
+	event!(TStore {
+		address: runtime.context.address,
+		index,
+		value
+	});
+
+	match handler.set_transient_storage(runtime.context.address, index, value) {
+		Ok(()) => Control::Continue,
+		Err(e) => Control::Exit(e.into()),
+	}
+}
+
 pub fn gas<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	push_u256!(runtime, handler.gas_left());
 
@@ -0,0 +1,177 @@
+use crate::Config;
+use alloc::vec::Vec;
+
+/// Hard forks that change the rules of the EVM, in activation order.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fork {
+	Frontier,
+	Homestead,
+	TangerineWhistle,
+	SpuriousDragon,
+	Byzantium,
+	Constantinople,
+	Petersburg,
+	Istanbul,
+	Berlin,
+	London,
+	Merge,
+	Shanghai,
+	Cancun,
+}
+
+impl Fork {
+	/// Configuration of the fork.
+	pub const fn config(self) -> Config {
+		match self {
+			Fork::Frontier => Config::frontier(),
+			Fork::Homestead => Config::homestead(),
+			Fork::TangerineWhistle => Config::tangerine_whistle(),
+			Fork::SpuriousDragon => Config::spurious_dragon(),
+			Fork::Byzantium => Config::byzantium(),
+			Fork::Constantinople => Config::constantinople(),
+			Fork::Petersburg => Config::petersburg(),
+			Fork::Istanbul => Config::istanbul(),
+			Fork::Berlin => Config::berlin(),
+			Fork::London => Config::london(),
+			Fork::Merge => Config::merge(),
+			Fork::Shanghai => Config::shanghai(),
+			Fork::Cancun => Config::cancun(),
+		}
+	}
+}
+
+/// Condition under which a fork activates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Activation {
+	/// Active from the block with this number onwards.
+	Block(u64),
+	/// Active from the first block with at least this timestamp.
+	Timestamp(u64),
+}
+
+impl Activation {
+	/// Whether the fork is active in a block.
+	pub fn is_active(&self, block_number: u64, timestamp: u64) -> bool {
+		match *self {
+			Activation::Block(number) => block_number >= number,
+			Activation::Timestamp(time) => timestamp >= time,
+		}
+	}
+}
+
+/// Schedule of the hard forks of a chain, resolving the configuration of any
+/// block so that history can be replayed across fork boundaries.
+#[derive(Clone, Debug)]
+pub struct ChainSpec {
+	forks: Vec<(Activation, Fork, Config)>,
+}
+
+impl ChainSpec {
+	/// Chain running `genesis` from its first block.
+	pub fn new(genesis: Fork) -> Self {
+		Self {
+			forks: alloc::vec![(Activation::Block(0), genesis, genesis.config())],
+		}
+	}
+
+	/// Schedule `fork` to activate at `activation`.
+	///
+	/// # Panics
+	///
+	/// Panics if `fork` is not after the last scheduled fork, or if it
+	/// activates before it. Timestamp activations come after block ones.
+	pub fn with_fork(mut self, fork: Fork, activation: Activation) -> Self {
+		let (last_activation, last_fork, _) = &self.forks[self.forks.len() - 1];
+		let in_order = match (*last_activation, activation) {
+			(Activation::Block(last), Activation::Block(next))
+			| (Activation::Timestamp(last), Activation::Timestamp(next)) => last <= next,
+			(Activation::Block(_), Activation::Timestamp(_)) => true,
+			(Activation::Timestamp(_), Activation::Block(_)) => false,
+		};
+		assert!(
+			fork > *last_fork && in_order,
+			"{:?} at {:?} scheduled after {:?} at {:?}",
+			fork,
+			activation,
+			last_fork,
+			last_activation
+		);
+		self.forks.push((activation, fork, fork.config()));
+		self
+	}
+
+	/// Ethereum mainnet.
+	pub fn mainnet() -> Self {
+		Self::new(Fork::Frontier)
+			.with_fork(Fork::Homestead, Activation::Block(1_150_000))
+			.with_fork(Fork::TangerineWhistle, Activation::Block(2_463_000))
+			.with_fork(Fork::SpuriousDragon, Activation::Block(2_675_000))
+			.with_fork(Fork::Byzantium, Activation::Block(4_370_000))
+			// Constantinople activated together with Petersburg.
+			.with_fork(Fork::Petersburg, Activation::Block(7_280_000))
+			.with_fork(Fork::Istanbul, Activation::Block(9_069_000))
+			.with_fork(Fork::Berlin, Activation::Block(12_244_000))
+			.with_fork(Fork::London, Activation::Block(12_965_000))
+			.with_fork(Fork::Merge, Activation::Block(15_537_394))
+			.with_fork(Fork::Shanghai, Activation::Timestamp(1_681_338_455))
+			.with_fork(Fork::Cancun, Activation::Timestamp(1_710_338_135))
+	}
+
+	/// Forks of the chain with their activations, in order.
+	pub fn forks(&self) -> impl Iterator<Item = (Activation, Fork)> + '_ {
+		self.forks
+			.iter()
+			.map(|(activation, fork, _)| (*activation, *fork))
+	}
+
+	/// Fork active in a block.
+	pub fn fork(&self, block_number: u64, timestamp: u64) -> Fork {
+		self.active(block_number, timestamp).1
+	}
+
+	/// Configuration of a block.
+	pub fn config(&self, block_number: u64, timestamp: u64) -> &Config {
+		&self.active(block_number, timestamp).2
+	}
+
+	fn active(&self, block_number: u64, timestamp: u64) -> &(Activation, Fork, Config) {
+		self.forks
+			.iter()
+			.rev()
+			.find(|(activation, _, _)| activation.is_active(block_number, timestamp))
+			.unwrap_or(&self.forks[0])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mainnet() {
+		let spec = ChainSpec::mainnet();
+		assert_eq!(spec.fork(0, 0), Fork::Frontier);
+		assert_eq!(spec.fork(4_369_999, 0), Fork::SpuriousDragon);
+		assert_eq!(spec.fork(4_370_000, 0), Fork::Byzantium);
+		assert!(spec.config(4_370_000, 0).has_revert);
+		assert_eq!(spec.fork(17_034_869, 1_681_338_454), Fork::Merge);
+		assert_eq!(spec.fork(17_034_870, 1_681_338_455), Fork::Shanghai);
+		assert!(spec.config(17_034_870, 1_681_338_455).has_push0);
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_fork_out_of_order() {
+		let _ = ChainSpec::new(Fork::Berlin).with_fork(Fork::Istanbul, Activation::Block(5));
+	}
+
+	#[test]
+	#[should_panic]
+	fn test_activation_out_of_order() {
+		let _ = ChainSpec::new(Fork::Merge)
+			.with_fork(Fork::Shanghai, Activation::Timestamp(15_000))
+			.with_fork(Fork::Cancun, Activation::Block(20));
+	}
+}
@@ -37,6 +37,8 @@ pub trait Handler {
 	fn storage(&self, address: H160, index: H256) -> H256;
 	/// Get original storage value of address at index.
 	fn original_storage(&self, address: H160, index: H256) -> H256;
+	/// Get transient storage value of address at index.
+	fn transient_storage(&self, address: H160, index: H256) -> H256;
 
 	/// Get the gas left value.
 	fn gas_left(&self) -> U256;
@@ -54,10 +56,18 @@ pub trait Handler {
 	fn block_timestamp(&self) -> U256;
 	/// Get environmental block difficulty.
 	fn block_difficulty(&self) -> U256;
+	/// Get environmental previous block randomness, returned by `DIFFICULTY`
+	/// after the merge.
+	fn block_prev_randao(&self) -> H256;
 	/// Get environmental gas limit.
 	fn block_gas_limit(&self) -> U256;
 	/// Environmental block base fee.
 	fn block_base_fee_per_gas(&self) -> U256;
+	/// Environmental block blob base fee.
+	fn block_blob_base_fee(&self) -> U256;
+	/// Get the versioned hash of a blob of the transaction, zero if there is
+	/// no blob at `index`.
+	fn blob_hash(&self, index: U256) -> H256;
 	/// Get environmental chain ID.
 	fn chain_id(&self) -> U256;
 
@@ -75,6 +85,13 @@ pub trait Handler {
 
 	/// Set storage value of address at index.
 	fn set_storage(&mut self, address: H160, index: H256, value: H256) -> Result<(), ExitError>;
+	/// Set transient storage value of address at index.
+	fn set_transient_storage(
+		&mut self,
+		address: H160,
+		index: H256,
+		value: H256,
+	) -> Result<(), ExitError>;
 	/// Create a log owned by address with given topics and data.
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError>;
 	/// Mark an address to be deleted, with funds transferred to target.
@@ -24,12 +24,14 @@ macro_rules! event {
 
 mod context;
 mod eval;
+mod fork;
 mod handler;
 mod interrupt;
 
 pub use evm_core::*;
 
 pub use crate::context::{CallScheme, Context, CreateScheme};
+pub use crate::fork::{Activation, ChainSpec, Fork};
 pub use crate::handler::{Handler, Transfer};
 pub use crate::interrupt::{Resolve, ResolveCall, ResolveCreate};
 
@@ -79,10 +81,49 @@ pub struct Runtime<'config> {
 	status: Result<(), ExitReason>,
 	return_data_buffer: Vec<u8>,
 	context: Context,
-	_config: &'config Config,
+	config: &'config Config,
 }
 
 impl<'config> Runtime<'config> {
+	/// Machine running `code` as EOF if it is a container and the config has
+	/// EOF, validated as `eof` if given. Invalid containers exit right away.
+	/// Legacy code uses `valids` as its jump destinations if given.
+	fn machine_for(
+		code: Rc<Vec<u8>>,
+		valids: Option<Rc<Valids>>,
+		eof: Option<Result<Eof, EofError>>,
+		data: Rc<Vec<u8>>,
+		config: &Config,
+	) -> Machine {
+		let eof = match eof {
+			Some(eof) => Some(eof),
+			None if config.has_eof && Eof::is_eof(&code) => Some(Eof::new(&code)),
+			None => None,
+		};
+		let mut machine = match eof {
+			Some(Ok(eof)) => {
+				Machine::new_eof(code, eof, data, config.stack_limit, config.memory_limit)
+			}
+			Some(Err(_)) => {
+				let mut machine = Machine::new(code, data, config.stack_limit, config.memory_limit);
+				machine.exit(ExitError::InvalidEof.into());
+				machine
+			}
+			None => match valids {
+				Some(valids) => Machine::new_with_valids(
+					code,
+					valids,
+					data,
+					config.stack_limit,
+					config.memory_limit,
+				),
+				None => Machine::new(code, data, config.stack_limit, config.memory_limit),
+			},
+		};
+		machine.set_disabled(disabled_opcodes(config));
+		machine
+	}
+
 	/// Create a new runtime with given code and data.
 	pub fn new(
 		code: Rc<Vec<u8>>,
@@ -91,11 +132,47 @@ impl<'config> Runtime<'config> {
 		config: &'config Config,
 	) -> Self {
 		Self {
-			machine: Machine::new(code, data, config.stack_limit, config.memory_limit),
+			machine: Self::machine_for(code, None, None, data, config),
+			status: Ok(()),
+			return_data_buffer: Vec::new(),
+			context,
+			config,
+		}
+	}
+
+	/// Create a new runtime with given code, its jump destinations analysed
+	/// beforehand as `valids`, and data.
+	pub fn new_with_valids(
+		code: Rc<Vec<u8>>,
+		valids: Rc<Valids>,
+		data: Rc<Vec<u8>>,
+		context: Context,
+		config: &'config Config,
+	) -> Self {
+		Self {
+			machine: Self::machine_for(code, Some(valids), None, data, config),
+			status: Ok(()),
+			return_data_buffer: Vec::new(),
+			context,
+			config,
+		}
+	}
+
+	/// Create a new runtime with given EOF code, validated beforehand as
+	/// `eof`, and data. An invalid container exits right away.
+	pub fn new_with_eof(
+		code: Rc<Vec<u8>>,
+		eof: Result<Eof, EofError>,
+		data: Rc<Vec<u8>>,
+		context: Context,
+		config: &'config Config,
+	) -> Self {
+		Self {
+			machine: Self::machine_for(code, None, Some(eof), data, config),
 			status: Ok(()),
 			return_data_buffer: Vec::new(),
 			context,
-			_config: config,
+			config,
 		}
 	}
 
@@ -128,8 +205,37 @@ impl<'config> Runtime<'config> {
 	}
 }
 
+/// Opcodes evaluated by the machine that `config` lacks.
+fn disabled_opcodes(config: &Config) -> OpcodeSet {
+	let forks = [
+		(config.has_revert, Opcode::REVERT),
+		(config.has_bitwise_shifting, Opcode::SHL),
+		(config.has_bitwise_shifting, Opcode::SHR),
+		(config.has_bitwise_shifting, Opcode::SAR),
+		(config.has_push0, Opcode::PUSH0),
+		(config.has_mcopy, Opcode::MCOPY),
+		(config.has_eof, Opcode::RJUMP),
+		(config.has_eof, Opcode::RJUMPI),
+		(config.has_eof, Opcode::RJUMPV),
+		(config.has_eof, Opcode::CALLF),
+		(config.has_eof, Opcode::RETF),
+	];
+	let mut disabled = OpcodeSet::new();
+	for (enabled, opcode) in forks {
+		if !enabled {
+			disabled.insert(opcode);
+		}
+	}
+	disabled
+}
+
 /// Runtime configuration.
 #[derive(Clone, Debug)]
+#[cfg_attr(
+	feature = "with-serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(deny_unknown_fields)
+)]
 pub struct Config {
 	/// Gas paid for extcode.
 	pub gas_ext_code: u64,
@@ -221,6 +327,31 @@ pub struct Config {
 	pub has_ext_code_hash: bool,
 	/// Has ext block fee. See [EIP-3198](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3198.md)
 	pub has_base_fee: bool,
+	/// Has PREVRANDAO in place of DIFFICULTY. See [EIP-4399](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-4399.md)
+	pub has_prevrandao: bool,
+	/// Has PUSH0 opcode. See [EIP-3855](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3855.md)
+	pub has_push0: bool,
+	/// Whether the coinbase address is warm at the start of a transaction.
+	/// See [EIP-3651](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3651.md)
+	pub warm_coinbase_address: bool,
+	/// Maximum size of init code, charged per word when set. See
+	/// [EIP-3860](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3860.md)
+	pub max_initcode_size: Option<usize>,
+	/// Has TLOAD and TSTORE opcodes. See [EIP-1153](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-1153.md)
+	pub has_transient_storage: bool,
+	/// Has MCOPY opcode. See [EIP-5656](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-5656.md)
+	pub has_mcopy: bool,
+	/// Has BLOBHASH opcode. See [EIP-4844](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-4844.md)
+	pub has_blob_hash: bool,
+	/// Has BLOBBASEFEE opcode. See [EIP-7516](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-7516.md)
+	pub has_blob_base_fee: bool,
+	/// Whether SUICIDE only deletes contracts created in the same transaction.
+	/// See [EIP-6780](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-6780.md)
+	pub suicide_only_in_same_tx: bool,
+	/// Has EVM Object Format containers, validated at creation, with relative
+	/// jumps and code sections. No hard fork enables it. See
+	/// [EIP-3540](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-3540.md)
+	pub has_eof: bool,
 	/// Whether the gasometer is running in estimate mode.
 	pub estimate: bool,
 }
@@ -273,10 +404,97 @@ impl Config {
 			has_self_balance: false,
 			has_ext_code_hash: false,
 			has_base_fee: false,
+			has_prevrandao: false,
+			has_push0: false,
+			warm_coinbase_address: false,
+			max_initcode_size: None,
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
+			has_eof: false,
 			estimate: false,
 		}
 	}
 
+	/// Homestead hard fork configuration.
+	pub const fn homestead() -> Config {
+		Config {
+			// EIP-2
+			gas_transaction_create: 53000,
+			// EIP-7
+			has_delegate_call: true,
+			..Self::frontier()
+		}
+	}
+
+	/// Tangerine Whistle hard fork configuration (EIP-150).
+	pub const fn tangerine_whistle() -> Config {
+		Config {
+			gas_ext_code: 700,
+			gas_ext_code_hash: 700,
+			gas_balance: 400,
+			gas_sload: 200,
+			gas_suicide: 5000,
+			gas_suicide_new_account: 25000,
+			gas_call: 700,
+			err_on_call_with_more_gas: false,
+			call_l64_after_gas: true,
+			..Self::homestead()
+		}
+	}
+
+	/// Spurious Dragon hard fork configuration.
+	pub const fn spurious_dragon() -> Config {
+		Config {
+			// EIP-160
+			gas_expbyte: 50,
+			// EIP-161
+			empty_considered_exists: false,
+			create_increase_nonce: true,
+			// EIP-170
+			create_contract_limit: Some(0x6000),
+			..Self::tangerine_whistle()
+		}
+	}
+
+	/// Byzantium hard fork configuration.
+	pub const fn byzantium() -> Config {
+		Config {
+			// EIP-140
+			has_revert: true,
+			// EIP-211
+			has_return_data: true,
+			..Self::spurious_dragon()
+		}
+	}
+
+	/// Constantinople hard fork configuration.
+	pub const fn constantinople() -> Config {
+		Config {
+			// EIP-145
+			has_bitwise_shifting: true,
+			// EIP-1014
+			has_create2: true,
+			// EIP-1052
+			has_ext_code_hash: true,
+			gas_ext_code_hash: 400,
+			// EIP-1283
+			sstore_gas_metering: true,
+			..Self::byzantium()
+		}
+	}
+
+	/// Petersburg hard fork configuration, which is Constantinople without
+	/// EIP-1283.
+	pub const fn petersburg() -> Config {
+		Config {
+			sstore_gas_metering: false,
+			..Self::constantinople()
+		}
+	}
+
 	/// Istanbul hard fork configuration.
 	pub const fn istanbul() -> Config {
 		Config {
@@ -324,6 +542,16 @@ impl Config {
 			has_self_balance: true,
 			has_ext_code_hash: true,
 			has_base_fee: false,
+			has_prevrandao: false,
+			has_push0: false,
+			warm_coinbase_address: false,
+			max_initcode_size: None,
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
+			has_eof: false,
 			estimate: false,
 		}
 	}
@@ -338,6 +566,21 @@ impl Config {
 		Self::config_with_derived_values(DerivedConfigInputs::london())
 	}
 
+	/// Merge hard fork configuration.
+	pub const fn merge() -> Config {
+		Self::config_with_derived_values(DerivedConfigInputs::merge())
+	}
+
+	/// Shanghai hard fork configuration.
+	pub const fn shanghai() -> Config {
+		Self::config_with_derived_values(DerivedConfigInputs::shanghai())
+	}
+
+	/// Cancun hard fork configuration.
+	pub const fn cancun() -> Config {
+		Self::config_with_derived_values(DerivedConfigInputs::cancun())
+	}
+
 	const fn config_with_derived_values(inputs: DerivedConfigInputs) -> Config {
 		let DerivedConfigInputs {
 			gas_storage_read_warm,
@@ -345,7 +588,16 @@ impl Config {
 			gas_access_list_storage_key,
 			decrease_clears_refund,
 			has_base_fee,
+			has_prevrandao,
 			disallow_executable_format,
+			has_push0,
+			warm_coinbase_address,
+			max_initcode_size,
+			has_transient_storage,
+			has_mcopy,
+			has_blob_hash,
+			has_blob_base_fee,
+			suicide_only_in_same_tx,
 		} = inputs;
 
 		// See https://eips.ethereum.org/EIPS/eip-2929
@@ -405,6 +657,16 @@ impl Config {
 			has_self_balance: true,
 			has_ext_code_hash: true,
 			has_base_fee,
+			has_prevrandao,
+			has_push0,
+			warm_coinbase_address,
+			max_initcode_size,
+			has_transient_storage,
+			has_mcopy,
+			has_blob_hash,
+			has_blob_base_fee,
+			suicide_only_in_same_tx,
+			has_eof: false,
 			estimate: false,
 		}
 	}
@@ -418,7 +680,16 @@ struct DerivedConfigInputs {
 	gas_access_list_storage_key: u64,
 	decrease_clears_refund: bool,
 	has_base_fee: bool,
+	has_prevrandao: bool,
 	disallow_executable_format: bool,
+	has_push0: bool,
+	warm_coinbase_address: bool,
+	max_initcode_size: Option<usize>,
+	has_transient_storage: bool,
+	has_mcopy: bool,
+	has_blob_hash: bool,
+	has_blob_base_fee: bool,
+	suicide_only_in_same_tx: bool,
 }
 
 impl DerivedConfigInputs {
@@ -429,7 +700,16 @@ impl DerivedConfigInputs {
 			gas_access_list_storage_key: 1900,
 			decrease_clears_refund: false,
 			has_base_fee: false,
+			has_prevrandao: false,
 			disallow_executable_format: false,
+			has_push0: false,
+			warm_coinbase_address: false,
+			max_initcode_size: None,
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
 		}
 	}
 
@@ -440,7 +720,78 @@ impl DerivedConfigInputs {
 			gas_access_list_storage_key: 1900,
 			decrease_clears_refund: true,
 			has_base_fee: true,
+			has_prevrandao: false,
+			disallow_executable_format: true,
+			has_push0: false,
+			warm_coinbase_address: false,
+			max_initcode_size: None,
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
+		}
+	}
+
+	const fn merge() -> Self {
+		Self {
+			gas_storage_read_warm: 100,
+			gas_sload_cold: 2100,
+			gas_access_list_storage_key: 1900,
+			decrease_clears_refund: true,
+			has_base_fee: true,
+			has_prevrandao: true,
+			disallow_executable_format: true,
+			has_push0: false,
+			warm_coinbase_address: false,
+			max_initcode_size: None,
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
+		}
+	}
+
+	const fn shanghai() -> Self {
+		Self {
+			gas_storage_read_warm: 100,
+			gas_sload_cold: 2100,
+			gas_access_list_storage_key: 1900,
+			decrease_clears_refund: true,
+			has_base_fee: true,
+			has_prevrandao: true,
+			disallow_executable_format: true,
+			has_push0: true,
+			warm_coinbase_address: true,
+			// 2 * 24576 as per EIP-3860
+			max_initcode_size: Some(0xC000),
+			has_transient_storage: false,
+			has_mcopy: false,
+			has_blob_hash: false,
+			has_blob_base_fee: false,
+			suicide_only_in_same_tx: false,
+		}
+	}
+
+	const fn cancun() -> Self {
+		Self {
+			gas_storage_read_warm: 100,
+			gas_sload_cold: 2100,
+			gas_access_list_storage_key: 1900,
+			decrease_clears_refund: true,
+			has_base_fee: true,
+			has_prevrandao: true,
 			disallow_executable_format: true,
+			has_push0: true,
+			warm_coinbase_address: true,
+			// 2 * 24576 as per EIP-3860
+			max_initcode_size: Some(0xC000),
+			has_transient_storage: true,
+			has_mcopy: true,
+			has_blob_hash: true,
+			has_blob_base_fee: true,
+			suicide_only_in_same_tx: true,
 		}
 	}
 }
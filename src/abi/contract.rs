@@ -0,0 +1,298 @@
+use super::param_type::{join, parse_list};
+use super::{decode, decode_value, encode, keccak, Error, ParamType, Token};
+use crate::backend::Log;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use primitive_types::H256;
+use serde_json::Value;
+
+/// Contract function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Function {
+	/// Function name.
+	pub name: String,
+	/// Argument types.
+	pub inputs: Vec<ParamType>,
+	/// Return types.
+	pub outputs: Vec<ParamType>,
+}
+
+impl Function {
+	/// Parse a signature such as `transfer(address,uint256)`. An optional
+	/// second parenthesized list gives the return types, as in
+	/// `balanceOf(address)(uint256)`.
+	pub fn parse(signature: &str) -> Result<Self, Error> {
+		let signature = signature.trim();
+		let invalid = || Error::InvalidSignature(signature.to_string());
+
+		let open = signature.find('(').ok_or_else(invalid)?;
+		let name = &signature[..open];
+		let rest = &signature[open..];
+
+		// Find the parenthesis closing the argument list.
+		let mut depth = 0usize;
+		let mut close = None;
+		for (i, c) in rest.char_indices() {
+			match c {
+				'(' => depth += 1,
+				')' => {
+					depth -= 1;
+					if depth == 0 {
+						close = Some(i);
+						break;
+					}
+				}
+				_ => (),
+			}
+		}
+		let close = close.ok_or_else(invalid)?;
+
+		let inputs = parse_list(&rest[1..close])?;
+		let outputs = match &rest[close + 1..] {
+			"" => Vec::new(),
+			o => parse_list(
+				o.strip_prefix('(')
+					.and_then(|o| o.strip_suffix(')'))
+					.ok_or_else(invalid)?,
+			)?,
+		};
+
+		Ok(Self {
+			name: name.to_string(),
+			inputs,
+			outputs,
+		})
+	}
+
+	/// Canonical signature, such as `transfer(address,uint256)`.
+	pub fn signature(&self) -> String {
+		format!("{}({})", self.name, join(&self.inputs))
+	}
+
+	/// The 4-byte function selector.
+	pub fn selector(&self) -> [u8; 4] {
+		let mut out = [0u8; 4];
+		out.copy_from_slice(&keccak(self.signature().as_bytes())[..4]);
+		out
+	}
+
+	/// Calldata for calling the function with the given arguments.
+	pub fn encode_input(&self, tokens: &[Token]) -> Result<Vec<u8>, Error> {
+		if tokens.len() != self.inputs.len() {
+			return Err(Error::ArgumentCount {
+				expected: self.inputs.len(),
+				found: tokens.len(),
+			});
+		}
+
+		let mut out = self.selector().to_vec();
+		out.extend(encode(tokens));
+		Ok(out)
+	}
+
+	/// Parse human readable arguments, see [`Token::parse`].
+	pub fn parse_inputs<S: AsRef<str>>(&self, args: &[S]) -> Result<Vec<Token>, Error> {
+		if args.len() != self.inputs.len() {
+			return Err(Error::ArgumentCount {
+				expected: self.inputs.len(),
+				found: args.len(),
+			});
+		}
+
+		self.inputs
+			.iter()
+			.zip(args)
+			.map(|(kind, arg)| Token::parse(kind, arg.as_ref()))
+			.collect()
+	}
+
+	/// Decode return data.
+	pub fn decode_output(&self, data: &[u8]) -> Result<Vec<Token>, Error> {
+		decode(&self.outputs, data)
+	}
+}
+
+/// Event parameter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventParam {
+	/// Parameter name.
+	pub name: String,
+	/// Parameter type.
+	pub kind: ParamType,
+	/// Whether the parameter is stored in a topic instead of the log data.
+	pub indexed: bool,
+}
+
+/// Contract event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Event {
+	/// Event name.
+	pub name: String,
+	/// Event parameters.
+	pub inputs: Vec<EventParam>,
+	/// Whether the signature topic is omitted.
+	pub anonymous: bool,
+}
+
+impl Event {
+	/// Canonical signature, such as `Transfer(address,address,uint256)`.
+	pub fn signature(&self) -> String {
+		let kinds = self
+			.inputs
+			.iter()
+			.map(|p| p.kind.clone())
+			.collect::<Vec<_>>();
+		format!("{}({})", self.name, join(&kinds))
+	}
+
+	/// The signature topic.
+	pub fn topic(&self) -> H256 {
+		keccak(self.signature().as_bytes())
+	}
+
+	/// Decode a log emitted by this event into named values. Indexed
+	/// parameters of dynamic types are only available as their hash, and are
+	/// returned as `bytes32`.
+	pub fn decode_log(&self, log: &Log) -> Result<Vec<(String, Token)>, Error> {
+		let mut topics = log.topics.iter();
+		if !self.anonymous && topics.next() != Some(&self.topic()) {
+			return Err(Error::InvalidData);
+		}
+
+		let data_types = self
+			.inputs
+			.iter()
+			.filter(|p| !p.indexed)
+			.map(|p| p.kind.clone())
+			.collect::<Vec<_>>();
+		let mut data = decode(&data_types, &log.data)?.into_iter();
+
+		let mut out = Vec::with_capacity(self.inputs.len());
+		for param in &self.inputs {
+			let token = if param.indexed {
+				let topic = topics.next().ok_or(Error::InvalidData)?;
+				if param.kind.is_dynamic() {
+					Token::FixedBytes(topic[..].to_vec())
+				} else {
+					decode_value(&param.kind, &topic[..])?
+				}
+			} else {
+				data.next().ok_or(Error::InvalidData)?
+			};
+			out.push((param.name.clone(), token));
+		}
+
+		if topics.next().is_some() {
+			return Err(Error::InvalidData);
+		}
+
+		Ok(out)
+	}
+}
+
+/// Functions and events of a contract ABI.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Contract {
+	/// Functions, in ABI order.
+	pub functions: Vec<Function>,
+	/// Events, in ABI order.
+	pub events: Vec<Event>,
+}
+
+impl Contract {
+	/// Load a contract ABI from its JSON form, either the bare ABI array or a
+	/// compiler artifact with an `abi` field.
+	pub fn load(json: &str) -> Result<Self, Error> {
+		let value: Value =
+			serde_json::from_str(json).map_err(|e| Error::InvalidAbi(e.to_string()))?;
+		let entries = match &value {
+			Value::Array(entries) => entries,
+			Value::Object(o) => match o.get("abi") {
+				Some(Value::Array(entries)) => entries,
+				_ => return Err(Error::InvalidAbi("missing abi field".into())),
+			},
+			_ => return Err(Error::InvalidAbi("expected an array".into())),
+		};
+
+		let mut contract = Self::default();
+		for entry in entries {
+			let name = entry["name"].as_str().unwrap_or_default().to_string();
+			match entry["type"].as_str() {
+				Some("function") => contract.functions.push(Function {
+					name,
+					inputs: params(&entry["inputs"])?
+						.into_iter()
+						.map(|p| p.kind)
+						.collect(),
+					outputs: params(&entry["outputs"])?
+						.into_iter()
+						.map(|p| p.kind)
+						.collect(),
+				}),
+				Some("event") => contract.events.push(Event {
+					name,
+					inputs: params(&entry["inputs"])?,
+					anonymous: entry["anonymous"].as_bool().unwrap_or(false),
+				}),
+				_ => (),
+			}
+		}
+
+		Ok(contract)
+	}
+
+	/// Find a function by name, or by its full signature for overloads.
+	pub fn function(&self, name: &str) -> Option<&Function> {
+		self.functions
+			.iter()
+			.find(|f| f.signature() == name)
+			.or_else(|| self.functions.iter().find(|f| f.name == name))
+	}
+
+	/// Decode a log with the first event that matches it.
+	pub fn decode_log(&self, log: &Log) -> Option<(&Event, Vec<(String, Token)>)> {
+		self.events
+			.iter()
+			.find_map(|event| event.decode_log(log).ok().map(|values| (event, values)))
+	}
+}
+
+fn params(value: &Value) -> Result<Vec<EventParam>, Error> {
+	let entries = match value {
+		Value::Array(entries) => entries,
+		Value::Null => return Ok(Vec::new()),
+		_ => return Err(Error::InvalidAbi("expected a parameter array".into())),
+	};
+
+	entries
+		.iter()
+		.map(|entry| {
+			Ok(EventParam {
+				name: entry["name"].as_str().unwrap_or_default().to_string(),
+				kind: ParamType::parse(&canonical_type(entry)?)?,
+				indexed: entry["indexed"].as_bool().unwrap_or(false),
+			})
+		})
+		.collect()
+}
+
+/// Expand `tuple` types in a JSON parameter into their components.
+fn canonical_type(entry: &Value) -> Result<String, Error> {
+	let kind = entry["type"]
+		.as_str()
+		.ok_or_else(|| Error::InvalidAbi("missing parameter type".into()))?;
+
+	match kind.strip_prefix("tuple") {
+		Some(suffix) => {
+			let components = match &entry["components"] {
+				Value::Array(c) => c
+					.iter()
+					.map(canonical_type)
+					.collect::<Result<Vec<_>, _>>()?,
+				_ => return Err(Error::InvalidAbi("missing tuple components".into())),
+			};
+			Ok(format!("({}){}", components.join(","), suffix))
+		}
+		None => Ok(kind.to_string()),
+	}
+}
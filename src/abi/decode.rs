@@ -0,0 +1,82 @@
+use super::{Error, ParamType, Token};
+use alloc::string::String;
+use alloc::vec::Vec;
+use primitive_types::{H160, U256};
+
+/// ABI decode a list of values of the given types, as for function return
+/// data.
+pub fn decode(types: &[ParamType], data: &[u8]) -> Result<Vec<Token>, Error> {
+	decode_frame(types, data)
+}
+
+fn decode_frame(types: &[ParamType], frame: &[u8]) -> Result<Vec<Token>, Error> {
+	let mut tokens = Vec::with_capacity(types.len());
+	let mut head = 0;
+
+	for kind in types {
+		let token = if kind.is_dynamic() {
+			let offset = read_usize(frame, head)?;
+			decode_value(kind, frame.get(offset..).ok_or(Error::InvalidData)?)?
+		} else {
+			decode_value(kind, frame.get(head..).ok_or(Error::InvalidData)?)?
+		};
+		tokens.push(token);
+		head += kind.head_size();
+	}
+
+	Ok(tokens)
+}
+
+/// Decode a single value starting at the beginning of `data`.
+pub(crate) fn decode_value(kind: &ParamType, data: &[u8]) -> Result<Token, Error> {
+	let token = match kind {
+		ParamType::Address => Token::Address(H160::from_slice(&read_word(data, 0)?[12..])),
+		ParamType::FixedBytes(len) => Token::FixedBytes(read_word(data, 0)?[..*len].to_vec()),
+		ParamType::Int(_) => Token::Int(U256::from_big_endian(read_word(data, 0)?)),
+		ParamType::Uint(_) => Token::Uint(U256::from_big_endian(read_word(data, 0)?)),
+		ParamType::Bool => match U256::from_big_endian(read_word(data, 0)?) {
+			v if v.is_zero() => Token::Bool(false),
+			v if v == U256::one() => Token::Bool(true),
+			_ => return Err(Error::InvalidData),
+		},
+		ParamType::Bytes => Token::Bytes(read_bytes(data)?.to_vec()),
+		ParamType::String => Token::String(
+			String::from_utf8(read_bytes(data)?.to_vec()).map_err(|_| Error::InvalidData)?,
+		),
+		ParamType::Array(inner) => {
+			let len = read_usize(data, 0)?;
+			let frame = &data[32..];
+			// Every element takes at least one word, which bounds the allocation
+			// below for malicious lengths.
+			if len > frame.len() / 32 {
+				return Err(Error::InvalidData);
+			}
+			Token::Array(decode_frame(&vec![(**inner).clone(); len], frame)?)
+		}
+		ParamType::FixedArray(inner, len) => {
+			Token::FixedArray(decode_frame(&vec![(**inner).clone(); *len], data)?)
+		}
+		ParamType::Tuple(inner) => Token::Tuple(decode_frame(inner, data)?),
+	};
+
+	Ok(token)
+}
+
+fn read_word(data: &[u8], offset: usize) -> Result<&[u8], Error> {
+	data.get(offset..offset + 32).ok_or(Error::InvalidData)
+}
+
+fn read_usize(data: &[u8], offset: usize) -> Result<usize, Error> {
+	let value = U256::from_big_endian(read_word(data, offset)?);
+	if value > U256::from(usize::MAX) {
+		return Err(Error::InvalidData);
+	}
+	Ok(value.as_usize())
+}
+
+fn read_bytes(data: &[u8]) -> Result<&[u8], Error> {
+	let len = read_usize(data, 0)?;
+	data.get(32..)
+		.and_then(|rest| rest.get(..len))
+		.ok_or(Error::InvalidData)
+}
@@ -0,0 +1,74 @@
+use super::Token;
+use alloc::vec::Vec;
+use primitive_types::U256;
+
+/// ABI encode a list of tokens, as for function arguments.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+	let head_size: usize = tokens.iter().map(head_size).sum();
+	let mut head = Vec::with_capacity(head_size);
+	let mut tail = Vec::new();
+
+	for token in tokens {
+		if token.is_dynamic() {
+			head.extend_from_slice(&word(U256::from(head_size + tail.len())));
+			tail.extend(encode_token(token));
+		} else {
+			head.extend(encode_token(token));
+		}
+	}
+
+	head.extend(tail);
+	head
+}
+
+fn head_size(token: &Token) -> usize {
+	if token.is_dynamic() {
+		return 32;
+	}
+
+	match token {
+		Token::FixedArray(inner) | Token::Tuple(inner) => inner.iter().map(head_size).sum(),
+		_ => 32,
+	}
+}
+
+fn word(value: U256) -> [u8; 32] {
+	let mut out = [0u8; 32];
+	value.to_big_endian(&mut out);
+	out
+}
+
+fn pad_right(bytes: &[u8]) -> Vec<u8> {
+	let mut out = bytes.to_vec();
+	out.resize(bytes.len() + (32 - bytes.len() % 32) % 32, 0);
+	out
+}
+
+fn encode_token(token: &Token) -> Vec<u8> {
+	match token {
+		Token::Address(address) => {
+			let mut out = [0u8; 32];
+			out[12..].copy_from_slice(&address[..]);
+			out.to_vec()
+		}
+		Token::FixedBytes(bytes) => {
+			let mut out = [0u8; 32];
+			out[..bytes.len()].copy_from_slice(bytes);
+			out.to_vec()
+		}
+		Token::Int(value) | Token::Uint(value) => word(*value).to_vec(),
+		Token::Bool(value) => word(U256::from(*value as u8)).to_vec(),
+		Token::Bytes(bytes) => {
+			let mut out = word(U256::from(bytes.len())).to_vec();
+			out.extend(pad_right(bytes));
+			out
+		}
+		Token::String(s) => encode_token(&Token::Bytes(s.as_bytes().to_vec())),
+		Token::Array(items) => {
+			let mut out = word(U256::from(items.len())).to_vec();
+			out.extend(encode(items));
+			out
+		}
+		Token::FixedArray(items) | Token::Tuple(items) => encode(items),
+	}
+}
@@ -0,0 +1,148 @@
+//! # Contract ABI
+//!
+//! Encoding and decoding of Solidity values, function calls and event logs.
+
+mod contract;
+mod decode;
+mod encode;
+mod param_type;
+mod token;
+
+pub use self::contract::{Contract, Event, EventParam, Function};
+pub use self::decode::decode;
+pub use self::encode::encode;
+pub use self::param_type::{parse_list, ParamType};
+pub use self::token::Token;
+
+use self::decode::decode_value;
+use alloc::string::String;
+use core::fmt;
+use primitive_types::H256;
+use sha3::{Digest, Keccak256};
+
+/// ABI error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+	/// Type string could not be parsed.
+	InvalidType(String),
+	/// Function signature could not be parsed.
+	InvalidSignature(String),
+	/// Value could not be parsed as its type.
+	InvalidArgument(String),
+	/// Wrong number of arguments for a function.
+	ArgumentCount {
+		/// Number of function inputs.
+		expected: usize,
+		/// Number of arguments given.
+		found: usize,
+	},
+	/// Encoded data does not match the expected types.
+	InvalidData,
+	/// Contract ABI JSON is malformed.
+	InvalidAbi(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::InvalidType(t) => write!(f, "invalid type: {}", t),
+			Self::InvalidSignature(s) => write!(f, "invalid signature: {}", s),
+			Self::InvalidArgument(a) => write!(f, "invalid argument: {}", a),
+			Self::ArgumentCount { expected, found } => {
+				write!(f, "expected {} arguments, found {}", expected, found)
+			}
+			Self::InvalidData => write!(f, "invalid ABI data"),
+			Self::InvalidAbi(e) => write!(f, "invalid contract ABI: {}", e),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+fn keccak(data: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(data).as_slice())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::Log;
+	use primitive_types::{H160, U256};
+
+	#[test]
+	fn test_function_call() {
+		let f = Function::parse("transfer(address,uint256)").unwrap();
+		let args = f
+			.parse_inputs(&["0x00000000000000000000000000000000000000aa", "100"])
+			.unwrap();
+		let data = f.encode_input(&args).unwrap();
+
+		assert_eq!(
+			hex::encode(&data),
+			"a9059cbb\
+			 00000000000000000000000000000000000000000000000000000000000000aa\
+			 0000000000000000000000000000000000000000000000000000000000000064"
+		);
+	}
+
+	#[test]
+	fn test_dynamic_roundtrip() {
+		let types = parse_list("uint256[],(string,bytes),int8,bytes2[2]").unwrap();
+		let tokens = vec![
+			Token::Array(vec![Token::Uint(1.into()), Token::Uint(2.into())]),
+			Token::Tuple(vec![
+				Token::String("hello".into()),
+				Token::Bytes(vec![0xde, 0xad]),
+			]),
+			Token::parse(&ParamType::Int(8), "-1").unwrap(),
+			Token::parse(&types[3], "[0x0102,0x0304]").unwrap(),
+		];
+
+		let data = encode(&tokens);
+		assert_eq!(U256::from_big_endian(&data[..32]), U256::from(32 * 5));
+		assert_eq!(decode(&types, &data).unwrap(), tokens);
+		assert_eq!(tokens[2].to_string(), "-1");
+	}
+
+	#[test]
+	fn test_parse_int_range() {
+		assert!(Token::parse(&ParamType::Uint(8), "255").is_ok());
+		assert!(Token::parse(&ParamType::Uint(8), "1000").is_err());
+		assert!(Token::parse(&ParamType::Int(8), "127").is_ok());
+		assert!(Token::parse(&ParamType::Int(8), "128").is_err());
+		assert!(Token::parse(&ParamType::Int(8), "-128").is_ok());
+		assert!(Token::parse(&ParamType::Int(8), "-129").is_err());
+		assert!(Token::parse(
+			&ParamType::Int(256),
+			"-0x8000000000000000000000000000000000000000000000000000000000000000"
+		)
+		.is_ok());
+	}
+
+	#[test]
+	fn test_decode_log() {
+		let contract = Contract::load(
+			r#"[{"type":"event","name":"Transfer","anonymous":false,"inputs":[
+				{"name":"from","type":"address","indexed":true},
+				{"name":"to","type":"address","indexed":true},
+				{"name":"value","type":"uint256","indexed":false}]}]"#,
+		)
+		.unwrap();
+		let event = &contract.events[0];
+		let from = H160::repeat_byte(1);
+		let log = Log {
+			address: H160::zero(),
+			topics: vec![event.topic(), from.into(), H160::zero().into()],
+			data: encode(&[Token::Uint(7.into())]),
+		};
+
+		assert_eq!(
+			format!("{:?}", event.topic()),
+			"0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+		);
+		let (_, values) = contract.decode_log(&log).unwrap();
+		assert_eq!(values[0], ("from".into(), Token::Address(from)));
+		assert_eq!(values[2], ("value".into(), Token::Uint(7.into())));
+	}
+}
@@ -0,0 +1,185 @@
+use super::Error;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Solidity parameter type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParamType {
+	/// `address`.
+	Address,
+	/// `bytes`.
+	Bytes,
+	/// `intN`, with the bit size.
+	Int(usize),
+	/// `uintN`, with the bit size.
+	Uint(usize),
+	/// `bool`.
+	Bool,
+	/// `string`.
+	String,
+	/// `T[]`.
+	Array(Box<ParamType>),
+	/// `bytesN`, with the byte size.
+	FixedBytes(usize),
+	/// `T[N]`.
+	FixedArray(Box<ParamType>, usize),
+	/// `(T1,T2,...)`.
+	Tuple(Vec<ParamType>),
+}
+
+impl ParamType {
+	/// Whether the type is encoded in the tail section.
+	pub fn is_dynamic(&self) -> bool {
+		match self {
+			Self::Bytes | Self::String | Self::Array(_) => true,
+			Self::FixedArray(inner, _) => inner.is_dynamic(),
+			Self::Tuple(inner) => inner.iter().any(|t| t.is_dynamic()),
+			_ => false,
+		}
+	}
+
+	/// Size of the type in the head section.
+	pub fn head_size(&self) -> usize {
+		if self.is_dynamic() {
+			return 32;
+		}
+
+		match self {
+			Self::FixedArray(inner, len) => inner.head_size() * len,
+			Self::Tuple(inner) => inner.iter().map(|t| t.head_size()).sum(),
+			_ => 32,
+		}
+	}
+
+	/// Parse a single type, such as `uint256`, `bytes32[]` or `(address,bool)[2]`.
+	pub fn parse(s: &str) -> Result<Self, Error> {
+		let s = s.trim();
+		let invalid = || Error::InvalidType(s.to_string());
+
+		if let Some(rest) = s.strip_suffix(']') {
+			let open = rest.rfind('[').ok_or_else(invalid)?;
+			let inner = Box::new(Self::parse(&rest[..open])?);
+			let len = &rest[open + 1..];
+			return if len.is_empty() {
+				Ok(Self::Array(inner))
+			} else {
+				let len = len.parse::<usize>().map_err(|_| invalid())?;
+				Ok(Self::FixedArray(inner, len))
+			};
+		}
+
+		if let Some(inner) = s.strip_prefix('(') {
+			let inner = inner.strip_suffix(')').ok_or_else(invalid)?;
+			return Ok(Self::Tuple(parse_list(inner)?));
+		}
+
+		let kind = match s {
+			"address" => Self::Address,
+			"bytes" => Self::Bytes,
+			"bool" => Self::Bool,
+			"string" => Self::String,
+			"int" => Self::Int(256),
+			"uint" => Self::Uint(256),
+			_ => {
+				if let Some(n) = s.strip_prefix("uint") {
+					Self::Uint(int_size(n).ok_or_else(invalid)?)
+				} else if let Some(n) = s.strip_prefix("int") {
+					Self::Int(int_size(n).ok_or_else(invalid)?)
+				} else if let Some(n) = s.strip_prefix("bytes") {
+					match n.parse::<usize>() {
+						Ok(n) if (1..=32).contains(&n) => Self::FixedBytes(n),
+						_ => return Err(invalid()),
+					}
+				} else {
+					return Err(invalid());
+				}
+			}
+		};
+
+		Ok(kind)
+	}
+}
+
+impl fmt::Display for ParamType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Address => write!(f, "address"),
+			Self::Bytes => write!(f, "bytes"),
+			Self::Int(n) => write!(f, "int{}", n),
+			Self::Uint(n) => write!(f, "uint{}", n),
+			Self::Bool => write!(f, "bool"),
+			Self::String => write!(f, "string"),
+			Self::Array(inner) => write!(f, "{}[]", inner),
+			Self::FixedBytes(n) => write!(f, "bytes{}", n),
+			Self::FixedArray(inner, len) => write!(f, "{}[{}]", inner, len),
+			Self::Tuple(inner) => {
+				write!(f, "(")?;
+				for (i, t) in inner.iter().enumerate() {
+					if i > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{}", t)?;
+				}
+				write!(f, ")")
+			}
+		}
+	}
+}
+
+fn int_size(s: &str) -> Option<usize> {
+	match s.parse::<usize>() {
+		Ok(n) if n > 0 && n <= 256 && n % 8 == 0 => Some(n),
+		_ => None,
+	}
+}
+
+/// Parse a comma separated list of types, such as `address,uint256`.
+pub fn parse_list(s: &str) -> Result<Vec<ParamType>, Error> {
+	split_top_level(s)?
+		.into_iter()
+		.map(ParamType::parse)
+		.collect()
+}
+
+/// Split `s` on commas that are not nested in brackets or parentheses.
+pub(crate) fn split_top_level(s: &str) -> Result<Vec<&str>, Error> {
+	let mut parts = Vec::new();
+	let mut depth = 0usize;
+	let mut start = 0;
+
+	for (i, c) in s.char_indices() {
+		match c {
+			'(' | '[' => depth += 1,
+			')' | ']' => {
+				depth = depth
+					.checked_sub(1)
+					.ok_or_else(|| Error::InvalidType(s.to_string()))?
+			}
+			',' if depth == 0 => {
+				parts.push(s[start..i].trim());
+				start = i + 1;
+			}
+			_ => (),
+		}
+	}
+	if depth != 0 {
+		return Err(Error::InvalidType(s.to_string()));
+	}
+
+	let last = s[start..].trim();
+	if !last.is_empty() || !parts.is_empty() {
+		parts.push(last);
+	}
+
+	Ok(parts)
+}
+
+pub(crate) fn join(types: &[ParamType]) -> String {
+	types
+		.iter()
+		.map(|t| t.to_string())
+		.collect::<Vec<_>>()
+		.join(",")
+}
@@ -0,0 +1,186 @@
+use super::param_type::split_top_level;
+use super::{Error, ParamType};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use primitive_types::{H160, U256};
+
+/// Solidity value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Token {
+	/// `address`.
+	Address(H160),
+	/// `bytesN`. Only the first `N` bytes are kept.
+	FixedBytes(Vec<u8>),
+	/// `bytes`.
+	Bytes(Vec<u8>),
+	/// `intN`, in two's complement.
+	Int(U256),
+	/// `uintN`.
+	Uint(U256),
+	/// `bool`.
+	Bool(bool),
+	/// `string`.
+	String(String),
+	/// `T[N]`.
+	FixedArray(Vec<Token>),
+	/// `T[]`.
+	Array(Vec<Token>),
+	/// `(T1,T2,...)`.
+	Tuple(Vec<Token>),
+}
+
+impl Token {
+	/// Whether the token is encoded in the tail section.
+	pub fn is_dynamic(&self) -> bool {
+		match self {
+			Self::Bytes(_) | Self::String(_) | Self::Array(_) => true,
+			Self::FixedArray(inner) | Self::Tuple(inner) => inner.iter().any(|t| t.is_dynamic()),
+			_ => false,
+		}
+	}
+
+	/// Parse a human readable value of the given type.
+	///
+	/// Integers are decimal, or hex with a `0x` prefix, and `int` values may be
+	/// negative. Addresses and bytes are hex. Arrays are written `[a,b]` and
+	/// tuples `(a,b)`. Strings are taken verbatim, unless wrapped in double
+	/// quotes inside an array or tuple.
+	pub fn parse(kind: &ParamType, value: &str) -> Result<Self, Error> {
+		let value = value.trim();
+		let invalid = || Error::InvalidArgument(value.to_string());
+
+		let token = match kind {
+			ParamType::Address => {
+				let bytes = parse_hex(value).ok_or_else(invalid)?;
+				if bytes.len() != 20 {
+					return Err(invalid());
+				}
+				Self::Address(H160::from_slice(&bytes))
+			}
+			ParamType::Bytes => Self::Bytes(parse_hex(value).ok_or_else(invalid)?),
+			ParamType::FixedBytes(len) => {
+				let bytes = parse_hex(value).ok_or_else(invalid)?;
+				if bytes.len() != *len {
+					return Err(invalid());
+				}
+				Self::FixedBytes(bytes)
+			}
+			ParamType::Uint(bits) => {
+				let v = parse_uint(value).ok_or_else(invalid)?;
+				if v.bits() > *bits {
+					return Err(invalid());
+				}
+				Self::Uint(v)
+			}
+			ParamType::Int(bits) => match value.strip_prefix('-') {
+				Some(abs) => {
+					let abs = parse_uint(abs).ok_or_else(invalid)?;
+					if abs > U256::one() << (bits - 1) {
+						return Err(invalid());
+					}
+					Self::Int((!abs).overflowing_add(U256::one()).0)
+				}
+				None => {
+					let v = parse_uint(value).ok_or_else(invalid)?;
+					if v.bits() >= *bits {
+						return Err(invalid());
+					}
+					Self::Int(v)
+				}
+			},
+			ParamType::Bool => match value {
+				"true" => Self::Bool(true),
+				"false" => Self::Bool(false),
+				_ => return Err(invalid()),
+			},
+			ParamType::String => {
+				let unquoted = value
+					.strip_prefix('"')
+					.and_then(|v| v.strip_suffix('"'))
+					.unwrap_or(value);
+				Self::String(unquoted.to_string())
+			}
+			ParamType::Array(inner) => Self::Array(parse_items(inner, value)?),
+			ParamType::FixedArray(inner, len) => {
+				let items = parse_items(inner, value)?;
+				if items.len() != *len {
+					return Err(invalid());
+				}
+				Self::FixedArray(items)
+			}
+			ParamType::Tuple(inner) => {
+				let body = value
+					.strip_prefix('(')
+					.and_then(|v| v.strip_suffix(')'))
+					.ok_or_else(invalid)?;
+				let parts = split_top_level(body)?;
+				if parts.len() != inner.len() {
+					return Err(invalid());
+				}
+				Self::Tuple(
+					inner
+						.iter()
+						.zip(parts)
+						.map(|(kind, part)| Self::parse(kind, part))
+						.collect::<Result<_, _>>()?,
+				)
+			}
+		};
+
+		Ok(token)
+	}
+}
+
+fn parse_items(kind: &ParamType, value: &str) -> Result<Vec<Token>, Error> {
+	let body = value
+		.strip_prefix('[')
+		.and_then(|v| v.strip_suffix(']'))
+		.ok_or_else(|| Error::InvalidArgument(value.to_string()))?;
+
+	split_top_level(body)?
+		.into_iter()
+		.map(|part| Token::parse(kind, part))
+		.collect()
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+	hex::decode(value.strip_prefix("0x").unwrap_or(value)).ok()
+}
+
+fn parse_uint(value: &str) -> Option<U256> {
+	match value.strip_prefix("0x") {
+		Some(hex) => U256::from_str_radix(hex, 16).ok(),
+		None => U256::from_dec_str(value).ok(),
+	}
+}
+
+fn write_list(f: &mut fmt::Formatter, tokens: &[Token], open: &str, close: &str) -> fmt::Result {
+	write!(f, "{}", open)?;
+	for (i, t) in tokens.iter().enumerate() {
+		if i > 0 {
+			write!(f, ",")?;
+		}
+		write!(f, "{}", t)?;
+	}
+	write!(f, "{}", close)
+}
+
+/// Formats the token in the same notation accepted by [`Token::parse`].
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Address(a) => write!(f, "{:?}", a),
+			Self::FixedBytes(b) | Self::Bytes(b) => write!(f, "0x{}", hex::encode(b)),
+			Self::Uint(v) => write!(f, "{}", v),
+			Self::Int(v) if v.bit(255) => {
+				write!(f, "-{}", (!*v).overflowing_add(U256::one()).0)
+			}
+			Self::Int(v) => write!(f, "{}", v),
+			Self::Bool(b) => write!(f, "{}", b),
+			Self::String(s) => write!(f, "{:?}", s),
+			Self::FixedArray(t) | Self::Array(t) => write_list(f, t, "[", "]"),
+			Self::Tuple(t) => write_list(f, t, "(", ")"),
+		}
+	}
+}
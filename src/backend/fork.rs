@@ -0,0 +1,671 @@
+use super::sql::{MemoryBackend, MemoryVicinity};
+use super::{Apply, ApplyBackend, Backend, Basic, Log};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use primitive_types::{H160, H256, U256};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use sqlite::State;
+
+/// Upstream JSON-RPC error.
+#[derive(Debug)]
+pub enum UpstreamError {
+	/// The request could not be sent, or the response could not be read.
+	Transport(String),
+	/// The node returned a JSON-RPC error.
+	Rpc(Value),
+	/// The node returned a result of an unexpected form.
+	InvalidResult(Value),
+}
+
+/// Blocking client of an Ethereum JSON-RPC endpoint.
+pub struct Upstream {
+	url: String,
+	agent: ureq::Agent,
+	id: Cell<u64>,
+	/// First error of a fetch made through the `Backend` interface, which
+	/// cannot return it.
+	error: RefCell<Option<UpstreamError>>,
+}
+
+impl Upstream {
+	/// Create a client of the endpoint at `url`.
+	pub fn new(url: String) -> Self {
+		Self {
+			url,
+			agent: ureq::Agent::new(),
+			id: Cell::new(0),
+			error: RefCell::new(None),
+		}
+	}
+
+	/// Take the first error recorded since the last call, if any.
+	pub fn take_error(&self) -> Option<UpstreamError> {
+		self.error.borrow_mut().take()
+	}
+
+	/// Record `error`, unless an earlier one has not been taken yet.
+	fn record_error(&self, error: UpstreamError) {
+		self.error.borrow_mut().get_or_insert(error);
+	}
+
+	fn has_error(&self) -> bool {
+		self.error.borrow().is_some()
+	}
+
+	/// Send a request and return its result.
+	pub fn request(&self, method: &str, params: Value) -> Result<Value, UpstreamError> {
+		let id = self.id.get();
+		self.id.set(id + 1);
+
+		let body = json!({
+			"jsonrpc": "2.0",
+			"id": id,
+			"method": method,
+			"params": params,
+		});
+		let response = self
+			.agent
+			.post(&self.url)
+			.set("Content-Type", "application/json")
+			.send_string(&body.to_string())
+			.map_err(|e| UpstreamError::Transport(e.to_string()))?
+			.into_string()
+			.map_err(|e| UpstreamError::Transport(e.to_string()))?;
+		let mut response: Value =
+			serde_json::from_str(&response).map_err(|e| UpstreamError::Transport(e.to_string()))?;
+
+		match response.get_mut("error") {
+			Some(error) if !error.is_null() => Err(UpstreamError::Rpc(error.take())),
+			_ => Ok(response["result"].take()),
+		}
+	}
+
+	/// Number of the latest block.
+	pub fn block_number(&self) -> Result<u64, UpstreamError> {
+		let result = self.request("eth_blockNumber", json!([]))?;
+		Ok(quantity(&result)?.low_u64())
+	}
+
+	/// Chain ID.
+	pub fn chain_id(&self) -> Result<U256, UpstreamError> {
+		quantity(&self.request("eth_chainId", json!([]))?)
+	}
+
+	/// Account information at the given block.
+	pub fn basic(&self, address: H160, block: u64) -> Result<Basic, UpstreamError> {
+		let params = json!([format!("{:?}", address), format!("{:#x}", block)]);
+		Ok(Basic {
+			balance: quantity(&self.request("eth_getBalance", params.clone())?)?,
+			nonce: quantity(&self.request("eth_getTransactionCount", params)?)?,
+		})
+	}
+
+	/// Code at the given block.
+	pub fn code(&self, address: H160, block: u64) -> Result<Vec<u8>, UpstreamError> {
+		let result = self.request(
+			"eth_getCode",
+			json!([format!("{:?}", address), format!("{:#x}", block)]),
+		)?;
+		bytes(&result)
+	}
+
+	/// Storage value at the given block.
+	pub fn storage(&self, address: H160, index: H256, block: u64) -> Result<H256, UpstreamError> {
+		let result = self.request(
+			"eth_getStorageAt",
+			json!([
+				format!("{:?}", address),
+				format!("{:?}", index),
+				format!("{:#x}", block)
+			]),
+		)?;
+		let value = bytes(&result)?;
+		if value.len() > 32 {
+			return Err(UpstreamError::InvalidResult(result));
+		}
+
+		let mut word = H256::zero();
+		word[32 - value.len()..].copy_from_slice(&value);
+		Ok(word)
+	}
+
+	/// Hash of the given block.
+	pub fn block_hash(&self, number: u64) -> Result<H256, UpstreamError> {
+		let result = self.request(
+			"eth_getBlockByNumber",
+			json!([format!("{:#x}", number), false]),
+		)?;
+		let hash = bytes(&result["hash"])?;
+		if hash.len() != 32 {
+			return Err(UpstreamError::InvalidResult(result));
+		}
+		Ok(H256::from_slice(&hash))
+	}
+}
+
+fn quantity(value: &Value) -> Result<U256, UpstreamError> {
+	value
+		.as_str()
+		.and_then(|s| s.strip_prefix("0x"))
+		.and_then(|s| U256::from_str_radix(s, 16).ok())
+		.ok_or_else(|| UpstreamError::InvalidResult(value.clone()))
+}
+
+fn bytes(value: &Value) -> Result<Vec<u8>, UpstreamError> {
+	value
+		.as_str()
+		.and_then(|s| s.strip_prefix("0x"))
+		.and_then(|s| hex::decode(s).ok())
+		.ok_or_else(|| UpstreamError::InvalidResult(value.clone()))
+}
+
+/// Backend that forks the state of an upstream node at a pinned block.
+///
+/// Values are read from the local overlay first, then from the cache, and only
+/// then fetched from the upstream node. Fetched values are cached in the same
+/// SQLite database as the overlay, and all changes are applied to the overlay.
+/// Accounts deleted and storage reset by those changes are recorded in the
+/// overlay, so that upstream values never reappear for them.
+///
+/// A value that cannot be fetched reads as zero and is not cached. The error
+/// is kept until [`ForkBackend::take_error`], which should be checked before
+/// applying the outcome of a transaction.
+pub struct ForkBackend<'vicinity> {
+	overlay: MemoryBackend<'vicinity>,
+	upstream: Upstream,
+	block: u64,
+	/// Hashes of upstream code, which is fixed at the pinned block.
+	remote_code_hashes: RefCell<BTreeMap<H160, H256>>,
+}
+
+impl<'vicinity> ForkBackend<'vicinity> {
+	/// Create a new fork backend of `upstream` at `block`, stored in the
+	/// database at `db_path`.
+	pub fn new(
+		vicinity: &'vicinity MemoryVicinity,
+		db_path: String,
+		upstream: Upstream,
+		block: u64,
+	) -> Self {
+		let overlay = MemoryBackend::new(vicinity, BTreeMap::new(), db_path, true);
+		overlay
+			.connection()
+			.execute(
+				"
+				CREATE TABLE IF NOT EXISTS fork_accounts (
+					block INTEGER,
+					address BLOB,
+					balance BLOB,
+					nonce BLOB,
+					PRIMARY KEY (block, address)
+				);
+				CREATE TABLE IF NOT EXISTS fork_code (
+					block INTEGER,
+					address BLOB,
+					value BLOB,
+					PRIMARY KEY (block, address)
+				);
+				CREATE TABLE IF NOT EXISTS fork_storage (
+					block INTEGER,
+					address BLOB,
+					idx BLOB,
+					value BLOB,
+					PRIMARY KEY (block, address, idx)
+				);
+				CREATE TABLE IF NOT EXISTS fork_block_hashes (
+					number INTEGER PRIMARY KEY,
+					hash BLOB
+				);
+				",
+			)
+			.unwrap();
+
+		Self {
+			overlay,
+			upstream,
+			block,
+			remote_code_hashes: RefCell::new(BTreeMap::new()),
+		}
+	}
+
+	/// The pinned upstream block.
+	pub fn fork_block(&self) -> u64 {
+		self.block
+	}
+
+	/// Take the first upstream error recorded since the last call, if any.
+	pub fn take_error(&self) -> Option<UpstreamError> {
+		self.upstream.take_error()
+	}
+
+	/// Record a failed fetch, reading the value as zero.
+	fn failed<T: Default>(&self, error: UpstreamError) -> T {
+		self.upstream.record_error(error);
+		T::default()
+	}
+
+	/// The local overlay holding all changes made on top of the fork.
+	pub fn overlay(&self) -> &MemoryBackend<'vicinity> {
+		&self.overlay
+	}
+
+	/// Run `query` against the cache, returning the columns of its first row.
+	fn cached(&self, query: String, columns: usize) -> Option<Vec<Vec<u8>>> {
+		let mut statement = self.overlay.connection().prepare(query).unwrap();
+		if let State::Row = statement.next().unwrap() {
+			Some(
+				(0..columns)
+					.map(|i| statement.read::<Vec<u8>>(i).unwrap())
+					.collect(),
+			)
+		} else {
+			None
+		}
+	}
+
+	fn cache(&self, statement: String) {
+		self.overlay.connection().execute(statement).unwrap();
+	}
+
+	fn remote_basic(&self, address: H160) -> Basic {
+		let block = self.block;
+		let address_hex = hex::encode(address);
+
+		if let Some(row) = self.cached(
+			format!(
+				"SELECT balance, nonce FROM fork_accounts
+				WHERE block = {block} AND address = X'{address_hex}'"
+			),
+			2,
+		) {
+			return Basic {
+				balance: U256::from_big_endian(&row[0]),
+				nonce: U256::from_big_endian(&row[1]),
+			};
+		}
+
+		let basic = match self.upstream.basic(address, block) {
+			Ok(basic) => basic,
+			Err(error) => return self.failed(error),
+		};
+
+		let mut balance_buf = [0u8; 32];
+		basic.balance.to_big_endian(&mut balance_buf);
+		let mut nonce_buf = [0u8; 32];
+		basic.nonce.to_big_endian(&mut nonce_buf);
+		let balance_hex = hex::encode(balance_buf);
+		let nonce_hex = hex::encode(nonce_buf);
+		self.cache(format!(
+			"INSERT OR REPLACE INTO fork_accounts
+			VALUES ({block}, X'{address_hex}', X'{balance_hex}', X'{nonce_hex}')"
+		));
+
+		basic
+	}
+
+	fn remote_code(&self, address: H160) -> Vec<u8> {
+		let block = self.block;
+		let address_hex = hex::encode(address);
+
+		if let Some(mut row) = self.cached(
+			format!(
+				"SELECT value FROM fork_code
+				WHERE block = {block} AND address = X'{address_hex}'"
+			),
+			1,
+		) {
+			return row.remove(0);
+		}
+
+		let code = match self.upstream.code(address, block) {
+			Ok(code) => code,
+			Err(error) => return self.failed(error),
+		};
+
+		let code_hex = hex::encode(&code);
+		self.cache(format!(
+			"INSERT OR REPLACE INTO fork_code
+			VALUES ({block}, X'{address_hex}', X'{code_hex}')"
+		));
+
+		code
+	}
+
+	fn remote_code_hash(&self, address: H160) -> H256 {
+		if let Some(hash) = self.remote_code_hashes.borrow().get(&address) {
+			return *hash;
+		}
+
+		let hash = H256::from_slice(Keccak256::digest(&self.remote_code(address)).as_slice());
+		// Not the hash of the upstream code if it could not be fetched.
+		if !self.upstream.has_error() {
+			self.remote_code_hashes.borrow_mut().insert(address, hash);
+		}
+		hash
+	}
+
+	fn remote_storage(&self, address: H160, index: H256) -> H256 {
+		let block = self.block;
+		let address_hex = hex::encode(address);
+		let index_hex = hex::encode(index);
+
+		if let Some(row) = self.cached(
+			format!(
+				"SELECT value FROM fork_storage
+				WHERE block = {block} AND address = X'{address_hex}' AND idx = X'{index_hex}'"
+			),
+			1,
+		) {
+			return H256::from_slice(&row[0]);
+		}
+
+		let value = match self.upstream.storage(address, index, block) {
+			Ok(value) => value,
+			Err(error) => return self.failed(error),
+		};
+
+		let value_hex = hex::encode(value);
+		self.cache(format!(
+			"INSERT OR REPLACE INTO fork_storage
+			VALUES ({block}, X'{address_hex}', X'{index_hex}', X'{value_hex}')"
+		));
+
+		value
+	}
+
+	fn remote_block_hash(&self, number: u64) -> H256 {
+		if let Some(row) = self.cached(
+			format!("SELECT hash FROM fork_block_hashes WHERE number = {number}"),
+			1,
+		) {
+			return H256::from_slice(&row[0]);
+		}
+
+		let hash = match self.upstream.block_hash(number) {
+			Ok(hash) => hash,
+			Err(error) => return self.failed(error),
+		};
+
+		let hash_hex = hex::encode(hash);
+		self.cache(format!(
+			"INSERT OR REPLACE INTO fork_block_hashes VALUES ({number}, X'{hash_hex}')"
+		));
+
+		hash
+	}
+}
+
+impl<'vicinity> Backend for ForkBackend<'vicinity> {
+	fn gas_price(&self) -> U256 {
+		self.overlay.gas_price()
+	}
+	fn origin(&self) -> H160 {
+		self.overlay.origin()
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		let current = self.overlay.block_number();
+		if number >= current || current - number > U256::from(256) {
+			H256::default()
+		} else if number <= U256::from(self.block) {
+			self.remote_block_hash(number.as_u64())
+		} else {
+			self.overlay.block_hash(number)
+		}
+	}
+	fn block_number(&self) -> U256 {
+		self.overlay.block_number()
+	}
+	fn block_coinbase(&self) -> H160 {
+		self.overlay.block_coinbase()
+	}
+	fn block_timestamp(&self) -> U256 {
+		self.overlay.block_timestamp()
+	}
+	fn block_difficulty(&self) -> U256 {
+		self.overlay.block_difficulty()
+	}
+	fn block_prev_randao(&self) -> H256 {
+		self.overlay.block_prev_randao()
+	}
+	fn block_gas_limit(&self) -> U256 {
+		self.overlay.block_gas_limit()
+	}
+	fn block_base_fee_per_gas(&self) -> U256 {
+		self.overlay.block_base_fee_per_gas()
+	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.overlay.block_blob_base_fee()
+	}
+	fn blob_hash(&self, index: usize) -> Option<H256> {
+		self.overlay.blob_hash(index)
+	}
+
+	fn chain_id(&self) -> U256 {
+		self.overlay.chain_id()
+	}
+
+	fn exists(&self, address: H160) -> bool {
+		let basic = self.basic(address);
+		!basic.balance.is_zero() || !basic.nonce.is_zero() || !self.code(address).is_empty()
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.overlay
+			.find_basic(address)
+			.unwrap_or_else(|| self.remote_basic(address))
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.overlay
+			.find_code(address)
+			.unwrap_or_else(|| self.remote_code(address))
+	}
+
+	fn code_hash(&self, address: H160) -> H256 {
+		self.overlay
+			.find_code_hash(address)
+			.unwrap_or_else(|| self.remote_code_hash(address))
+	}
+
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.overlay
+			.find_storage(address, index)
+			.unwrap_or_else(|| self.remote_storage(address, index))
+	}
+
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		Some(self.storage(address, index))
+	}
+}
+
+impl<'vicinity> ApplyBackend for ForkBackend<'vicinity> {
+	fn apply<A, I, L>(&mut self, values: A, logs: L, delete_empty: bool)
+	where
+		A: IntoIterator<Item = Apply<I>>,
+		I: IntoIterator<Item = (H256, H256)>,
+		L: IntoIterator<Item = Log>,
+	{
+		self.overlay.apply(values, logs, delete_empty)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use jsonrpc_http_server::jsonrpc_core::{IoHandler, Params};
+	use jsonrpc_http_server::{Server, ServerBuilder};
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::Arc;
+
+	/// Upstream node at block 10 with a single account, counting requests.
+	fn upstream(requests: Arc<AtomicUsize>) -> Server {
+		let mut io = IoHandler::new();
+		for (method, result) in [
+			("eth_getBalance", "0x64"),
+			("eth_getTransactionCount", "0x2"),
+			("eth_getCode", "0x6000"),
+			("eth_getStorageAt", "0x2a"),
+		] {
+			let requests = requests.clone();
+			io.add_sync_method(method, move |params: Params| {
+				requests.fetch_add(1, Ordering::SeqCst);
+				let params: Vec<Value> = params.parse()?;
+				assert_eq!(params.last(), Some(&json!("0xa")));
+				Ok(json!(result))
+			});
+		}
+		ServerBuilder::new(io)
+			.start_http(&"127.0.0.1:0".parse().unwrap())
+			.unwrap()
+	}
+
+	fn vicinity() -> MemoryVicinity {
+		MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::from(11),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::zero(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		}
+	}
+
+	#[test]
+	fn test_fork_reads_through_cache() {
+		let requests = Arc::new(AtomicUsize::new(0));
+		let server = upstream(requests.clone());
+
+		let db_path = std::env::temp_dir().join(format!("fork-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = vicinity();
+		let upstream = Upstream::new(format!("http://{}", server.address()));
+		let mut backend =
+			ForkBackend::new(&vicinity, db_path.to_str().unwrap().into(), upstream, 10);
+
+		let address = H160::repeat_byte(0xaa);
+		let index = H256::from_low_u64_be(1);
+		for _ in 0..2 {
+			assert_eq!(backend.basic(address).balance, U256::from(100));
+			assert_eq!(backend.code(address), vec![0x60, 0x00]);
+			assert_eq!(backend.storage(address, index), H256::from_low_u64_be(42));
+		}
+		assert_eq!(
+			backend.code_hash(address),
+			H256::from_slice(Keccak256::digest(&[0x60, 0x00]).as_slice())
+		);
+		assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+		backend.apply(
+			vec![Apply::Modify {
+				address,
+				basic: Basic {
+					balance: U256::from(7),
+					nonce: U256::from(3),
+				},
+				code: None,
+				storage: vec![(index, H256::from_low_u64_be(9))],
+				reset_storage: false,
+			}],
+			Vec::new(),
+			false,
+		);
+		assert_eq!(backend.basic(address).balance, U256::from(7));
+		assert_eq!(backend.code(address), vec![0x60, 0x00]);
+		assert_eq!(backend.storage(address, index), H256::from_low_u64_be(9));
+		assert_eq!(requests.load(Ordering::SeqCst), 4);
+
+		server.close();
+		let _ = std::fs::remove_file(&db_path);
+	}
+
+	#[test]
+	fn test_fork_deleted_state() {
+		let requests = Arc::new(AtomicUsize::new(0));
+		let server = upstream(requests.clone());
+
+		let db_path =
+			std::env::temp_dir().join(format!("fork-deleted-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = vicinity();
+		let upstream = Upstream::new(format!("http://{}", server.address()));
+		let mut backend =
+			ForkBackend::new(&vicinity, db_path.to_str().unwrap().into(), upstream, 10);
+
+		// Redeployed with its storage reset: the upstream slot is gone, but new
+		// writes are kept.
+		let address = H160::repeat_byte(0xaa);
+		let (index, other) = (H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+		backend.apply(
+			vec![Apply::Modify {
+				address,
+				basic: Basic {
+					balance: U256::zero(),
+					nonce: U256::one(),
+				},
+				code: Some(vec![0x00]),
+				storage: vec![(other, H256::from_low_u64_be(9))],
+				reset_storage: true,
+			}],
+			Vec::new(),
+			false,
+		);
+		assert_eq!(backend.storage(address, index), H256::zero());
+		assert_eq!(backend.storage(address, other), H256::from_low_u64_be(9));
+		assert_eq!(
+			backend.code_hash(address),
+			H256::from_slice(Keccak256::digest(&[0x00]).as_slice())
+		);
+
+		// Deleted: nothing is read from upstream again.
+		let deleted = H160::repeat_byte(0xbb);
+		backend.apply(
+			vec![Apply::<Vec<(H256, H256)>>::Delete { address: deleted }],
+			Vec::new(),
+			false,
+		);
+		assert_eq!(backend.basic(deleted), Basic::default());
+		assert!(backend.code(deleted).is_empty());
+		assert_eq!(backend.storage(deleted, index), H256::zero());
+		assert!(!backend.exists(deleted));
+		assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+		server.close();
+		let _ = std::fs::remove_file(&db_path);
+	}
+
+	#[test]
+	fn test_fork_upstream_error() {
+		let db_path =
+			std::env::temp_dir().join(format!("fork-error-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = vicinity();
+		// Nothing listens on port 1.
+		let upstream = Upstream::new("http://127.0.0.1:1".into());
+		let backend = ForkBackend::new(&vicinity, db_path.to_str().unwrap().into(), upstream, 10);
+
+		let address = H160::repeat_byte(0xaa);
+		assert_eq!(backend.basic(address), Basic::default());
+		assert!(backend.code(address).is_empty());
+		assert!(matches!(
+			backend.take_error(),
+			Some(UpstreamError::Transport(_))
+		));
+		assert!(backend.take_error().is_none());
+
+		// Failed fetches are not cached.
+		assert_eq!(backend.basic(address), Basic::default());
+		assert!(backend.take_error().is_some());
+
+		let _ = std::fs::remove_file(&db_path);
+	}
+}
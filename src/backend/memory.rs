@@ -1,7 +1,9 @@
 use super::{Apply, ApplyBackend, Backend, Basic, Log};
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
+use core::cell::RefCell;
 use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
 
 /// Vivinity value of a memory backend.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -27,10 +29,16 @@ pub struct MemoryVicinity {
 	pub block_timestamp: U256,
 	/// Environmental block difficulty.
 	pub block_difficulty: U256,
+	/// Environmental previous block randomness.
+	pub block_prev_randao: H256,
 	/// Environmental block gas limit.
 	pub block_gas_limit: U256,
 	/// Environmental base fee per gas.
 	pub block_base_fee_per_gas: U256,
+	/// Environmental blob base fee.
+	pub block_blob_base_fee: U256,
+	/// Versioned hashes of the blobs of the transaction.
+	pub blob_hashes: Vec<H256>,
 }
 
 /// Account information of a memory backend.
@@ -56,6 +64,8 @@ pub struct MemoryAccount {
 pub struct MemoryBackend<'vicinity> {
 	vicinity: &'vicinity MemoryVicinity,
 	state: BTreeMap<H160, MemoryAccount>,
+	/// Hashes of account code, filled in as they are looked up.
+	code_hashes: RefCell<BTreeMap<H160, H256>>,
 	logs: Vec<Log>,
 }
 
@@ -65,6 +75,7 @@ impl<'vicinity> MemoryBackend<'vicinity> {
 		Self {
 			vicinity,
 			state,
+			code_hashes: RefCell::new(BTreeMap::new()),
 			logs: Vec::new(),
 		}
 	}
@@ -76,6 +87,8 @@ impl<'vicinity> MemoryBackend<'vicinity> {
 
 	/// Get a mutable reference to the underlying `BTreeMap` storing the state.
 	pub fn state_mut(&mut self) -> &mut BTreeMap<H160, MemoryAccount> {
+		// Code may be changed through the reference.
+		self.code_hashes.get_mut().clear();
 		&mut self.state
 	}
 }
@@ -110,12 +123,21 @@ impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 	fn block_difficulty(&self) -> U256 {
 		self.vicinity.block_difficulty
 	}
+	fn block_prev_randao(&self) -> H256 {
+		self.vicinity.block_prev_randao
+	}
 	fn block_gas_limit(&self) -> U256 {
 		self.vicinity.block_gas_limit
 	}
 	fn block_base_fee_per_gas(&self) -> U256 {
 		self.vicinity.block_base_fee_per_gas
 	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.vicinity.block_blob_base_fee
+	}
+	fn blob_hash(&self, index: usize) -> Option<H256> {
+		self.vicinity.blob_hashes.get(index).copied()
+	}
 
 	fn chain_id(&self) -> U256 {
 		self.vicinity.chain_id
@@ -142,6 +164,17 @@ impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 			.unwrap_or_default()
 	}
 
+	fn code_hash(&self, address: H160) -> H256 {
+		*self
+			.code_hashes
+			.borrow_mut()
+			.entry(address)
+			.or_insert_with(|| {
+				let code = self.state.get(&address).map(|v| &v.code[..]).unwrap_or(&[]);
+				H256::from_slice(Keccak256::digest(code).as_slice())
+			})
+	}
+
 	fn storage(&self, address: H160, index: H256) -> H256 {
 		self.state
 			.get(&address)
@@ -176,6 +209,7 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 						account.nonce = basic.nonce;
 						if let Some(code) = code {
 							account.code = code;
+							self.code_hashes.get_mut().remove(&address);
 						}
 
 						if reset_storage {
@@ -208,10 +242,12 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 
 					if is_empty && delete_empty {
 						self.state.remove(&address);
+						self.code_hashes.get_mut().remove(&address);
 					}
 				}
 				Apply::Delete { address } => {
 					self.state.remove(&address);
+					self.code_hashes.get_mut().remove(&address);
 				}
 			}
 		}
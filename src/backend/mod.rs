@@ -2,6 +2,7 @@
 //!
 //! Backends store state information of the VM, and exposes it to runtime.
 
+pub mod fork;
 pub mod memory;
 pub mod sql;
 
@@ -9,6 +10,7 @@ pub use self::memory::{MemoryAccount, MemoryBackend, MemoryVicinity};
 
 use alloc::vec::Vec;
 use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
 
 /// Basic account information.
 #[derive(Clone, Eq, PartialEq, Debug, Default)]
@@ -67,10 +69,16 @@ pub trait Backend {
 	fn block_timestamp(&self) -> U256;
 	/// Environmental block difficulty.
 	fn block_difficulty(&self) -> U256;
+	/// Environmental previous block randomness.
+	fn block_prev_randao(&self) -> H256;
 	/// Environmental block gas limit.
 	fn block_gas_limit(&self) -> U256;
 	/// Environmental block base fee.
 	fn block_base_fee_per_gas(&self) -> U256;
+	/// Environmental block blob base fee.
+	fn block_blob_base_fee(&self) -> U256;
+	/// Versioned hash of the blob at `index` of the transaction, if any.
+	fn blob_hash(&self, index: usize) -> Option<H256>;
 	/// Environmental chain ID.
 	fn chain_id(&self) -> U256;
 
@@ -80,6 +88,12 @@ pub trait Backend {
 	fn basic(&self, address: H160) -> Basic;
 	/// Get account code.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get the Keccak hash of account code. Backends that store code hashes
+	/// should override this, so that looking up the hash does not load and
+	/// hash the code.
+	fn code_hash(&self, address: H160) -> H256 {
+		H256::from_slice(Keccak256::digest(&self.code(address)).as_slice())
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: H256) -> H256;
 	/// Get original storage value of address at index, if available.
@@ -2,6 +2,7 @@ use super::{Apply, ApplyBackend, Backend, Basic, Log};
 use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
 use sqlite::{Connection};
 use sqlite::State;
 
@@ -30,10 +31,16 @@ pub struct MemoryVicinity {
 	pub block_timestamp: U256,
 	/// Environmental block difficulty.
 	pub block_difficulty: U256,
+	/// Environmental previous block randomness.
+	pub block_prev_randao: H256,
 	/// Environmental block gas limit.
 	pub block_gas_limit: U256,
 	/// Environmental base fee per gas.
 	pub block_base_fee_per_gas: U256,
+	/// Environmental blob base fee.
+	pub block_blob_base_fee: U256,
+	/// Versioned hashes of the blobs of the transaction.
+	pub blob_hashes: Vec<H256>,
 }
 
 /// Account information of a memory backend.
@@ -62,6 +69,8 @@ pub struct MemoryBackend<'vicinity> {
 	db: Connection,
 	vicinity: &'vicinity MemoryVicinity,
 	state: BTreeMap<H160, MemoryAccount>,
+	/// Accounts given at creation, which `state` is rebuilt from on revert.
+	genesis: BTreeMap<H160, MemoryAccount>,
 	logs: Vec<Log>,
 }
 
@@ -109,7 +118,8 @@ impl<'vicinity> MemoryBackend<'vicinity> {
 				CREATE TABLE code (
 					time INTEGER PRIMARY KEY AUTOINCREMENT, 
 					address BLOB, 
-					value BLOB
+					value BLOB,
+					hash BLOB
 				);
 				CREATE TABLE storage (
 					time INTEGER PRIMARY KEY AUTOINCREMENT, 
@@ -127,14 +137,290 @@ impl<'vicinity> MemoryBackend<'vicinity> {
 			);
 		}
 
+		// Databases created before code hashes were stored lack the column,
+		// and their rows have no hash. This fails if the column exists.
+		let _ = connection.execute("ALTER TABLE code ADD COLUMN hash BLOB");
+		// Likewise for the number of logs of snapshots, if there are any.
+		let _ = connection.execute("ALTER TABLE snapshots ADD COLUMN logs INTEGER");
+
 		Self {
 			db: connection,
 			vicinity,
+			genesis: state.clone(),
 			state,
 			logs: Vec::new(),
 		}
 	}
 
+	/// Begin a database transaction. Changes applied after this are only
+	/// persisted once `commit` is called.
+	pub fn begin(&self) {
+		self.db.execute("BEGIN").unwrap();
+	}
+
+	/// Commit the transaction started by `begin`.
+	pub fn commit(&self) {
+		self.db.execute("COMMIT").unwrap();
+	}
+
+	/// Discard all changes applied since `begin`.
+	pub fn rollback(&self) {
+		self.db.execute("ROLLBACK").unwrap();
+	}
+
+	fn create_snapshots_table(&self) {
+		self.db.execute("
+			CREATE TABLE IF NOT EXISTS snapshots (
+				id INTEGER PRIMARY KEY AUTOINCREMENT,
+				code INTEGER,
+				storage INTEGER,
+				accounts INTEGER,
+				logs INTEGER
+			);
+		").unwrap();
+	}
+
+	/// Record the current state position, returning an ID to `revert` to.
+	pub fn snapshot(&self) -> u64 {
+		self.create_snapshots_table();
+		let logs = self.logs.len();
+		self.db.execute(format!("
+			INSERT INTO snapshots VALUES (
+				NULL,
+				(SELECT IFNULL(MAX(time), 0) FROM code),
+				(SELECT IFNULL(MAX(time), 0) FROM storage),
+				(SELECT IFNULL(MAX(time), 0) FROM accounts),
+				{logs}
+			);
+		")).unwrap();
+
+		let mut statement = self.db.prepare("SELECT MAX(id) FROM snapshots").unwrap();
+		statement.next().unwrap();
+		statement.read::<i64>(0).unwrap() as u64
+	}
+
+	/// Discard everything written after the snapshot `id` was taken. The
+	/// snapshot and all later ones are consumed. Returns `false` if there is
+	/// no such snapshot.
+	pub fn revert(&mut self, id: u64) -> bool {
+		self.create_snapshots_table();
+		let mut statement = self.db.prepare(format!("
+			SELECT code, storage, accounts, IFNULL(logs, 0) FROM snapshots WHERE id = {id}
+		")).unwrap();
+
+		if let State::Row = statement.next().unwrap() {
+			let code = statement.read::<i64>(0).unwrap();
+			let storage = statement.read::<i64>(1).unwrap();
+			let accounts = statement.read::<i64>(2).unwrap();
+			let logs = statement.read::<i64>(3).unwrap();
+			self.db.execute(format!("
+				DELETE FROM code WHERE time > {code};
+				DELETE FROM storage WHERE time > {storage};
+				DELETE FROM accounts WHERE time > {accounts};
+				DELETE FROM snapshots WHERE id >= {id};
+			")).unwrap();
+			drop(statement);
+			self.rebuild_state();
+			self.logs.truncate(logs as usize);
+			true
+		} else {
+			false
+		}
+	}
+
+	/// Rebuild the accounts held in memory from the genesis accounts and the
+	/// latest account rows of the database.
+	fn rebuild_state(&mut self) {
+		let mut state = self.genesis.clone();
+		let mut statement = self.db.prepare("
+			SELECT address, balance, nonce FROM accounts AS latest
+			WHERE time = (SELECT MAX(time) FROM accounts WHERE address = latest.address)
+		").unwrap();
+
+		while let State::Row = statement.next().unwrap() {
+			let address = H160::from_slice(&statement.read::<Vec<u8>>(0).unwrap());
+			let balance = statement.read::<Vec<u8>>(1).unwrap();
+			let nonce = statement.read::<Vec<u8>>(2).unwrap();
+			// Deleted accounts have an empty row.
+			if balance.is_empty() {
+				state.remove(&address);
+				continue;
+			}
+
+			let account = state.entry(address).or_default();
+			account.balance = U256::from_big_endian(&balance);
+			account.nonce = U256::from_big_endian(&nonce);
+			account.code = self.find_code(address).unwrap_or_default();
+		}
+
+		self.state = state;
+	}
+
+	/// Record the deletion of an account, as empty code and account rows.
+	fn delete_account(&mut self, address: H160) {
+		let address_hex = hex::encode(address);
+		self.insert_code(address, &[]);
+		self.db.execute(format!("
+			INSERT INTO accounts VALUES (NULL, X'{address_hex}', X'', X'');
+		")).unwrap();
+		self.wipe_storage(address);
+		self.state.remove(&address);
+	}
+
+	fn create_config_table(&self) {
+		self.db.execute("
+			CREATE TABLE IF NOT EXISTS config (
+				id INTEGER PRIMARY KEY CHECK (id = 0),
+				value BLOB
+			);
+		").unwrap();
+	}
+
+	/// Chain configuration stored in the database, if any, so that every node
+	/// over it runs the same rules. See `config::load`.
+	pub fn chain_config(&self) -> Option<String> {
+		self.create_config_table();
+		let mut statement = self.db.prepare("SELECT value FROM config WHERE id = 0").unwrap();
+
+		if let State::Row = statement.next().unwrap() {
+			String::from_utf8(statement.read::<Vec<u8>>(0).unwrap()).ok()
+		} else {
+			None
+		}
+	}
+
+	/// Store the chain configuration, replacing any previous one.
+	pub fn set_chain_config(&self, config: &str) {
+		self.create_config_table();
+		let config_hex = hex::encode(config);
+		self.db.execute(format!("
+			INSERT OR REPLACE INTO config VALUES (0, X'{config_hex}');
+		")).unwrap();
+	}
+
+	/// Latest account information written to the database, if any.
+	pub fn find_basic(&self, address: H160) -> Option<Basic> {
+		let address_hex = hex::encode(address);
+		
+		let mut statement = self.db
+			.prepare(format!("
+				SELECT * FROM accounts 
+				WHERE address = X'{address_hex}' 
+				ORDER BY time DESC LIMIT 1
+			"))
+			.unwrap();
+		
+		if let State::Row = statement.next().unwrap() {
+			let balance = statement.read::<Vec<u8>>(2).unwrap();
+			let nonce = statement.read::<Vec<u8>>(3).unwrap();
+			Some(Basic{
+				balance: U256::from_big_endian(&balance),
+				nonce: U256::from_big_endian(&nonce),
+			})
+		} else {
+			None
+		}
+	}
+
+	/// Latest code written to the database, if any.
+	pub fn find_code(&self, address: H160) -> Option<Vec<u8>> {
+		let address_hex = hex::encode(address);
+		let mut statement = self.db
+			.prepare(format!("
+				SELECT * FROM code 
+				WHERE address = X'{address_hex}'
+				ORDER BY time DESC LIMIT 1
+			"))
+			.unwrap();
+		
+		if let State::Row = statement.next().unwrap() {
+			Some(statement.read::<Vec<u8>>(2).unwrap())
+		} else {
+			None
+		}
+	}
+
+	/// Hash of the latest code written to the database, if any.
+	pub fn find_code_hash(&self, address: H160) -> Option<H256> {
+		let address_hex = hex::encode(address);
+		let mut statement = self.db
+			.prepare(format!("
+				SELECT IFNULL(hash, X''), value FROM code
+				WHERE address = X'{address_hex}'
+				ORDER BY time DESC LIMIT 1
+			"))
+			.unwrap();
+
+		if let State::Row = statement.next().unwrap() {
+			let hash = statement.read::<Vec<u8>>(0).unwrap();
+			if hash.is_empty() {
+				// Written before hashes were stored.
+				let code = statement.read::<Vec<u8>>(1).unwrap();
+				Some(H256::from_slice(Keccak256::digest(&code).as_slice()))
+			} else {
+				Some(H256::from_slice(&hash))
+			}
+		} else {
+			None
+		}
+	}
+
+	/// Record `code` as the code of `address`, along with its hash.
+	fn insert_code(&self, address: H160, code: &[u8]) {
+		let address_hex = hex::encode(address);
+		let code_hex = hex::encode(code);
+		let hash_hex = hex::encode(Keccak256::digest(code));
+		self.db.execute(format!("
+			INSERT INTO code VALUES (NULL, X'{address_hex}', X'{code_hex}', X'{hash_hex}')
+		")).unwrap();
+	}
+
+	/// Latest storage value written to the database, if any. Slots of an
+	/// account whose storage was wiped, and not written since, are zero.
+	pub fn find_storage(&self, address: H160, index: H256) -> Option<H256> {
+		let address_hex = hex::encode(address);
+		let index_hex = hex::encode(index);
+		let mut statement = self.db
+			.prepare(format!("
+				SELECT * FROM storage 
+				WHERE 
+					address = X'{address_hex}' 
+					AND (idx = X'{index_hex}' OR idx = X'')
+				ORDER BY time DESC LIMIT 1
+			"))
+			.unwrap();
+		
+		if let State::Row = statement.next().unwrap() {
+			let value = statement.read::<Vec<u8>>(3).unwrap();
+			if value.is_empty() {
+				Some(H256::zero())
+			} else {
+				Some(H256::from_slice(&value))
+			}
+		} else {
+			None
+		}
+	}
+
+	/// Record that all storage of `address` was wiped, as a row with an empty
+	/// index that hides older rows of the account.
+	fn wipe_storage(&self, address: H160) {
+		let address_hex = hex::encode(address);
+		self.db.execute(format!("
+			INSERT INTO storage VALUES (NULL, X'{address_hex}', X'', X'')
+		")).unwrap();
+	}
+
+	/// Logs applied to the backend.
+	pub fn logs(&self) -> &[Log] {
+		&self.logs
+	}
+
+	/// The database connection.
+	pub(crate) fn connection(&self) -> &Connection {
+		&self.db
+	}
+
 	/// Get the underlying `BTreeMap` storing the state.
 	pub fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
 		&self.state
@@ -176,102 +462,59 @@ impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 	fn block_difficulty(&self) -> U256 {
 		self.vicinity.block_difficulty
 	}
+	fn block_prev_randao(&self) -> H256 {
+		self.vicinity.block_prev_randao
+	}
 	fn block_gas_limit(&self) -> U256 {
 		self.vicinity.block_gas_limit
 	}
 	fn block_base_fee_per_gas(&self) -> U256 {
 		self.vicinity.block_base_fee_per_gas
 	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.vicinity.block_blob_base_fee
+	}
+	fn blob_hash(&self, index: usize) -> Option<H256> {
+		self.vicinity.blob_hashes.get(index).copied()
+	}
 
 	fn chain_id(&self) -> U256 {
 		self.vicinity.chain_id
 	}
 
 	fn exists(&self, address: H160) -> bool {
-		self.state.contains_key(&address)
-	}
-
-	fn basic(&self, address: H160) -> Basic {
 		let address_hex = hex::encode(address);
-		
 		let mut statement = self.db
 			.prepare(format!("
-				SELECT * FROM accounts 
-				WHERE address = X'{address_hex}' 
+				SELECT balance FROM accounts
+				WHERE address = X'{address_hex}'
 				ORDER BY time DESC LIMIT 1
 			"))
 			.unwrap();
-		
+
 		if let State::Row = statement.next().unwrap() {
-			let balance = statement.read::<Vec<u8>>(2).unwrap();
-			let nonce = statement.read::<Vec<u8>>(3).unwrap();
-			return Basic{
-				balance: U256::from_big_endian(&balance),
-				nonce: U256::from_big_endian(&nonce),
-			}
+			// Deleted accounts have an empty row.
+			!statement.read::<Vec<u8>>(0).unwrap().is_empty()
 		} else {
-			return Basic{
-				balance: U256::zero(),
-				nonce: U256::zero()
-			}
+			self.state.contains_key(&address)
 		}
+	}
 
-		// self.state
-		// 	.get(&address)
-		// 	.map(|a| Basic {
-		// 		balance: a.balance,
-		// 		nonce: a.nonce,
-		// 	})
-		// 	.unwrap_or_default()
+	fn basic(&self, address: H160) -> Basic {
+		self.find_basic(address).unwrap_or_default()
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
-		let address_hex = hex::encode(address);
-		let mut statement = self.db
-			.prepare(format!("
-				SELECT * FROM code 
-				WHERE address = X'{address_hex}'
-				ORDER BY time DESC LIMIT 1
-			"))
-			.unwrap();
-		
-		if let State::Row = statement.next().unwrap() {
-			let code = statement.read::<Vec<u8>>(2).unwrap();
-			return code
-		} else {
-			return vec![]
-		}
+		self.find_code(address).unwrap_or_default()
+	}
 
-		// self.state
-		// 	.get(&address)
-		// 	.map(|v| v.code.clone())
-		// 	.unwrap_or_default()
+	fn code_hash(&self, address: H160) -> H256 {
+		self.find_code_hash(address)
+			.unwrap_or_else(|| H256::from_slice(Keccak256::digest(&[]).as_slice()))
 	}
 
 	fn storage(&self, address: H160, index: H256) -> H256 {
-		let address_hex = hex::encode(address);
-		let index_hex = hex::encode(index);
-		let mut statement = self.db
-			.prepare(format!("
-				SELECT * FROM storage 
-				WHERE 
-					address = X'{address_hex}' 
-					AND idx = X'{index_hex}'
-				ORDER BY time DESC LIMIT 1
-			"))
-			.unwrap();
-		
-		if let State::Row = statement.next().unwrap() {
-			let value = statement.read::<Vec<u8>>(3).unwrap();
-			return H256::from_slice(&value)
-		} else {
-			return H256::zero();
-		}
-
-		// self.state
-		// 	.get(&address)
-		// 	.map(|v| v.storage.get(&index).cloned().unwrap_or_default())
-		// 	.unwrap_or_default()
+		self.find_storage(address, index).unwrap_or_default()
 	}
 
 	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
@@ -293,23 +536,23 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 					basic,
 					code,
 					storage,
-					reset_storage: _,
+					reset_storage,
 				} => {
 					let is_empty = {
 
 						let address_hex = hex::encode(address);
 
+						if reset_storage {
+							self.wipe_storage(address);
+						}
+
 						// 1. Borrow code
 						// 2. Unwrap Option<Vec<u8>> into Vec<u8>
 						// Usually we borrow using the & operator
 						// However we don't want to borrow the option, do we? 
 						if let Some(code) = &code {
-							let code_hex = hex::encode(code);
-						
 							// 1. Code.
-							self.db.execute(format!("
-								INSERT INTO code VALUES (NULL, X'{address_hex}', X'{code_hex}')
-							")).unwrap();
+							self.insert_code(address, code);
 						} else {
 							// None means leaving it unchanged. 
 						} 
@@ -372,25 +615,11 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 					};
 
 					if is_empty && delete_empty {
-						self.state.remove(&address);
+						self.delete_account(address);
 					}
 				}
 				Apply::Delete { address } => {
-					// self.db.execute(format!("
-					// 	INSERT INTO storage VALUES ('{address_hex}', '{index_hex}', '{value_hex}')
-					// ")).unwrap();
-
-					let address_hex = hex::encode(address);
-					
-					self.db.execute(format!("
-						INSERT INTO code VALUES (X'{address_hex}', NULL)
-					")).unwrap();
-
-					self.db.execute(format!("
-						INSERT INTO accounts VALUES (X'{address_hex}', NULL, NULL)
-					")).unwrap();
-
-					// self.state.remove(&address);
+					self.delete_account(address);
 				}
 			}
 		}
@@ -400,3 +629,134 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_snapshot_revert() {
+		let db_path = std::env::temp_dir().join(format!("snapshot-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::zero(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new(), db_path.to_str().unwrap().into(), true);
+
+		let address = H160::repeat_byte(1);
+		let set_balance = |backend: &mut MemoryBackend, balance: u64| {
+			backend.apply(
+				vec![Apply::Modify {
+					address,
+					basic: Basic { balance: balance.into(), nonce: U256::zero() },
+					code: None,
+					storage: Vec::new(),
+					reset_storage: false,
+				}],
+				Vec::new(),
+				false,
+			);
+		};
+
+		set_balance(&mut backend, 1);
+		let first = backend.snapshot();
+		set_balance(&mut backend, 2);
+		let second = backend.snapshot();
+		set_balance(&mut backend, 3);
+		backend.apply(
+			Vec::<Apply<Vec<(H256, H256)>>>::new(),
+			vec![Log { address, topics: Vec::new(), data: Vec::new() }],
+			false,
+		);
+		assert_eq!(backend.logs().len(), 1);
+
+		let created = H160::repeat_byte(2);
+		backend.apply(
+			vec![Apply::Modify {
+				address: created,
+				basic: Basic { balance: U256::zero(), nonce: U256::one() },
+				code: Some(vec![0x00]),
+				storage: vec![(H256::zero(), H256::repeat_byte(1))],
+				reset_storage: false,
+			}],
+			Vec::new(),
+			false,
+		);
+		assert!(backend.exists(created));
+		backend.apply(vec![Apply::<Vec<(H256, H256)>>::Delete { address }], Vec::new(), false);
+		assert!(!backend.exists(address));
+		assert!(!backend.state().contains_key(&address));
+
+		assert!(backend.revert(second));
+		assert!(backend.logs().is_empty());
+		assert_eq!(backend.basic(address).balance, U256::from(2));
+		assert!(backend.exists(address));
+		assert!(!backend.exists(created));
+		assert!(!backend.state().contains_key(&created));
+		assert_eq!(backend.storage(created, H256::zero()), H256::zero());
+		assert!(backend.revert(first));
+		assert_eq!(backend.basic(address).balance, U256::from(1));
+		assert!(!backend.revert(second));
+
+		let _ = std::fs::remove_file(&db_path);
+	}
+
+	#[test]
+	fn test_code_hash() {
+		let db_path = std::env::temp_dir().join(format!("code-hash-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::zero(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let mut backend = MemoryBackend::new(&vicinity, BTreeMap::new(), db_path.to_str().unwrap().into(), true);
+		let hash = |code: &[u8]| H256::from_slice(Keccak256::digest(code).as_slice());
+
+		let address = H160::repeat_byte(1);
+		assert_eq!(backend.code_hash(address), hash(&[]));
+		backend.apply(
+			vec![Apply::Modify {
+				address,
+				basic: Basic { balance: U256::zero(), nonce: U256::one() },
+				code: Some(vec![0x60, 0x00]),
+				storage: Vec::new(),
+				reset_storage: false,
+			}],
+			Vec::new(),
+			false,
+		);
+		assert_eq!(backend.code_hash(address), hash(&[0x60, 0x00]));
+
+		// Rows written before hashes were stored have none.
+		let address_hex = hex::encode(address);
+		backend.connection().execute(format!("
+			INSERT INTO code (address, value) VALUES (X'{address_hex}', X'00')
+		")).unwrap();
+		assert_eq!(backend.code_hash(address), hash(&[0x00]));
+
+		let _ = std::fs::remove_file(&db_path);
+	}
+}
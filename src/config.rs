@@ -0,0 +1,169 @@
+//! # Config files
+//!
+//! [`Config`] as JSON or TOML, so that private chains can tune costs and
+//! limits without code changes. A file names the fork it inherits from with
+//! `base`, and overrides individual fields of its configuration:
+//!
+//! ```toml
+//! base = "Istanbul"
+//! gas_sload = 1000
+//! create_contract_limit = 49152
+//! call_stack_limit = 256
+//! ```
+//!
+//! Without `base`, every field must be given, as written by [`to_json`].
+
+use crate::backend::sql::MemoryBackend;
+use crate::{Config, Fork};
+use alloc::string::{String, ToString};
+use core::fmt;
+use serde_json::{Map, Value};
+
+/// Config file error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+	/// The file is not valid JSON or TOML.
+	Syntax(String),
+	/// `base` does not name a fork.
+	InvalidBase(String),
+	/// A field is not a field of `Config`.
+	UnknownField(String),
+	/// A field is missing or has a value of the wrong type.
+	InvalidField(String),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Syntax(e) => write!(f, "invalid config file: {}", e),
+			Self::InvalidBase(b) => write!(f, "invalid base fork: {}", b),
+			Self::UnknownField(name) => write!(f, "unknown config field: {}", name),
+			Self::InvalidField(e) => write!(f, "invalid config field: {}", e),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+/// Parse a JSON config file.
+pub fn from_json(s: &str) -> Result<Config, Error> {
+	match serde_json::from_str(s).map_err(|e| Error::Syntax(e.to_string()))? {
+		Value::Object(fields) => from_fields(fields),
+		_ => Err(Error::Syntax("expected an object".into())),
+	}
+}
+
+/// Parse a TOML config file.
+pub fn from_toml(s: &str) -> Result<Config, Error> {
+	match toml::from_str(s).map_err(|e| Error::Syntax(e.to_string()))? {
+		Value::Object(fields) => from_fields(fields),
+		_ => Err(Error::Syntax("expected a table".into())),
+	}
+}
+
+/// Write a config as JSON, with every field.
+pub fn to_json(config: &Config) -> String {
+	serde_json::to_string_pretty(config).expect("config serializes to JSON")
+}
+
+/// Config stored in the chain database, if any.
+pub fn load(backend: &MemoryBackend<'_>) -> Result<Option<Config>, Error> {
+	backend
+		.chain_config()
+		.map(|config| from_json(&config))
+		.transpose()
+}
+
+/// Store a config in the chain database, replacing any previous one.
+pub fn store(backend: &MemoryBackend<'_>, config: &Config) {
+	backend.set_chain_config(&to_json(config));
+}
+
+fn from_fields(mut fields: Map<String, Value>) -> Result<Config, Error> {
+	let base = match fields.remove("base") {
+		Some(base) => {
+			let fork = serde_json::from_value::<Fork>(base.clone())
+				.map_err(|_| Error::InvalidBase(base.to_string()))?;
+			match serde_json::to_value(fork.config()).expect("config serializes to JSON") {
+				Value::Object(base) => base,
+				_ => unreachable!("config serializes to an object"),
+			}
+		}
+		None => Map::new(),
+	};
+
+	let mut config = base.clone();
+	for (name, value) in fields {
+		if !base.is_empty() && !base.contains_key(&name) {
+			return Err(Error::UnknownField(name));
+		}
+		config.insert(name, value);
+	}
+
+	serde_json::from_value(Value::Object(config)).map_err(|e| Error::InvalidField(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_from_toml() {
+		let config = from_toml(
+			r#"
+			base = "Istanbul"
+			gas_sload = 1000
+			create_contract_limit = 49152
+			"#,
+		)
+		.unwrap();
+		assert_eq!(config.gas_sload, 1000);
+		assert_eq!(config.create_contract_limit, Some(49152));
+		assert_eq!(config.gas_sstore_set, Config::istanbul().gas_sstore_set);
+		assert!(config.has_chain_id);
+
+		assert_eq!(
+			from_toml("base = \"Istanbul\"\ngas_sloads = 1000").err(),
+			Some(Error::UnknownField("gas_sloads".into()))
+		);
+		assert!(matches!(
+			from_toml("base = \"Atlantis\""),
+			Err(Error::InvalidBase(_))
+		));
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let config = from_json(&to_json(&Config::london())).unwrap();
+		assert_eq!(to_json(&config), to_json(&Config::london()));
+
+		let db_path = std::env::temp_dir().join(format!("config-{}.sqlite", std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = crate::backend::sql::MemoryVicinity {
+			gas_price: Default::default(),
+			origin: Default::default(),
+			chain_id: Default::default(),
+			block_hashes: Default::default(),
+			block_number: Default::default(),
+			block_coinbase: Default::default(),
+			block_timestamp: Default::default(),
+			block_difficulty: Default::default(),
+			block_prev_randao: Default::default(),
+			block_gas_limit: Default::default(),
+			block_base_fee_per_gas: Default::default(),
+			block_blob_base_fee: Default::default(),
+			blob_hashes: Default::default(),
+		};
+		let backend = MemoryBackend::new(
+			&vicinity,
+			Default::default(),
+			db_path.to_str().unwrap().into(),
+			false,
+		);
+		assert!(matches!(load(&backend), Ok(None)));
+		store(&backend, &config);
+		let loaded = load(&backend).unwrap().unwrap();
+		assert_eq!(to_json(&loaded), to_json(&Config::london()));
+		let _ = std::fs::remove_file(&db_path);
+	}
+}
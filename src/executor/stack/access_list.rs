@@ -0,0 +1,189 @@
+//! EIP-2930 access list generation, as done by `eth_createAccessList`.
+
+use super::{MemoryStackState, PrecompileSet, StackExecutor, StackState, StackSubstateMetadata};
+use crate::backend::Backend;
+use crate::executor::Executor;
+use crate::{Config, CreateScheme, ExitReason};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+
+/// Number of runs after which a list that keeps changing is returned as is.
+const MAX_ITERATIONS: usize = 16;
+
+/// An access list, as accepted by the `transact_*` functions.
+pub type AccessList = Vec<(H160, Vec<H256>)>;
+
+/// Access list of a transaction, with its effect on gas.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccessListResult {
+	/// Addresses and storage keys touched by the transaction, excluding the
+	/// sender, the recipient and precompiles, which are warm regardless.
+	pub access_list: AccessList,
+	/// Gas used by the transaction with the access list.
+	pub gas_used: u64,
+	/// Gas used by the transaction without an access list.
+	pub gas_used_without_access_list: u64,
+	/// Exit reason of the transaction with the access list.
+	pub exit_reason: ExitReason,
+}
+
+/// Generate the access list of a transaction against `backend`, which is left
+/// untouched. Creates a contract if `address` is `None`.
+///
+/// Touching a new address or slot can change the execution path, so the
+/// transaction is run with the list from the previous run until the list no
+/// longer changes.
+#[allow(clippy::too_many_arguments)]
+pub fn create_access_list<B: Backend, P: PrecompileSet>(
+	backend: &B,
+	config: &Config,
+	precompiles: &P,
+	caller: H160,
+	address: Option<H160>,
+	value: U256,
+	data: Vec<u8>,
+	gas_limit: u64,
+) -> AccessListResult {
+	let run = |access_list: AccessList| {
+		let metadata = StackSubstateMetadata::new(gas_limit, config);
+		let state = MemoryStackState::new(metadata, backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, config, precompiles);
+
+		let (reason, address) = match address {
+			Some(address) => {
+				let (reason, _) = executor.transact_call(
+					caller,
+					address,
+					value,
+					data.clone(),
+					gas_limit,
+					access_list,
+				);
+				(reason, address)
+			}
+			None => {
+				let address = executor.create_address(CreateScheme::Legacy { caller });
+				let (reason, _) =
+					executor.transact_create(caller, value, data.clone(), gas_limit, access_list);
+				(reason, address)
+			}
+		};
+
+		let mut touched = BTreeMap::<H160, Vec<H256>>::new();
+		if let Some(accessed) = executor.state().metadata().accessed() {
+			for touched_address in &accessed.accessed_addresses {
+				touched.entry(*touched_address).or_default();
+			}
+			for (touched_address, key) in &accessed.accessed_storage {
+				touched.entry(*touched_address).or_default().push(*key);
+			}
+		}
+		touched.remove(&caller);
+		touched.remove(&address);
+		// EIP-3651
+		if config.warm_coinbase_address {
+			touched.remove(&executor.state().block_coinbase());
+		}
+		touched.retain(|address, _| !precompiles.is_precompile(*address));
+
+		(
+			reason,
+			executor.used_gas(),
+			touched.into_iter().collect::<AccessList>(),
+		)
+	};
+
+	let (mut exit_reason, gas_used_without_access_list, mut access_list) = run(Vec::new());
+	let mut gas_used = gas_used_without_access_list;
+	for _ in 0..MAX_ITERATIONS {
+		let (reason, used_gas, next) = run(access_list.clone());
+		exit_reason = reason;
+		gas_used = used_gas;
+		if next == access_list {
+			break;
+		}
+		access_list = next;
+	}
+
+	AccessListResult {
+		access_list,
+		gas_used,
+		gas_used_without_access_list,
+		exit_reason,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+
+	#[test]
+	fn test_create_access_list() {
+		let config = Config::berlin();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let caller = H160::from_low_u64_be(1);
+		let proxy = H160::from_low_u64_be(2);
+		let token = H160::from_low_u64_be(3);
+
+		let mut state = BTreeMap::new();
+		// Calls `token` and returns.
+		state.insert(
+			proxy,
+			MemoryAccount {
+				code: hex::decode("6000808080807300000000000000000000000000000000000000035af100")
+					.unwrap(),
+				..Default::default()
+			},
+		);
+		// Loads slots 1 and 2.
+		state.insert(
+			token,
+			MemoryAccount {
+				code: hex::decode("600154506002545000").unwrap(),
+				..Default::default()
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+
+		let result = create_access_list(
+			&backend,
+			&config,
+			&(),
+			caller,
+			Some(proxy),
+			U256::zero(),
+			Vec::new(),
+			1_000_000,
+		);
+		assert!(result.exit_reason.is_succeed());
+		assert_eq!(
+			result.access_list,
+			vec![(
+				token,
+				vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)]
+			)]
+		);
+		// Warming `token` and its slots ahead of time costs 2400 + 2 * 1900
+		// and saves 2500 + 2 * 2000.
+		assert_eq!(
+			result.gas_used_without_access_list - result.gas_used,
+			2500 + 2 * 2000 - 2400 - 2 * 1900
+		);
+	}
+}
@@ -0,0 +1,179 @@
+//! Code analysis shared across call frames and transactions.
+
+use crate::{Config, Eof, EofError, Valids};
+use alloc::{collections::BTreeMap, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+use primitive_types::H256;
+
+/// How code runs, analysed beforehand.
+#[derive(Clone, Debug)]
+pub enum Analysis {
+	/// Legacy code, with its jump destinations.
+	Legacy(Rc<Valids>),
+	/// EOF code, with its validated container or the reason it is invalid.
+	Eof(Result<Eof, EofError>),
+}
+
+/// Code with its analysis.
+#[derive(Clone, Debug)]
+pub struct AnalysedCode {
+	/// Code.
+	pub code: Rc<Vec<u8>>,
+	/// Analysis of the code.
+	pub analysis: Analysis,
+}
+
+/// Code with the analyses made of it so far. Whether code starting with the
+/// EOF magic runs as EOF depends on the config, so either may be needed.
+#[derive(Debug)]
+struct Entry {
+	code: Rc<Vec<u8>>,
+	valids: Option<Rc<Valids>>,
+	eof: Option<Result<Eof, EofError>>,
+}
+
+/// Analysed code by code hash, so that contracts called repeatedly are loaded
+/// and analysed once. A cache can be shared by executors running successive
+/// transactions. Entries are never evicted, call [`CodeCache::clear`] to bound
+/// its size.
+#[derive(Debug, Default)]
+pub struct CodeCache {
+	entries: RefCell<BTreeMap<H256, Entry>>,
+}
+
+impl CodeCache {
+	/// Create an empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Code with hash `code_hash` analysed to run with `config`, loading it
+	/// with `code` if it is not cached.
+	pub fn get_or_analyse<F: FnOnce() -> Vec<u8>>(
+		&self,
+		code_hash: H256,
+		config: &Config,
+		code: F,
+	) -> AnalysedCode {
+		let mut entries = self.entries.borrow_mut();
+		let entry = entries.entry(code_hash).or_insert_with(|| Entry {
+			code: Rc::new(code()),
+			valids: None,
+			eof: None,
+		});
+
+		let code = &entry.code;
+		let analysis = if config.has_eof && Eof::is_eof(code) {
+			Analysis::Eof(entry.eof.get_or_insert_with(|| Eof::new(code)).clone())
+		} else {
+			Analysis::Legacy(
+				entry
+					.valids
+					.get_or_insert_with(|| Rc::new(Valids::new(code)))
+					.clone(),
+			)
+		};
+
+		AnalysedCode {
+			code: code.clone(),
+			analysis,
+		}
+	}
+
+	/// Number of cached codes.
+	pub fn len(&self) -> usize {
+		self.entries.borrow().len()
+	}
+
+	/// Whether the cache is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Remove all cached codes.
+	pub fn clear(&self) {
+		self.entries.borrow_mut().clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+	use crate::executor::Executor;
+	use crate::{Config, ExitReason, ExitSucceed};
+	use primitive_types::{H160, U256};
+
+	#[test]
+	fn test_shared_code_cache() {
+		let config = Config::istanbul();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let caller = H160::from_low_u64_be(1);
+		let contract = H160::from_low_u64_be(2);
+		let mut state = BTreeMap::new();
+		// Jumps over INVALID and returns 42.
+		state.insert(
+			contract,
+			MemoryAccount {
+				code: hex::decode("600456fe5b602a60005260206000f3").unwrap(),
+				..Default::default()
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+
+		let cache = Rc::new(CodeCache::new());
+		for _ in 0..2 {
+			let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+			let state = MemoryStackState::new(metadata, &backend);
+			let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+			executor.set_code_cache(cache.clone());
+
+			let (reason, output) = executor.transact_call(
+				caller,
+				contract,
+				U256::zero(),
+				Vec::new(),
+				100_000,
+				Vec::new(),
+			);
+			assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+			assert_eq!(U256::from_big_endian(&output), U256::from(42));
+			assert_eq!(cache.len(), 1);
+		}
+	}
+
+	#[test]
+	fn test_eof_analysis() {
+		let cache = CodeCache::new();
+		let hash = H256::repeat_byte(1);
+		// A container with a single code section holding STOP.
+		let container = hex::decode("ef00010100040200010001030000000000000000").unwrap();
+
+		let legacy = cache.get_or_analyse(hash, &Config::cancun(), || container.clone());
+		assert!(matches!(legacy.analysis, Analysis::Legacy(_)));
+
+		let config = Config {
+			has_eof: true,
+			..Config::cancun()
+		};
+		let eof = cache.get_or_analyse(hash, &config, || unreachable!());
+		assert!(matches!(eof.analysis, Analysis::Eof(Ok(_))));
+		assert!(Rc::ptr_eq(&legacy.code, &eof.code));
+		assert_eq!(cache.len(), 1);
+	}
+}
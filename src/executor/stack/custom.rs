@@ -0,0 +1,145 @@
+//! Custom opcodes, registered on the executor by embedders.
+
+use crate::{ExitError, Machine};
+use alloc::boxed::Box;
+
+/// Implementation of a custom opcode. It pops its inputs from and pushes its
+/// outputs to the machine stack, and may read and write the executor state.
+pub type CustomOpcodeFn<S> = Box<dyn Fn(&mut Machine, &mut S) -> Result<(), ExitError>>;
+
+/// Gas cost of a custom opcode, computed from the machine, whose stack holds
+/// the inputs, and the executor state.
+pub type CustomOpcodeCostFn<S> = Box<dyn Fn(&Machine, &S) -> Result<u64, ExitError>>;
+
+/// Gas cost of a custom opcode.
+pub enum CustomOpcodeCost<S> {
+	/// Fixed cost.
+	Static(u64),
+	/// Cost computed before each execution.
+	Dynamic(CustomOpcodeCostFn<S>),
+}
+
+/// An opcode the EVM leaves undefined, implemented by the embedder.
+///
+/// The cost is charged before execution like that of any other opcode, and
+/// the stack is checked to hold `inputs` items and to have room for
+/// `outputs` more once they are popped.
+pub struct CustomOpcode<S> {
+	/// Gas cost.
+	pub cost: CustomOpcodeCost<S>,
+	/// Number of stack items popped.
+	pub inputs: usize,
+	/// Number of stack items pushed.
+	pub outputs: usize,
+	/// Implementation.
+	pub execute: CustomOpcodeFn<S>,
+}
+
+impl<S> CustomOpcode<S> {
+	pub(crate) fn cost(&self, machine: &Machine, state: &S) -> Result<u64, ExitError> {
+		match &self.cost {
+			CustomOpcodeCost::Static(cost) => Ok(*cost),
+			CustomOpcodeCost::Dynamic(cost) => cost(machine, state),
+		}
+	}
+
+	pub(crate) fn execute(&self, machine: &mut Machine, state: &mut S) -> Result<(), ExitError> {
+		let stack = machine.stack();
+		if stack.len() < self.inputs {
+			return Err(ExitError::StackUnderflow);
+		}
+		if stack.len() - self.inputs + self.outputs > stack.limit() {
+			return Err(ExitError::StackOverflow);
+		}
+
+		(self.execute)(machine, state)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+	use crate::executor::Executor;
+	use crate::{Config, ExitReason, ExitSucceed, Opcode};
+	use alloc::collections::BTreeMap;
+	use alloc::vec::Vec;
+	use primitive_types::{H160, H256, U256};
+
+	#[test]
+	fn test_custom_opcode() {
+		let config = Config::istanbul();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let caller = H160::from_low_u64_be(1);
+		let contract = H160::from_low_u64_be(2);
+		let mut state = BTreeMap::new();
+		state.insert(
+			caller,
+			MemoryAccount {
+				balance: U256::from(1000),
+				..Default::default()
+			},
+		);
+		// Returns the balance of address 1, read by 0x0c.
+		state.insert(
+			contract,
+			MemoryAccount {
+				code: hex::decode("60010c60005260206000f3").unwrap(),
+				..Default::default()
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+		let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+
+		let (reason, _) = executor.transact_call(
+			caller,
+			contract,
+			U256::zero(),
+			Vec::new(),
+			100_000,
+			Vec::new(),
+		);
+		assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+
+		executor.register_opcode(
+			Opcode(0x0c),
+			CustomOpcode {
+				cost: CustomOpcodeCost::Static(100),
+				inputs: 1,
+				outputs: 1,
+				execute: Box::new(|machine, state: &mut MemoryStackState<_>| {
+					let address = H256::from(<[u8; 32]>::from(machine.stack_mut().pop()?));
+					let balance = state.basic(address.into()).balance;
+					machine.stack_mut().push(balance)
+				}),
+			},
+		);
+		let (reason, output) = executor.transact_call(
+			caller,
+			contract,
+			U256::zero(),
+			Vec::new(),
+			100_000,
+			Vec::new(),
+		);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+		assert_eq!(U256::from_big_endian(&output), U256::from(1000));
+	}
+}
@@ -1,20 +1,25 @@
 use crate::backend::Backend;
-use crate::gasometer::{self, Gasometer, StorageTarget};
+use crate::gasometer::{self, Gasometer, InstructionTable, StorageTarget};
 use crate::{
-	Capture, Config, Context, CreateScheme, ExitError, ExitReason, ExitSucceed, Handler, Opcode,
-	Runtime, Transfer,
+	Capture, Config, Context, CreateScheme, Eof, ExitError, ExitFatal, ExitReason, Handler,
+	Opcode, Runtime, Transfer,
 };
 use crate::executor::{
 	Executor
 };
+use super::code_cache::{Analysis, CodeCache};
+use super::custom::CustomOpcode;
+use super::fee::DynamicFee;
+use super::precompile::{
+	PrecompileFailure, PrecompileHandle, PrecompileOutput, PrecompileSet,
+};
 use alloc::{
 	collections::{BTreeMap, BTreeSet},
 	rc::Rc,
 	vec::Vec,
 };
 use core::{cmp::min, convert::Infallible};
-use ethereum::Log;
-use evm_core::{ExitFatal, ExitRevert, InterpreterHandler, Machine, Trap};
+use evm_core::{InterpreterHandler, Machine, Trap};
 use primitive_types::{H160, H256, U256};
 use sha3::{Digest, Keccak256};
 
@@ -194,122 +199,48 @@ pub trait StackState<'config>: Backend {
 	fn exit_commit(&mut self) -> Result<(), ExitError>;
 	fn exit_revert(&mut self) -> Result<(), ExitError>;
 	fn exit_discard(&mut self) -> Result<(), ExitError>;
+	/// Forget the transient storage and the created accounts of the previous
+	/// transaction, before executing the next one.
+	fn clear_transaction(&mut self);
 
 	fn is_empty(&self, address: H160) -> bool;
 	fn deleted(&self, address: H160) -> bool;
+	fn created(&self, address: H160) -> bool;
+	fn transient_storage(&self, address: H160, key: H256) -> H256;
 	fn is_cold(&self, address: H160) -> bool;
 	fn is_storage_cold(&self, address: H160, key: H256) -> bool;
 
 	fn inc_nonce(&mut self, address: H160);
 	fn set_storage(&mut self, address: H160, key: H256, value: H256);
+	fn set_transient_storage(&mut self, address: H160, key: H256, value: H256);
 	fn reset_storage(&mut self, address: H160);
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>);
 	fn set_deleted(&mut self, address: H160);
+	fn set_created(&mut self, address: H160);
 	fn set_code(&mut self, address: H160, code: Vec<u8>);
 	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError>;
+	fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError>;
+	fn deposit(&mut self, address: H160, value: U256);
 	fn reset_balance(&mut self, address: H160);
 	fn touch(&mut self, address: H160);
 }
 
-/// Data returned by a precompile on success.
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct PrecompileOutput {
-	pub exit_status: ExitSucceed,
-	pub cost: u64,
-	pub output: Vec<u8>,
-	pub logs: Vec<Log>,
-}
-
-/// Data returned by a precompile in case of failure.
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub enum PrecompileFailure {
-	/// Reverts the state changes and consume all the gas.
-	Error { exit_status: ExitError },
-	/// Reverts the state changes and consume the provided `cost`.
-	/// Returns the provided error message.
-	Revert {
-		exit_status: ExitRevert,
-		output: Vec<u8>,
-		cost: u64,
-	},
-	/// Mark this failure as fatal, and all EVM execution stacks must be exited.
-	Fatal { exit_status: ExitFatal },
-}
-
-/// A precompile result.
-pub type PrecompileResult = Result<PrecompileOutput, PrecompileFailure>;
-
-/// A set of precompiles.
-/// Checks of the provided address being in the precompile set should be
-/// as cheap as possible since it may be called often.
-pub trait PrecompileSet {
-	/// Tries to execute a precompile in the precompile set.
-	/// If the provided address is not a precompile, returns None.
-	fn execute(
-		&self,
-		address: H160,
-		input: &[u8],
-		gas_limit: Option<u64>,
-		context: &Context,
-		is_static: bool,
-	) -> Option<PrecompileResult>;
-
-	/// Check if the given address is a precompile. Should only be called to
-	/// perform the check while not executing the precompile afterward, since
-	/// `execute` already performs a check internally.
-	fn is_precompile(&self, address: H160) -> bool;
-}
-
-impl PrecompileSet for () {
-	fn execute(
-		&self,
-		_: H160,
-		_: &[u8],
-		_: Option<u64>,
-		_: &Context,
-		_: bool,
-	) -> Option<PrecompileResult> {
-		None
-	}
-
-	fn is_precompile(&self, _: H160) -> bool {
-		false
-	}
-}
-
-/// Precompiles function signature. Expected input arguments are:
-///  * Input
-///  * Gas limit
-///  * Context
-///  * Is static
-pub type PrecompileFn = fn(&[u8], Option<u64>, &Context, bool) -> PrecompileResult;
-
-impl PrecompileSet for BTreeMap<H160, PrecompileFn> {
-	fn execute(
-		&self,
-		address: H160,
-		input: &[u8],
-		gas_limit: Option<u64>,
-		context: &Context,
-		is_static: bool,
-	) -> Option<PrecompileResult> {
-		self.get(&address)
-			.map(|precompile| (*precompile)(input, gas_limit, context, is_static))
-	}
-
-	/// Check if the given address is a precompile. Should only be called to
-	/// perform the check while not executing the precompile afterward, since
-	/// `execute` already performs a check internally.
-	fn is_precompile(&self, address: H160) -> bool {
-		self.contains_key(&address)
-	}
-}
-
 /// Stack-based executor.
 pub struct StackExecutor<'config, 'precompiles, S, P> {
 	config: &'config Config,
 	state: S,
 	precompile_set: &'precompiles P,
+	/// Recipient of the base fee of dynamic fee transactions, which is burned
+	/// otherwise.
+	base_fee_treasury: Option<H160>,
+	/// Effective gas price of the dynamic fee transaction being executed.
+	gas_price: Option<U256>,
+	/// Custom opcodes, by opcode byte.
+	custom_opcodes: BTreeMap<u8, CustomOpcode<S>>,
+	/// Opcodes enabled by the config, with their static costs.
+	instructions: InstructionTable,
+	/// Code analysed by previous calls.
+	code_cache: Rc<CodeCache>,
 }
 
 impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> 
@@ -337,6 +268,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			data: &data,
 			gas_limit,
 		});
+		self.state.clear_transaction();
 
 		let transaction_cost = gasometer::call_transaction_cost(&data, &access_list);
 		let gasometer = &mut self.state.metadata_mut().gasometer;
@@ -396,6 +328,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			gas_limit,
 			address: self.create_address(CreateScheme::Legacy { caller }),
 		});
+		self.state.clear_transaction();
 
 		if let Err(e) = self.record_create_transaction_cost(&init_code, &access_list) {
 			return emit_exit!(e.into(), Vec::new());
@@ -410,10 +343,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			Some(gas_limit),
 			false,
 		) {
-			Capture::Exit((s, address, v)) => {
-				println!("contract created {:?}", address);
-				emit_exit!(s, v)
-			},
+			Capture::Exit((s, _, v)) => emit_exit!(s, v),
 			Capture::Trap(_) => unreachable!(),
 		}
 	}
@@ -442,9 +372,56 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			config,
 			state,
 			precompile_set,
+			base_fee_treasury: None,
+			gas_price: None,
+			custom_opcodes: BTreeMap::new(),
+			instructions: InstructionTable::new(config),
+			code_cache: Rc::new(CodeCache::new()),
 		}
 	}
 
+	/// Return a reference to the code cache.
+	pub fn code_cache(&self) -> &Rc<CodeCache> {
+		&self.code_cache
+	}
+
+	/// Replace the code cache, for example with one shared by the executors
+	/// of successive transactions.
+	pub fn set_code_cache(&mut self, code_cache: Rc<CodeCache>) {
+		self.code_cache = code_cache;
+	}
+
+	/// Return a reference to the instruction table, built from the config.
+	pub fn instructions(&self) -> &InstructionTable {
+		&self.instructions
+	}
+
+	/// Replace the instruction table, to enable or reprice opcodes
+	/// independently of the config.
+	pub fn set_instructions(&mut self, instructions: InstructionTable) {
+		self.instructions = instructions;
+	}
+
+	/// Send the base fee of dynamic fee transactions to `treasury` instead of
+	/// burning it.
+	pub fn set_base_fee_treasury(&mut self, treasury: Option<H160>) {
+		self.base_fee_treasury = treasury;
+	}
+
+	/// Register a custom opcode, replacing any previous one at `opcode`.
+	///
+	/// # Panics
+	///
+	/// Panics if `opcode` is defined by the EVM.
+	pub fn register_opcode(&mut self, opcode: Opcode, custom: CustomOpcode<S>) {
+		assert!(
+			!opcode.is_defined(),
+			"opcode {:?} is defined by the EVM",
+			opcode
+		);
+		self.custom_opcodes.insert(opcode.as_u8(), custom);
+	}
+
 	pub fn state(&self) -> &S {
 		&self.state
 	}
@@ -489,6 +466,13 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 		init_code: &[u8],
 		access_list: &[(H160, Vec<H256>)],
 	) -> Result<(), ExitError> {
+		if let Some(limit) = self.config.max_initcode_size {
+			if init_code.len() > limit {
+				self.state.metadata_mut().gasometer.fail();
+				return Err(ExitError::CreateContractLimit);
+			}
+		}
+
 		let transaction_cost = gasometer::create_transaction_cost(init_code, access_list);
 		let gasometer = &mut self.state.metadata_mut().gasometer;
 		gasometer.record_transaction(transaction_cost)
@@ -517,6 +501,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 				salt,
 			}),
 		});
+		self.state.clear_transaction();
 
 		if let Err(e) = self.record_create_transaction_cost(&init_code, &access_list) {
 			return emit_exit!(e.into(), Vec::new());
@@ -540,6 +525,122 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 		}
 	}
 
+	/// Execute a `CALL` transaction that pays for gas as per EIP-1559. The
+	/// caller buys `gas_limit` gas at the effective gas price and is refunded
+	/// for unused gas. Of the used gas, the base fee is burned or sent to the
+	/// treasury, and only the tip is paid to the coinbase.
+	#[allow(clippy::too_many_arguments)]
+	pub fn transact_call_with_fee(
+		&mut self,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+		fee: DynamicFee,
+		access_list: Vec<(H160, Vec<H256>)>,
+	) -> (ExitReason, Vec<u8>) {
+		let cost = gasometer::call_transaction_cost(&data, &access_list);
+		self.transact_with_fee(caller, value, gas_limit, fee, cost, |executor| {
+			executor.transact_call(caller, address, value, data, gas_limit, access_list)
+		})
+	}
+
+	/// Execute a `CREATE` transaction that pays for gas as per EIP-1559, like
+	/// [`Self::transact_call_with_fee`].
+	pub fn transact_create_with_fee(
+		&mut self,
+		caller: H160,
+		value: U256,
+		init_code: Vec<u8>,
+		gas_limit: u64,
+		fee: DynamicFee,
+		access_list: Vec<(H160, Vec<H256>)>,
+	) -> (ExitReason, Vec<u8>) {
+		let cost = gasometer::create_transaction_cost(&init_code, &access_list);
+		self.transact_with_fee(caller, value, gas_limit, fee, cost, |executor| {
+			executor.transact_create(caller, value, init_code, gas_limit, access_list)
+		})
+	}
+
+	fn transact_with_fee(
+		&mut self,
+		caller: H160,
+		value: U256,
+		gas_limit: u64,
+		fee: DynamicFee,
+		cost: gasometer::TransactionCost,
+		transact: impl FnOnce(&mut Self) -> (ExitReason, Vec<u8>),
+	) -> (ExitReason, Vec<u8>) {
+		if !self.config.has_base_fee {
+			return (
+				ExitError::Other("dynamic fee transaction before London".into()).into(),
+				Vec::new(),
+			);
+		}
+
+		if let Err(e) = Gasometer::new(gas_limit, self.config).record_transaction(cost) {
+			return (e.into(), Vec::new());
+		}
+
+		let base_fee = self.state.block_base_fee_per_gas();
+		let gas_price = match fee.effective_gas_price(base_fee) {
+			Ok(gas_price) => gas_price,
+			Err(e) => return (e.into(), Vec::new()),
+		};
+
+		// The caller must afford the fee cap, even if it pays less.
+		let max_cost = U256::from(gas_limit)
+			.checked_mul(fee.max_fee_per_gas)
+			.and_then(|cost| cost.checked_add(value));
+		if !matches!(max_cost, Some(cost) if cost <= self.state.basic(caller).balance) {
+			return (ExitError::OutOfFund.into(), Vec::new());
+		}
+
+		let prepaid = U256::from(gas_limit) * gas_price;
+		if let Err(e) = self.state.withdraw(caller, prepaid) {
+			return (e.into(), Vec::new());
+		}
+
+		// The gasometer is shared by all transactions of the executor, so only
+		// the gas used since this one started is paid for.
+		let gasometer = &self.state.metadata().gasometer;
+		let (used_before, refunded_before) = (gasometer.total_used_gas(), gasometer.refunded_gas());
+
+		self.gas_price = Some(gas_price);
+		let (reason, output) = transact(self);
+		self.gas_price = None;
+
+		let gasometer = &self.state.metadata().gasometer;
+		let used = gasometer.total_used_gas().saturating_sub(used_before);
+		let refunded = gasometer.refunded_gas().saturating_sub(refunded_before).max(0) as u64;
+		let used_gas = min(
+			used - min(used / self.config.max_refund_quotient, refunded),
+			gas_limit,
+		);
+
+		let used_gas = U256::from(used_gas);
+		let refund = used_gas
+			.checked_mul(gas_price)
+			.and_then(|cost| prepaid.checked_sub(cost));
+		let tip = gas_price
+			.checked_sub(base_fee)
+			.and_then(|tip| tip.checked_mul(used_gas));
+		let burned = used_gas.checked_mul(base_fee);
+		let (refund, tip, burned) = match (refund, tip, burned) {
+			(Some(refund), Some(tip), Some(burned)) => (refund, tip, burned),
+			_ => return (ExitFatal::Other("fee overflow".into()).into(), Vec::new()),
+		};
+		self.state.deposit(caller, refund);
+		let coinbase = self.state.block_coinbase();
+		self.state.deposit(coinbase, tip);
+		if let Some(treasury) = self.base_fee_treasury {
+			self.state.deposit(treasury, burned);
+		}
+
+		(reason, output)
+	}
+
 	/// Get used gas for the current executor, given the price.
 	pub fn used_gas(&self) -> u64 {
 		self.state.metadata().gasometer.total_used_gas()
@@ -587,6 +688,14 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 	}
 
 	pub fn initialize_with_access_list(&mut self, access_list: Vec<(H160, Vec<H256>)>) {
+		// EIP-3651
+		if self.config.warm_coinbase_address {
+			let coinbase = self.state.block_coinbase();
+			self.state
+				.metadata_mut()
+				.access_addresses(core::iter::once(coinbase));
+		}
+
 		let addresses = access_list.iter().map(|a| a.0);
 		self.state.metadata_mut().access_addresses(addresses);
 
@@ -685,6 +794,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			}
 
 			self.state.reset_storage(address);
+			self.state.set_created(address);
 		}
 
 		let context = Context {
@@ -723,8 +833,14 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			ExitReason::Succeed(s) => {
 				let out = runtime.machine().return_value();
 
-				// As of EIP-3541 code starting with 0xef cannot be deployed
-				if let Err(e) = check_first_byte(self.config, &out) {
+				// As of EIP-3541 code starting with 0xef cannot be deployed,
+				// except that EOF init code must deploy a valid container.
+				let checked = if runtime.machine().eof().is_some() {
+					Eof::new(&out).map(|_| ()).map_err(|_| ExitError::InvalidEof)
+				} else {
+					check_first_byte(self.config, &out)
+				};
+				if let Err(e) = checked {
 					self.state.metadata_mut().gasometer.fail();
 					let _ = self.exit_substate(StackExitKind::Failed);
 					return Capture::Exit((e.into(), None, Vec::new()));
@@ -839,8 +955,6 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			}
 		}
 
-		let code = self.code(code_address);
-
 		self.enter_substate(gas_limit, is_static);
 		self.state.touch(context.address);
 
@@ -861,32 +975,20 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			}
 		}
 
-		if let Some(result) =
-			self.precompile_set
-				.execute(code_address, &input, Some(gas_limit), &context, is_static)
-		{
+		let precompile_set = self.precompile_set;
+		if let Some(result) = precompile_set.execute(&mut StackExecutorHandle {
+			executor: self,
+			code_address,
+			input: &input,
+			gas_limit: Some(gas_limit),
+			context: &context,
+			is_static,
+		}) {
 			return match result {
 				Ok(PrecompileOutput {
 					exit_status,
 					output,
-					cost,
-					logs,
 				}) => {
-					for Log {
-						address,
-						topics,
-						data,
-					} in logs
-					{
-						match self.log(address, topics, data) {
-							Ok(_) => continue,
-							Err(error) => {
-								return Capture::Exit((ExitReason::Error(error), output));
-							}
-						}
-					}
-
-					let _ = self.state.metadata_mut().gasometer.record_cost(cost);
 					let _ = self.exit_substate(StackExitKind::Succeeded);
 					Capture::Exit((ExitReason::Succeed(exit_status), output))
 				}
@@ -897,9 +999,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 				Err(PrecompileFailure::Revert {
 					exit_status,
 					output,
-					cost,
 				}) => {
-					let _ = self.state.metadata_mut().gasometer.record_cost(cost);
 					let _ = self.exit_substate(StackExitKind::Reverted);
 					Capture::Exit((ExitReason::Revert(exit_status), output))
 				}
@@ -911,7 +1011,22 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
 			};
 		}
 
-		let mut runtime = Runtime::new(Rc::new(code), Rc::new(input), context, self.config);
+		let code_hash = self.state.code_hash(code_address);
+		let code = self
+			.code_cache
+			.get_or_analyse(code_hash, self.config, || self.state.code(code_address));
+		let mut runtime = match code.analysis {
+			Analysis::Legacy(valids) => Runtime::new_with_valids(
+				code.code,
+				valids,
+				Rc::new(input),
+				context,
+				self.config,
+			),
+			Analysis::Eof(eof) => {
+				Runtime::new_with_eof(code.code, eof, Rc::new(input), context, self.config)
+			}
+		};
 
 		let reason = self.execute(&mut runtime);
 		log::debug!(target: "evm", "Call execution using address {}: {:?}", code_address, reason);
@@ -969,11 +1084,17 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Interprete
 			});
 		}
 
-		if let Some(cost) = gasometer::static_opcode_cost(opcode) {
-			self.state
-				.metadata_mut()
-				.gasometer
-				.record_cost(cost as u64)?;
+		let instruction = self.instructions.get(opcode);
+		if !instruction.enabled {
+			match self.custom_opcodes.get(&opcode.as_u8()) {
+				Some(custom) => {
+					let cost = custom.cost(machine, &self.state)?;
+					self.state.metadata_mut().gasometer.record_cost(cost)?;
+				}
+				None => return Err(self.state.metadata_mut().gasometer.fail()),
+			}
+		} else if let Some(cost) = instruction.static_cost {
+			self.state.metadata_mut().gasometer.record_cost(cost)?;
 		} else {
 			let is_static = self.state.metadata().is_static;
 			let (gas_cost, target, memory_cost) = gasometer::dynamic_opcode_cost(
@@ -1042,7 +1163,7 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 			return H256::default();
 		}
 
-		H256::from_slice(Keccak256::digest(&self.state.code(address)).as_slice())
+		self.state.code_hash(address)
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
@@ -1059,12 +1180,16 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 			.unwrap_or_default()
 	}
 
+	fn transient_storage(&self, address: H160, index: H256) -> H256 {
+		self.state.transient_storage(address, index)
+	}
+
 	fn gas_left(&self) -> U256 {
 		U256::from(self.state.metadata().gasometer.gas())
 	}
 
 	fn gas_price(&self) -> U256 {
-		self.state.gas_price()
+		self.gas_price.unwrap_or_else(|| self.state.gas_price())
 	}
 
 	fn origin(&self) -> H160 {
@@ -1086,12 +1211,24 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 	fn block_difficulty(&self) -> U256 {
 		self.state.block_difficulty()
 	}
+	fn block_prev_randao(&self) -> H256 {
+		self.state.block_prev_randao()
+	}
 	fn block_gas_limit(&self) -> U256 {
 		self.state.block_gas_limit()
 	}
 	fn block_base_fee_per_gas(&self) -> U256 {
 		self.state.block_base_fee_per_gas()
 	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.state.block_blob_base_fee()
+	}
+	fn blob_hash(&self, index: U256) -> H256 {
+		if index > U256::from(usize::MAX) {
+			return H256::default();
+		}
+		self.state.blob_hash(index.as_usize()).unwrap_or_default()
+	}
 	fn chain_id(&self) -> U256 {
 		self.state.chain_id()
 	}
@@ -1119,6 +1256,16 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 		Ok(())
 	}
 
+	fn set_transient_storage(
+		&mut self,
+		address: H160,
+		index: H256,
+		value: H256,
+	) -> Result<(), ExitError> {
+		self.state.set_transient_storage(address, index, value);
+		Ok(())
+	}
+
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
 		self.state.log(address, topics, data);
 		Ok(())
@@ -1138,6 +1285,13 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 			target,
 			value: balance,
 		})?;
+
+		// EIP-6780: contracts that existed before the transaction only send
+		// their balance, to themselves included.
+		if self.config.suicide_only_in_same_tx && !self.state.created(address) {
+			return Ok(());
+		}
+
 		self.state.reset_balance(address);
 		self.state.set_deleted(address);
 
@@ -1223,4 +1377,149 @@ impl<'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> Handler
 
 		capture
 	}
+
+	fn other(&mut self, opcode: Opcode, machine: &mut Machine) -> Result<(), ExitError> {
+		match self.custom_opcodes.get(&opcode.as_u8()) {
+			Some(custom) => custom.execute(machine, &mut self.state),
+			None => Err(ExitError::OutOfGas),
+		}
+	}
+}
+
+/// Handle given to precompiles called by a [`StackExecutor`].
+struct StackExecutorHandle<'inner, 'config, 'precompiles, S, P> {
+	executor: &'inner mut StackExecutor<'config, 'precompiles, S, P>,
+	code_address: H160,
+	input: &'inner [u8],
+	gas_limit: Option<u64>,
+	context: &'inner Context,
+	is_static: bool,
+}
+
+impl<'inner, 'config, 'precompiles, S: StackState<'config>, P: PrecompileSet> PrecompileHandle
+	for StackExecutorHandle<'inner, 'config, 'precompiles, S, P>
+{
+	fn call(
+		&mut self,
+		code_address: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		gas_limit: Option<u64>,
+		is_static: bool,
+		context: &Context,
+	) -> (ExitReason, Vec<u8>) {
+		let value = transfer.as_ref().map(|t| t.value).unwrap_or_default();
+		if value != U256::zero() {
+			if let Err(error) = self.check_not_static() {
+				return (ExitReason::Error(error), Vec::new());
+			}
+		}
+
+		// A `CALL` opcode would have recorded its base cost before the call
+		// itself, which also allows the stipend to be returned afterwards.
+		// Without a limit the call is given all gas left after that cost, so
+		// there is no requested amount for forks before EIP-150 to reject.
+		let gas_cost = gasometer::GasCost::Call {
+			value,
+			gas: gas_limit.map(U256::from).unwrap_or_default(),
+			target_is_cold: self.executor.is_cold(code_address, None),
+			target_exists: self.executor.exists(code_address),
+		};
+		if let Err(error) = self
+			.executor
+			.state
+			.metadata_mut()
+			.gasometer
+			.record_dynamic_cost(gas_cost, None)
+		{
+			return (ExitReason::Error(error), Vec::new());
+		}
+		self.executor.state.metadata_mut().access_address(code_address);
+
+		match Handler::call(
+			self.executor,
+			code_address,
+			transfer,
+			input,
+			gas_limit,
+			is_static || self.is_static,
+			context.clone(),
+		) {
+			Capture::Exit((reason, output)) => (reason, output),
+			Capture::Trap(_) => unreachable!("Trap is Infallible"),
+		}
+	}
+
+	fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+		self.executor.state.metadata_mut().gasometer.record_cost(cost)
+	}
+
+	fn remaining_gas(&self) -> u64 {
+		self.executor.gas()
+	}
+
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
+		self.check_not_static()?;
+		Handler::log(self.executor, address, topics, data)
+	}
+
+	fn balance(&self, address: H160) -> U256 {
+		Handler::balance(self.executor, address)
+	}
+
+	fn storage(&mut self, address: H160, index: H256) -> H256 {
+		self.executor
+			.state
+			.metadata_mut()
+			.access_storage(address, index);
+		Handler::storage(self.executor, address, index)
+	}
+
+	fn set_storage(&mut self, address: H160, index: H256, value: H256) -> Result<(), ExitError> {
+		self.check_not_static()?;
+		self.executor
+			.state
+			.metadata_mut()
+			.access_storage(address, index);
+		Handler::set_storage(self.executor, address, index, value)
+	}
+
+	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError> {
+		if transfer.value != U256::zero() {
+			self.check_not_static()?;
+		}
+		self.executor.state.transfer(transfer)
+	}
+
+	fn code_address(&self) -> H160 {
+		self.code_address
+	}
+
+	fn input(&self) -> &[u8] {
+		self.input
+	}
+
+	fn context(&self) -> &Context {
+		self.context
+	}
+
+	fn is_static(&self) -> bool {
+		self.is_static
+	}
+
+	fn gas_limit(&self) -> Option<u64> {
+		self.gas_limit
+	}
+}
+
+impl<'inner, 'config, 'precompiles, S: StackState<'config>, P: PrecompileSet>
+	StackExecutorHandle<'inner, 'config, 'precompiles, S, P>
+{
+	fn check_not_static(&self) -> Result<(), ExitError> {
+		if self.is_static || self.executor.state.metadata().is_static() {
+			Err(ExitError::Other("state change in static call".into()))
+		} else {
+			Ok(())
+		}
+	}
 }
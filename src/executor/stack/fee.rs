@@ -0,0 +1,221 @@
+//! EIP-1559 fee market, and the EIP-4844 one of blob gas.
+
+use crate::ExitError;
+use alloc::borrow::Cow;
+use primitive_types::U256;
+
+/// Base fee of the first block of the fee market.
+pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
+/// Bound on the change of the base fee from one block to the next, as a
+/// fraction of the base fee.
+pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
+/// Ratio of the gas limit of a block to its gas target.
+pub const ELASTICITY_MULTIPLIER: u64 = 2;
+/// Blob gas used by each blob of a transaction.
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+/// Blob gas used per block that keeps the blob base fee unchanged.
+pub const TARGET_BLOB_GAS_PER_BLOCK: u64 = 3 * GAS_PER_BLOB;
+/// Bound on the blob gas used by a block.
+pub const MAX_BLOB_GAS_PER_BLOCK: u64 = 6 * GAS_PER_BLOB;
+const MIN_BLOB_BASE_FEE: u64 = 1;
+const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3_338_477;
+
+/// Fee caps of a dynamic fee transaction. See
+/// [EIP-1559](https://github.com/ethereum/EIPs/blob/master/EIPS/eip-1559.md).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DynamicFee {
+	/// Maximum price paid per unit of gas, base fee included.
+	pub max_fee_per_gas: U256,
+	/// Maximum price paid per unit of gas to the coinbase, on top of the base
+	/// fee.
+	pub max_priority_fee_per_gas: U256,
+}
+
+impl DynamicFee {
+	/// Price paid per unit of gas in a block with `base_fee`. Fails if the
+	/// fee cap is below the base fee or the tip cap above the fee cap.
+	pub fn effective_gas_price(&self, base_fee: U256) -> Result<U256, ExitError> {
+		if self.max_priority_fee_per_gas > self.max_fee_per_gas {
+			return Err(ExitError::Other(Cow::Borrowed(
+				"max priority fee per gas above max fee per gas",
+			)));
+		}
+		if self.max_fee_per_gas < base_fee {
+			return Err(ExitError::Other(Cow::Borrowed(
+				"max fee per gas below base fee",
+			)));
+		}
+
+		Ok(self
+			.max_fee_per_gas
+			.min(base_fee.saturating_add(self.max_priority_fee_per_gas)))
+	}
+}
+
+/// Base fee of the child of a block with `base_fee` that used `gas_used`, for
+/// a `gas_target` of its gas limit over [`ELASTICITY_MULTIPLIER`]. The base
+/// fee moves towards the demand by at most an eighth per block.
+pub fn next_base_fee(base_fee: U256, gas_used: U256, gas_target: U256) -> U256 {
+	if gas_target.is_zero() || gas_used == gas_target {
+		return base_fee;
+	}
+
+	let denominator = U256::from(BASE_FEE_MAX_CHANGE_DENOMINATOR);
+	if gas_used > gas_target {
+		let delta = base_fee * (gas_used - gas_target) / gas_target / denominator;
+		base_fee + delta.max(U256::one())
+	} else {
+		let delta = base_fee * (gas_target - gas_used) / gas_target / denominator;
+		base_fee - delta
+	}
+}
+
+/// Excess blob gas of the child of a block, which accumulates the blob gas
+/// used above [`TARGET_BLOB_GAS_PER_BLOCK`].
+pub fn next_excess_blob_gas(excess_blob_gas: U256, blob_gas_used: U256) -> U256 {
+	(excess_blob_gas + blob_gas_used).saturating_sub(U256::from(TARGET_BLOB_GAS_PER_BLOCK))
+}
+
+/// Price of blob gas in a block with `excess_blob_gas`, which grows
+/// exponentially with the excess.
+pub fn blob_base_fee(excess_blob_gas: U256) -> U256 {
+	let denominator = U256::from(BLOB_BASE_FEE_UPDATE_FRACTION);
+	// Taylor expansion of `MIN_BLOB_BASE_FEE * e ** (excess / denominator)`.
+	let mut output = U256::zero();
+	let mut accumulator = U256::from(MIN_BLOB_BASE_FEE) * denominator;
+	let mut i = U256::one();
+	while !accumulator.is_zero() {
+		output += accumulator;
+		accumulator = accumulator * excess_blob_gas / (denominator * i);
+		i += U256::one();
+	}
+	output / denominator
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+	use crate::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+	use crate::Config;
+	use alloc::collections::BTreeMap;
+	use alloc::vec::Vec;
+	use primitive_types::{H160, H256};
+
+	#[test]
+	fn test_next_base_fee() {
+		let base_fee = U256::from(INITIAL_BASE_FEE);
+		let target = U256::from(15_000_000);
+		assert_eq!(next_base_fee(base_fee, target, target), base_fee);
+		assert_eq!(
+			next_base_fee(base_fee, target * ELASTICITY_MULTIPLIER, target),
+			U256::from(1_125_000_000)
+		);
+		assert_eq!(
+			next_base_fee(base_fee, U256::zero(), target),
+			U256::from(875_000_000)
+		);
+		// The base fee always increases over the target.
+		assert_eq!(
+			next_base_fee(U256::one(), target + 1, target),
+			U256::from(2)
+		);
+	}
+
+	#[test]
+	fn test_blob_base_fee() {
+		assert_eq!(blob_base_fee(U256::zero()), U256::one());
+		// About e.
+		assert_eq!(
+			blob_base_fee(U256::from(BLOB_BASE_FEE_UPDATE_FRACTION)),
+			U256::from(2)
+		);
+		assert_eq!(
+			blob_base_fee(U256::from(BLOB_BASE_FEE_UPDATE_FRACTION * 10)),
+			U256::from(22_026)
+		);
+
+		let target = U256::from(TARGET_BLOB_GAS_PER_BLOCK);
+		let max = U256::from(MAX_BLOB_GAS_PER_BLOCK);
+		assert_eq!(next_excess_blob_gas(U256::zero(), max), target);
+		assert_eq!(next_excess_blob_gas(target, U256::zero()), U256::zero());
+		assert_eq!(next_excess_blob_gas(target, target), target);
+	}
+
+	#[test]
+	fn test_transact_call_with_fee() {
+		let config = Config::london();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::from_low_u64_be(0xc0),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::from(10),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let caller = H160::from_low_u64_be(1);
+		let treasury = H160::from_low_u64_be(2);
+		let mut state = BTreeMap::new();
+		state.insert(
+			caller,
+			MemoryAccount {
+				balance: U256::from(1_000_000),
+				..Default::default()
+			},
+		);
+		let backend = MemoryBackend::new(&vicinity, state);
+		let metadata = StackSubstateMetadata::new(100_000, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &());
+		executor.set_base_fee_treasury(Some(treasury));
+
+		let fee = DynamicFee {
+			max_fee_per_gas: U256::from(20),
+			max_priority_fee_per_gas: U256::from(3),
+		};
+		let mut transact = |gas_limit| {
+			executor.transact_call_with_fee(
+				caller,
+				H160::from_low_u64_be(3),
+				U256::zero(),
+				Vec::new(),
+				gas_limit,
+				fee,
+				Vec::new(),
+			)
+		};
+		// Intrinsic gas above the gas limit is rejected before paying.
+		let (reason, _) = transact(20_000);
+		assert_eq!(reason, ExitError::OutOfGas.into());
+		// Each transaction pays for its own gas only.
+		assert!(transact(30_000).0.is_succeed());
+		assert!(transact(30_000).0.is_succeed());
+		assert_eq!(executor.used_gas(), 2 * 21_000);
+
+		// The caller pays 13 per unit of gas, 10 of which go to the treasury.
+		let state = executor.state();
+		assert_eq!(
+			state.basic(caller).balance,
+			U256::from(1_000_000 - 2 * 21_000 * 13)
+		);
+		assert_eq!(state.basic(treasury).balance, U256::from(2 * 21_000 * 10));
+		assert_eq!(
+			state.basic(vicinity.block_coinbase).balance,
+			U256::from(2 * 21_000 * 3)
+		);
+
+		// A fee cap below the base fee is rejected.
+		let fee = DynamicFee {
+			max_fee_per_gas: U256::from(5),
+			max_priority_fee_per_gas: U256::zero(),
+		};
+		assert!(fee.effective_gas_price(U256::from(10)).is_err());
+	}
+}
@@ -8,11 +8,14 @@ use alloc::{
 };
 use core::mem;
 use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
 
 #[derive(Clone, Debug)]
 pub struct MemoryStackAccount {
 	pub basic: Basic,
 	pub code: Option<Vec<u8>>,
+	/// Hash of `code`, if known.
+	pub code_hash: Option<H256>,
 	pub reset: bool,
 }
 
@@ -23,7 +26,9 @@ pub struct MemoryStackSubstate<'config> {
 	logs: Vec<Log>,
 	accounts: BTreeMap<H160, MemoryStackAccount>,
 	storages: BTreeMap<(H160, H256), H256>,
+	transient_storages: BTreeMap<(H160, H256), H256>,
 	deletes: BTreeSet<H160>,
+	creates: BTreeSet<H160>,
 }
 
 impl<'config> MemoryStackSubstate<'config> {
@@ -34,7 +39,9 @@ impl<'config> MemoryStackSubstate<'config> {
 			logs: Vec::new(),
 			accounts: BTreeMap::new(),
 			storages: BTreeMap::new(),
+			transient_storages: BTreeMap::new(),
 			deletes: BTreeSet::new(),
+			creates: BTreeSet::new(),
 		}
 	}
 
@@ -119,7 +126,9 @@ impl<'config> MemoryStackSubstate<'config> {
 			logs: Vec::new(),
 			accounts: BTreeMap::new(),
 			storages: BTreeMap::new(),
+			transient_storages: BTreeMap::new(),
 			deletes: BTreeSet::new(),
+			creates: BTreeSet::new(),
 		};
 		mem::swap(&mut entering, self);
 
@@ -151,7 +160,10 @@ impl<'config> MemoryStackSubstate<'config> {
 
 		self.accounts.append(&mut exited.accounts);
 		self.storages.append(&mut exited.storages);
+		self.transient_storages
+			.append(&mut exited.transient_storages);
 		self.deletes.append(&mut exited.deletes);
+		self.creates.append(&mut exited.creates);
 
 		Ok(())
 	}
@@ -192,6 +204,14 @@ impl<'config> MemoryStackSubstate<'config> {
 		self.known_account(address).and_then(|acc| acc.code.clone())
 	}
 
+	pub fn known_code_hash(&self, address: H160) -> Option<H256> {
+		self.known_account(address).and_then(|acc| match (&acc.code, acc.code_hash) {
+			(Some(_), Some(hash)) => Some(hash),
+			(Some(code), None) => Some(H256::from_slice(Keccak256::digest(code).as_slice())),
+			(None, _) => None,
+		})
+	}
+
 	pub fn known_empty(&self, address: H160) -> Option<bool> {
 		if let Some(account) = self.known_account(address) {
 			if account.basic.balance != U256::zero() {
@@ -232,6 +252,18 @@ impl<'config> MemoryStackSubstate<'config> {
 		None
 	}
 
+	pub fn known_transient_storage(&self, address: H160, key: H256) -> Option<H256> {
+		if let Some(value) = self.transient_storages.get(&(address, key)) {
+			return Some(*value);
+		}
+
+		if let Some(parent) = self.parent.as_ref() {
+			return parent.known_transient_storage(address, key);
+		}
+
+		None
+	}
+
 	pub fn known_original_storage(&self, address: H160, key: H256) -> Option<H256> {
 		if let Some(account) = self.accounts.get(&address) {
 			if account.reset {
@@ -278,6 +310,18 @@ impl<'config> MemoryStackSubstate<'config> {
 		false
 	}
 
+	pub fn created(&self, address: H160) -> bool {
+		if self.creates.contains(&address) {
+			return true;
+		}
+
+		if let Some(parent) = self.parent.as_ref() {
+			return parent.created(address);
+		}
+
+		false
+	}
+
 	#[allow(clippy::map_entry)]
 	fn account_mut<B: Backend>(&mut self, address: H160, backend: &B) -> &mut MemoryStackAccount {
 		if !self.accounts.contains_key(&address) {
@@ -291,6 +335,7 @@ impl<'config> MemoryStackSubstate<'config> {
 				.unwrap_or_else(|| MemoryStackAccount {
 					basic: backend.basic(address),
 					code: None,
+					code_hash: None,
 					reset: false,
 				});
 			self.accounts.insert(address, account);
@@ -309,6 +354,10 @@ impl<'config> MemoryStackSubstate<'config> {
 		self.storages.insert((address, key), value);
 	}
 
+	pub fn set_transient_storage(&mut self, address: H160, key: H256, value: H256) {
+		self.transient_storages.insert((address, key), value);
+	}
+
 	pub fn reset_storage<B: Backend>(&mut self, address: H160, backend: &B) {
 		let mut removing = Vec::new();
 
@@ -337,8 +386,21 @@ impl<'config> MemoryStackSubstate<'config> {
 		self.deletes.insert(address);
 	}
 
+	pub fn set_created(&mut self, address: H160) {
+		self.creates.insert(address);
+	}
+
+	/// Forget the transient storage and the created accounts, which only last
+	/// for a transaction.
+	pub fn clear_transaction(&mut self) {
+		self.transient_storages.clear();
+		self.creates.clear();
+	}
+
 	pub fn set_code<B: Backend>(&mut self, address: H160, code: Vec<u8>, backend: &B) {
-		self.account_mut(address, backend).code = Some(code);
+		let account = self.account_mut(address, backend);
+		account.code_hash = Some(H256::from_slice(Keccak256::digest(&code).as_slice()));
+		account.code = Some(code);
 	}
 
 	pub fn transfer<B: Backend>(
@@ -421,12 +483,21 @@ impl<'backend, 'config, B: Backend> Backend for MemoryStackState<'backend, 'conf
 	fn block_difficulty(&self) -> U256 {
 		self.backend.block_difficulty()
 	}
+	fn block_prev_randao(&self) -> H256 {
+		self.backend.block_prev_randao()
+	}
 	fn block_gas_limit(&self) -> U256 {
 		self.backend.block_gas_limit()
 	}
 	fn block_base_fee_per_gas(&self) -> U256 {
 		self.backend.block_base_fee_per_gas()
 	}
+	fn block_blob_base_fee(&self) -> U256 {
+		self.backend.block_blob_base_fee()
+	}
+	fn blob_hash(&self, index: usize) -> Option<H256> {
+		self.backend.blob_hash(index)
+	}
 
 	fn chain_id(&self) -> U256 {
 		self.backend.chain_id()
@@ -448,6 +519,12 @@ impl<'backend, 'config, B: Backend> Backend for MemoryStackState<'backend, 'conf
 			.unwrap_or_else(|| self.backend.code(address))
 	}
 
+	fn code_hash(&self, address: H160) -> H256 {
+		self.substate
+			.known_code_hash(address)
+			.unwrap_or_else(|| self.backend.code_hash(address))
+	}
+
 	fn storage(&self, address: H160, key: H256) -> H256 {
 		self.substate
 			.known_storage(address, key)
@@ -488,6 +565,10 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 		self.substate.exit_discard()
 	}
 
+	fn clear_transaction(&mut self) {
+		self.substate.clear_transaction()
+	}
+
 	fn is_empty(&self, address: H160) -> bool {
 		if let Some(known_empty) = self.substate.known_empty(address) {
 			return known_empty;
@@ -502,6 +583,16 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 		self.substate.deleted(address)
 	}
 
+	fn created(&self, address: H160) -> bool {
+		self.substate.created(address)
+	}
+
+	fn transient_storage(&self, address: H160, key: H256) -> H256 {
+		self.substate
+			.known_transient_storage(address, key)
+			.unwrap_or_default()
+	}
+
 	fn is_cold(&self, address: H160) -> bool {
 		self.substate.is_cold(address)
 	}
@@ -518,6 +609,10 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 		self.substate.set_storage(address, key, value)
 	}
 
+	fn set_transient_storage(&mut self, address: H160, key: H256, value: H256) {
+		self.substate.set_transient_storage(address, key, value)
+	}
+
 	fn reset_storage(&mut self, address: H160) {
 		self.substate.reset_storage(address, self.backend);
 	}
@@ -530,6 +625,10 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 		self.substate.set_deleted(address)
 	}
 
+	fn set_created(&mut self, address: H160) {
+		self.substate.set_created(address)
+	}
+
 	fn set_code(&mut self, address: H160, code: Vec<u8>) {
 		self.substate.set_code(address, code, self.backend)
 	}
@@ -538,6 +637,14 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 		self.substate.transfer(transfer, self.backend)
 	}
 
+	fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError> {
+		self.substate.withdraw(address, value, self.backend)
+	}
+
+	fn deposit(&mut self, address: H160, value: U256) {
+		self.substate.deposit(address, value, self.backend)
+	}
+
 	fn reset_balance(&mut self, address: H160) {
 		self.substate.reset_balance(address, self.backend)
 	}
@@ -555,9 +662,12 @@ impl<'backend, 'config, B: Backend> MemoryStackState<'backend, 'config, B> {
 		}
 	}
 
-	/// Returns a mutable reference to an account given its address
+	/// Returns a mutable reference to an account given its address. Its code
+	/// hash is cleared, as its code may be changed through the reference.
 	pub fn account_mut(&mut self, address: H160) -> &mut MemoryStackAccount {
-		self.substate.account_mut(address, self.backend)
+		let account = self.substate.account_mut(address, self.backend);
+		account.code_hash = None;
+		account
 	}
 
 	#[must_use]
@@ -2,12 +2,30 @@
 //! A memory-based state is provided, but can replaced by a custom
 //! implementation, for exemple one interacting with a database.
 
+mod access_list;
+mod code_cache;
+mod custom;
 mod executor;
+mod fee;
 mod memory;
+mod precompile;
+#[cfg(test)]
+mod tests;
 
+pub use self::access_list::{create_access_list, AccessList, AccessListResult};
+pub use self::code_cache::{AnalysedCode, Analysis, CodeCache};
+pub use self::custom::{CustomOpcode, CustomOpcodeCost, CustomOpcodeCostFn, CustomOpcodeFn};
 pub use self::executor::{
-	Accessed, PrecompileFailure, PrecompileFn, PrecompileOutput, PrecompileSet, StackExecutor,
-	StackExitKind, StackState, StackSubstateMetadata,
+	Accessed, StackExecutor, StackExitKind, StackState, StackSubstateMetadata,
+};
+pub use self::fee::{
+	blob_base_fee, next_base_fee, next_excess_blob_gas, DynamicFee,
+	BASE_FEE_MAX_CHANGE_DENOMINATOR, ELASTICITY_MULTIPLIER, GAS_PER_BLOB, INITIAL_BASE_FEE,
+	MAX_BLOB_GAS_PER_BLOCK, TARGET_BLOB_GAS_PER_BLOCK,
+};
+pub use self::precompile::{
+	Precompile, PrecompileFailure, PrecompileFn, PrecompileHandle, PrecompileOutput,
+	PrecompileResult, PrecompileSet,
 };
 
 pub use self::memory::{MemoryStackAccount, MemoryStackState, MemoryStackSubstate};
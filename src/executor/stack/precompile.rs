@@ -0,0 +1,365 @@
+use crate::{Context, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed, Transfer};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+
+/// Data returned by a precompile on success.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PrecompileOutput {
+	pub exit_status: ExitSucceed,
+	pub output: Vec<u8>,
+}
+
+/// Data returned by a precompile in case of failure.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum PrecompileFailure {
+	/// Reverts the state changes and consume all the gas.
+	Error { exit_status: ExitError },
+	/// Reverts the state changes, keeping the gas that was not recorded.
+	/// Returns the provided error message.
+	Revert {
+		exit_status: ExitRevert,
+		output: Vec<u8>,
+	},
+	/// Mark this failure as fatal, and all EVM execution stacks must be exited.
+	Fatal { exit_status: ExitFatal },
+}
+
+impl From<ExitError> for PrecompileFailure {
+	fn from(exit_status: ExitError) -> Self {
+		Self::Error { exit_status }
+	}
+}
+
+/// A precompile result.
+pub type PrecompileResult = Result<PrecompileOutput, PrecompileFailure>;
+
+/// Access to the call being executed and to the executor state.
+///
+/// A precompile runs in its own substate, like any other call: state changes
+/// made through the handle are committed if it succeeds and discarded if it
+/// reverts or fails. Gas is only charged through [`record_cost`], and a
+/// failure consumes the whole gas limit of the call.
+///
+/// [`record_cost`]: PrecompileHandle::record_cost
+pub trait PrecompileHandle {
+	/// Perform a subcall, as the `CALL` family of opcodes would. The base cost
+	/// of the call is recorded before `gas_limit` is passed on.
+	fn call(
+		&mut self,
+		to: H160,
+		transfer: Option<Transfer>,
+		input: Vec<u8>,
+		gas_limit: Option<u64>,
+		is_static: bool,
+		context: &Context,
+	) -> (ExitReason, Vec<u8>);
+
+	/// Charge gas, failing with `OutOfGas` if not enough is left.
+	fn record_cost(&mut self, cost: u64) -> Result<(), ExitError>;
+
+	/// Gas left to the precompile.
+	fn remaining_gas(&self) -> u64;
+
+	/// Emit a log.
+	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError>;
+
+	/// Balance of an account.
+	fn balance(&self, address: H160) -> U256;
+
+	/// Read a storage value, adding the slot to the accessed set.
+	fn storage(&mut self, address: H160, index: H256) -> H256;
+
+	/// Write a storage value, adding the slot to the accessed set.
+	fn set_storage(&mut self, address: H160, index: H256, value: H256) -> Result<(), ExitError>;
+
+	/// Move value between accounts.
+	fn transfer(&mut self, transfer: Transfer) -> Result<(), ExitError>;
+
+	/// Address of the precompile being called.
+	fn code_address(&self) -> H160;
+
+	/// Input of the call.
+	fn input(&self) -> &[u8];
+
+	/// Context of the call.
+	fn context(&self) -> &Context;
+
+	/// Whether the call is static, in which case state changes fail.
+	fn is_static(&self) -> bool;
+
+	/// Gas limit of the call, if any.
+	fn gas_limit(&self) -> Option<u64>;
+}
+
+/// A set of precompiles.
+/// Checks of the provided address being in the precompile set should be
+/// as cheap as possible since it may be called often.
+pub trait PrecompileSet {
+	/// Tries to execute the precompile at `handle.code_address()`.
+	/// If the address is not a precompile, returns None.
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult>;
+
+	/// Check if the given address is a precompile. Should only be called to
+	/// perform the check while not executing the precompile afterward, since
+	/// `execute` already performs a check internally.
+	fn is_precompile(&self, address: H160) -> bool;
+}
+
+impl PrecompileSet for () {
+	fn execute(&self, _: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		None
+	}
+
+	fn is_precompile(&self, _: H160) -> bool {
+		false
+	}
+}
+
+/// Precompiles function signature.
+pub type PrecompileFn = fn(&mut dyn PrecompileHandle) -> PrecompileResult;
+
+impl PrecompileSet for BTreeMap<H160, PrecompileFn> {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		self.get(&handle.code_address())
+			.map(|precompile| (*precompile)(handle))
+	}
+
+	/// Check if the given address is a precompile. Should only be called to
+	/// perform the check while not executing the precompile afterward, since
+	/// `execute` already performs a check internally.
+	fn is_precompile(&self, address: H160) -> bool {
+		self.contains_key(&address)
+	}
+}
+
+/// A single precompile. Unlike [`PrecompileFn`], implementations may carry
+/// their own configuration and state, and closures taking a handle are
+/// precompiles too. See `precompiles::Overrides` to place them at addresses.
+pub trait Precompile {
+	/// Execute the precompile.
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> PrecompileResult;
+}
+
+impl<F> Precompile for F
+where
+	F: Fn(&mut dyn PrecompileHandle) -> PrecompileResult,
+{
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> PrecompileResult {
+		self(handle)
+	}
+}
+
+impl<P: PrecompileSet + ?Sized> PrecompileSet for &P {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		(**self).execute(handle)
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		(**self).is_precompile(address)
+	}
+}
+
+impl<P: PrecompileSet + ?Sized> PrecompileSet for Box<P> {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		(**self).execute(handle)
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		(**self).is_precompile(address)
+	}
+}
+
+/// Tuples chain their sets in order: the first set containing an address
+/// handles it.
+macro_rules! impl_precompile_set_for_tuple {
+	($($set:ident),+) => {
+		impl<$($set: PrecompileSet),+> PrecompileSet for ($($set,)+) {
+			#[allow(non_snake_case)]
+			fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+				let ($($set,)+) = self;
+				$(
+					if let Some(result) = $set.execute(handle) {
+						return Some(result);
+					}
+				)+
+				None
+			}
+
+			#[allow(non_snake_case)]
+			fn is_precompile(&self, address: H160) -> bool {
+				let ($($set,)+) = self;
+				$($set.is_precompile(address))||+
+			}
+		}
+	};
+}
+
+impl_precompile_set_for_tuple!(A, B);
+impl_precompile_set_for_tuple!(A, B, C);
+impl_precompile_set_for_tuple!(A, B, C, D);
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::backend::{Backend, MemoryBackend, MemoryVicinity};
+	use crate::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+	use crate::executor::Executor;
+	use crate::Config;
+
+	/// Increments a counter in its own storage, reverting afterwards when
+	/// called with `revert`.
+	fn counter(handle: &mut dyn PrecompileHandle) -> PrecompileResult {
+		handle.record_cost(100)?;
+
+		let address = handle.code_address();
+		let count = handle.storage(address, H256::zero());
+		let count = H256::from_low_u64_be(count.to_low_u64_be() + 1);
+		handle.set_storage(address, H256::zero(), count)?;
+
+		if handle.input() == b"revert" {
+			return Err(PrecompileFailure::Revert {
+				exit_status: ExitRevert::Reverted,
+				output: Vec::new(),
+			});
+		}
+		Ok(PrecompileOutput {
+			exit_status: ExitSucceed::Returned,
+			output: count.as_bytes().to_vec(),
+		})
+	}
+
+	/// Calls an empty account, as a static call of itself when called with
+	/// `static`, moving value when called with `pay`.
+	fn forwarder(handle: &mut dyn PrecompileHandle) -> PrecompileResult {
+		let address = handle.code_address();
+		let context = handle.context().clone();
+		let target = H160::repeat_byte(0xee);
+		let (reason, _) = match handle.input() {
+			b"static" => handle.call(address, None, b"pay".to_vec(), None, true, &context),
+			b"pay" => {
+				let transfer = Transfer {
+					source: address,
+					target,
+					value: U256::one(),
+				};
+				handle.call(target, Some(transfer), Vec::new(), None, false, &context)
+			}
+			_ => handle.call(target, None, Vec::new(), None, false, &context),
+		};
+		match reason {
+			ExitReason::Error(exit_status) => Err(PrecompileFailure::Error { exit_status }),
+			_ => Ok(PrecompileOutput {
+				exit_status: ExitSucceed::Returned,
+				output: Vec::new(),
+			}),
+		}
+	}
+
+	#[test]
+	fn test_precompile_subcall() {
+		let config = Config::frontier();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+		let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+
+		let address = H160::from_low_u64_be(0x100);
+		let mut precompiles = BTreeMap::<H160, PrecompileFn>::new();
+		precompiles.insert(address, forwarder);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+
+		// Without a gas limit, the subcall asks for the gas left.
+		let (reason, _) = executor.transact_call(
+			H160::default(),
+			address,
+			U256::zero(),
+			Vec::new(),
+			1_000_000,
+			Vec::new(),
+		);
+		assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+
+		let (reason, _) = executor.transact_call(
+			H160::default(),
+			address,
+			U256::zero(),
+			b"static".to_vec(),
+			1_000_000,
+			Vec::new(),
+		);
+		assert_eq!(
+			reason,
+			ExitReason::Error(ExitError::Other("state change in static call".into()))
+		);
+	}
+
+	#[test]
+	fn test_stateful_precompile() {
+		let config = Config::istanbul();
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::max_value(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+		let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+
+		let address = H160::from_low_u64_be(0x100);
+		let mut precompiles = BTreeMap::<H160, PrecompileFn>::new();
+		precompiles.insert(address, counter);
+		let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+
+		let (reason, output) = executor.transact_call(
+			H160::default(),
+			address,
+			U256::zero(),
+			Vec::new(),
+			1_000_000,
+			Vec::new(),
+		);
+		assert!(reason.is_succeed());
+		assert_eq!(output, H256::from_low_u64_be(1).as_bytes());
+
+		// Changes made before a revert are discarded.
+		let (reason, _) = executor.transact_call(
+			H160::default(),
+			address,
+			U256::zero(),
+			b"revert".to_vec(),
+			1_000_000,
+			Vec::new(),
+		);
+		assert_eq!(reason, ExitReason::Revert(ExitRevert::Reverted));
+		assert_eq!(
+			executor.state().storage(address, H256::zero()),
+			H256::from_low_u64_be(1)
+		);
+	}
+}
@@ -0,0 +1,355 @@
+//! Behaviour of the executor that depends on the fork.
+
+use crate::backend::{ApplyBackend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use crate::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+use crate::executor::Executor;
+use crate::{Config, CreateScheme, ExitError, ExitReason};
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+
+const GAS_LIMIT: u64 = 1_000_000;
+
+type TestExecutor<'a, 'vicinity> =
+	StackExecutor<'a, 'a, MemoryStackState<'a, 'a, MemoryBackend<'vicinity>>, ()>;
+
+fn caller() -> H160 {
+	H160::from_low_u64_be(1)
+}
+
+fn contract() -> H160 {
+	H160::from_low_u64_be(0x10)
+}
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: caller(),
+		chain_id: U256::one(),
+		block_hashes: Vec::new(),
+		block_number: U256::one(),
+		block_coinbase: H160::from_low_u64_be(0xc0),
+		block_timestamp: U256::one(),
+		block_difficulty: U256::from(0xd1ff),
+		block_prev_randao: H256::repeat_byte(0x5a),
+		block_gas_limit: U256::from(GAS_LIMIT),
+		block_base_fee_per_gas: U256::zero(),
+		block_blob_base_fee: U256::zero(),
+		blob_hashes: Vec::new(),
+	}
+}
+
+/// The caller, and `code` at `contract()`.
+fn state(code: Vec<u8>) -> BTreeMap<H160, MemoryAccount> {
+	let mut state = BTreeMap::new();
+	state.insert(
+		caller(),
+		MemoryAccount {
+			balance: U256::from(GAS_LIMIT),
+			..Default::default()
+		},
+	);
+	state.insert(
+		contract(),
+		MemoryAccount {
+			nonce: U256::one(),
+			code,
+			..Default::default()
+		},
+	);
+	state
+}
+
+/// Run `f` on an executor over `state`, returning its result and the state
+/// after applying the changes.
+fn execute<R>(
+	config: &Config,
+	state: BTreeMap<H160, MemoryAccount>,
+	f: impl FnOnce(&mut TestExecutor) -> R,
+) -> (R, BTreeMap<H160, MemoryAccount>) {
+	let vicinity = vicinity();
+	let mut backend = MemoryBackend::new(&vicinity, state);
+	let metadata = StackSubstateMetadata::new(GAS_LIMIT, config);
+	let state = MemoryStackState::new(metadata, &backend);
+	let mut executor = StackExecutor::new_with_precompiles(state, config, &());
+
+	let result = f(&mut executor);
+	let (values, logs) = executor.into_state().deconstruct();
+	backend.apply(values, logs, true);
+	(result, backend.state().clone())
+}
+
+/// Call `code`, returning the exit reason, the output and the used gas.
+fn call(config: &Config, code: Vec<u8>) -> (ExitReason, Vec<u8>, u64) {
+	execute(config, state(code), |executor| {
+		let (reason, output) = executor.transact_call(
+			caller(),
+			contract(),
+			U256::zero(),
+			Vec::new(),
+			GAS_LIMIT,
+			Vec::new(),
+		);
+		(reason, output, executor.used_gas())
+	})
+	.0
+}
+
+/// Deploy `init_code`, returning the exit reason and the used gas.
+fn create(config: &Config, init_code: Vec<u8>) -> (ExitReason, u64) {
+	execute(config, state(Vec::new()), |executor| {
+		let (reason, _) =
+			executor.transact_create(caller(), U256::zero(), init_code, GAS_LIMIT, Vec::new());
+		(reason, executor.used_gas())
+	})
+	.0
+}
+
+#[test]
+fn test_push0() {
+	// PUSH0, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+	let code = vec![0x5f, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+	let (reason, output, _) = call(&Config::shanghai(), code.clone());
+	assert!(reason.is_succeed());
+	assert_eq!(output, vec![0; 32]);
+
+	let (reason, _, used_gas) = call(&Config::london(), code);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(used_gas, GAS_LIMIT);
+}
+
+#[test]
+fn test_initcode_limits() {
+	// Zero bytes, which stop at once.
+	let (reason, used_gas) = create(&Config::shanghai(), vec![0; 64]);
+	assert!(reason.is_succeed());
+	// Creation, 4 per zero byte and 2 per word of initcode.
+	assert_eq!(used_gas, 53_000 + 64 * 4 + 2 * 2);
+	let (_, used_gas) = create(&Config::london(), vec![0; 64]);
+	assert_eq!(used_gas, 53_000 + 64 * 4);
+
+	let limit = Config::shanghai().max_initcode_size.unwrap();
+	let (reason, _) = create(&Config::shanghai(), vec![0; limit]);
+	assert!(reason.is_succeed());
+	let (reason, used_gas) = create(&Config::shanghai(), vec![0; limit + 1]);
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateContractLimit));
+	assert_eq!(used_gas, GAS_LIMIT);
+	let (reason, _) = create(&Config::london(), vec![0; limit + 1]);
+	assert!(reason.is_succeed());
+
+	// CREATE with initcode of `limit + 1` bytes fails, consuming the gas of
+	// the contract.
+	let size = (limit + 1) as u16;
+	// PUSH2 size, PUSH1 0, PUSH1 0, CREATE, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+	let code = vec![
+		0x61,
+		(size >> 8) as u8,
+		size as u8,
+		0x60,
+		0x00,
+		0x60,
+		0x00,
+		0xf0,
+		0x60,
+		0x00,
+		0x52,
+		0x60,
+		0x20,
+		0x60,
+		0x00,
+		0xf3,
+	];
+	let (reason, _, used_gas) = call(&Config::shanghai(), code.clone());
+	assert_eq!(reason, ExitReason::Error(ExitError::CreateContractLimit));
+	assert_eq!(used_gas, GAS_LIMIT);
+	let (reason, output, _) = call(&Config::london(), code);
+	assert!(reason.is_succeed());
+	assert_ne!(output, vec![0; 32]);
+}
+
+#[test]
+fn test_warm_coinbase() {
+	// COINBASE, BALANCE, STOP
+	let code = vec![0x41, 0x31, 0x00];
+	let (_, _, used_gas) = call(&Config::shanghai(), code.clone());
+	assert_eq!(used_gas, 21_000 + 2 + 100);
+	let (_, _, used_gas) = call(&Config::london(), code);
+	assert_eq!(used_gas, 21_000 + 2 + 2600);
+}
+
+#[test]
+fn test_prevrandao() {
+	// DIFFICULTY, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+	let code = vec![0x44, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3];
+	let (_, output, _) = call(&Config::merge(), code.clone());
+	assert_eq!(output, vicinity().block_prev_randao.as_bytes());
+	let (_, output, _) = call(&Config::london(), code);
+	assert_eq!(U256::from_big_endian(&output), vicinity().block_difficulty);
+}
+
+/// Code that calls itself with 32 bytes of call data, then returns the
+/// transient storage slot 0. The inner call stores 1 in the slot, then ends
+/// with `end`.
+fn transient_storage_code(end: u8) -> Vec<u8> {
+	vec![
+		0x36, 0x60, 0x1d, 0x57, // JUMPI to the inner call with call data
+		0x60, 0x00, 0x60, 0x00, 0x60, 0x20, 0x60, 0x00, 0x60, 0x00, // CALL arguments
+		0x30, 0x5a, 0xf1, 0x50, // CALL ADDRESS with all the gas
+		0x60, 0x00, 0x5c, 0x60, 0x00, 0x52, // MSTORE(0, TLOAD(0))
+		0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN 32 bytes
+		0x5b, 0x60, 0x01, 0x60, 0x00, 0x5d, // TSTORE(0, 1)
+		0x60, 0x00, 0x60, 0x00, end,
+	]
+}
+
+#[test]
+fn test_transient_storage() {
+	let one = H256::from_low_u64_be(1).as_bytes().to_vec();
+	let (reason, output, _) = call(&Config::cancun(), transient_storage_code(0xf3));
+	assert!(reason.is_succeed());
+	assert_eq!(output, one);
+	// Values stored by a reverted call are dropped.
+	let (reason, output, _) = call(&Config::cancun(), transient_storage_code(0xfd));
+	assert!(reason.is_succeed());
+	assert_eq!(output, vec![0; 32]);
+
+	// TSTORE in a static call fails.
+	let code = vec![
+		0x36, 0x60, 0x17, 0x57, // JUMPI to the inner call with call data
+		0x60, 0x00, 0x60, 0x00, 0x60, 0x20, 0x60, 0x00, // STATICCALL arguments
+		0x30, 0x5a, 0xfa, // STATICCALL ADDRESS with all the gas
+		0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN the success flag
+		0x5b, 0x60, 0x01, 0x60, 0x00, 0x5d, 0x00, // TSTORE(0, 1)
+	];
+	let (reason, output, _) = call(&Config::cancun(), code);
+	assert!(reason.is_succeed());
+	assert_eq!(output, vec![0; 32]);
+
+	// Transient storage does not outlive the transaction.
+	let code = vec![
+		0x36, 0x60, 0x0f, 0x57, // JUMPI to the store with call data
+		0x60, 0x00, 0x5c, 0x60, 0x00, 0x52, // MSTORE(0, TLOAD(0))
+		0x60, 0x20, 0x60, 0x00, 0xf3, // RETURN 32 bytes
+		0x5b, 0x60, 0x01, 0x60, 0x00, 0x5d, 0x00, // TSTORE(0, 1)
+	];
+	let (outputs, _) = execute(&Config::cancun(), state(code), |executor| {
+		[vec![1], vec![]]
+			.iter()
+			.map(|data| {
+				let (_, output) = executor.transact_call(
+					caller(),
+					contract(),
+					U256::zero(),
+					data.clone(),
+					GAS_LIMIT,
+					Vec::new(),
+				);
+				output
+			})
+			.collect::<Vec<_>>()
+	});
+	assert_eq!(outputs, vec![Vec::new(), vec![0; 32]]);
+}
+
+#[test]
+fn test_mcopy() {
+	// MSTORE(0, 0x0102..20), MCOPY(dst, src, 8), RETURN 32 bytes
+	let code = |dst: u8, src: u8| {
+		let mut code = vec![0x7f];
+		code.extend(1..=32);
+		code.extend([0x60, 0x00, 0x52, 0x60, 0x08, 0x60, src, 0x60, dst, 0x5e]);
+		code.extend([0x60, 0x20, 0x60, 0x00, 0xf3]);
+		code
+	};
+
+	let (reason, output, _) = call(&Config::cancun(), code(1, 0));
+	assert!(reason.is_succeed());
+	let mut expected = vec![1, 1, 2, 3, 4, 5, 6, 7, 8];
+	expected.extend(10..=32);
+	assert_eq!(output, expected);
+
+	let (_, output, _) = call(&Config::cancun(), code(0, 1));
+	let mut expected = (2..=9).collect::<Vec<_>>();
+	expected.extend(9..=32);
+	assert_eq!(output, expected);
+
+	let (reason, _, _) = call(&Config::shanghai(), code(1, 0));
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+}
+
+#[test]
+fn test_selfdestruct() {
+	let beneficiary = H160::from_low_u64_be(0xbe);
+	// SELFDESTRUCT to the beneficiary
+	let mut selfdestruct = vec![0x73];
+	selfdestruct.extend(beneficiary.as_bytes());
+	selfdestruct.push(0xff);
+
+	// A contract created earlier only sends its balance.
+	let mut accounts = state(selfdestruct.clone());
+	accounts.get_mut(&contract()).unwrap().balance = U256::from(100);
+	accounts
+		.get_mut(&contract())
+		.unwrap()
+		.storage
+		.insert(H256::zero(), H256::repeat_byte(1));
+	let call = |executor: &mut TestExecutor, address| {
+		executor
+			.transact_call(
+				caller(),
+				address,
+				U256::zero(),
+				Vec::new(),
+				GAS_LIMIT,
+				Vec::new(),
+			)
+			.0
+	};
+	let (reason, after) = execute(&Config::cancun(), accounts.clone(), |executor| {
+		call(executor, contract())
+	});
+	assert!(reason.is_succeed());
+	assert_eq!(after[&beneficiary].balance, U256::from(100));
+	let account = &after[&contract()];
+	assert_eq!(account.balance, U256::zero());
+	assert_eq!(account.code, selfdestruct);
+	assert_eq!(account.storage.len(), 1);
+
+	let (_, after) = execute(&Config::shanghai(), accounts, |executor| {
+		call(executor, contract())
+	});
+	assert!(!after.contains_key(&contract()));
+
+	// A contract created in the same transaction is deleted.
+	let (address, after) = execute(&Config::cancun(), state(Vec::new()), |executor| {
+		let address = executor.create_address(CreateScheme::Legacy { caller: caller() });
+		let (reason, _) = executor.transact_create(
+			caller(),
+			U256::from(7),
+			selfdestruct.clone(),
+			GAS_LIMIT,
+			Vec::new(),
+		);
+		assert!(reason.is_succeed());
+		address
+	});
+	assert!(!after.contains_key(&address));
+	assert_eq!(after[&beneficiary].balance, U256::from(7));
+
+	// Contracts created by a previous transaction are not.
+	// PUSH1 22, DUP1, PUSH1 12, PUSH1 0, CODECOPY, PUSH1 0, RETURN, then the code
+	let mut init_code = vec![
+		0x60, 0x16, 0x80, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x00, 0xf3, 0x00,
+	];
+	init_code.extend(&selfdestruct);
+	let (address, after) = execute(&Config::cancun(), state(Vec::new()), |executor| {
+		let address = executor.create_address(CreateScheme::Legacy { caller: caller() });
+		let (reason, _) =
+			executor.transact_create(caller(), U256::zero(), init_code, GAS_LIMIT, Vec::new());
+		assert!(reason.is_succeed());
+		assert!(call(executor, address).is_succeed());
+		address
+	});
+	assert_eq!(after[&address].code, selfdestruct);
+}
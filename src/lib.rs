@@ -26,5 +26,10 @@ macro_rules! event {
 	($x:expr) => {};
 }
 
+pub mod abi;
 pub mod backend;
+#[cfg(feature = "with-serde")]
+pub mod config;
 pub mod executor;
+pub mod precompiles;
+pub mod rpc;
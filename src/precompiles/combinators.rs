@@ -0,0 +1,149 @@
+use crate::executor::stack::{Precompile, PrecompileHandle, PrecompileResult, PrecompileSet};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use primitive_types::H160;
+
+/// Replace or remove individual addresses of an inner set.
+///
+/// ```ignore
+/// let mut precompiles = Overrides::new(StandardPrecompiles::new(&config));
+/// precompiles.insert(H160::from_low_u64_be(1), |handle: &mut dyn PrecompileHandle| mock(handle));
+/// precompiles.remove(H160::from_low_u64_be(9));
+/// ```
+pub struct Overrides<P> {
+	inner: P,
+	overrides: BTreeMap<H160, Option<Box<dyn Precompile>>>,
+}
+
+impl<P> Overrides<P> {
+	/// Wrap `inner` without overriding anything.
+	pub fn new(inner: P) -> Self {
+		Self {
+			inner,
+			overrides: BTreeMap::new(),
+		}
+	}
+
+	/// Handle calls to `address` with `precompile`.
+	pub fn insert<T: Precompile + 'static>(&mut self, address: H160, precompile: T) -> &mut Self {
+		self.overrides.insert(address, Some(Box::new(precompile)));
+		self
+	}
+
+	/// Treat `address` as an ordinary account, even if `inner` contains it.
+	pub fn remove(&mut self, address: H160) -> &mut Self {
+		self.overrides.insert(address, None);
+		self
+	}
+
+	/// The wrapped set.
+	pub fn inner(&self) -> &P {
+		&self.inner
+	}
+}
+
+impl<P: PrecompileSet> PrecompileSet for Overrides<P> {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		match self.overrides.get(&handle.code_address()) {
+			Some(Some(precompile)) => Some(precompile.execute(handle)),
+			Some(None) => None,
+			None => self.inner.execute(handle),
+		}
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		match self.overrides.get(&address) {
+			Some(overridden) => overridden.is_some(),
+			None => self.inner.is_precompile(address),
+		}
+	}
+}
+
+/// A single precompile handling every address that starts with a prefix. It
+/// can tell the addresses apart with [`PrecompileHandle::code_address`].
+pub struct Prefixed<P> {
+	prefix: Vec<u8>,
+	precompile: P,
+}
+
+impl<P> Prefixed<P> {
+	/// Route addresses starting with `prefix` to `precompile`.
+	///
+	/// Panics if the prefix is longer than an address.
+	pub fn new(prefix: &[u8], precompile: P) -> Self {
+		assert!(prefix.len() <= 20, "prefix longer than an address");
+
+		Self {
+			prefix: prefix.to_vec(),
+			precompile,
+		}
+	}
+}
+
+impl<P: Precompile> PrecompileSet for Prefixed<P> {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		if self.is_precompile(handle.code_address()) {
+			Some(self.precompile.execute(handle))
+		} else {
+			None
+		}
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address.as_bytes().starts_with(&self.prefix)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::executor::stack::PrecompileOutput;
+	use crate::precompiles::mock::MockHandle;
+	use crate::precompiles::StandardPrecompiles;
+	use crate::{Config, ExitSucceed};
+	use core::cell::Cell;
+
+	fn output(set: &dyn PrecompileSet, address: H160) -> Option<Vec<u8>> {
+		let mut handle = MockHandle::new(address, Vec::new());
+		set.execute(&mut handle)
+			.map(|result| result.ok().unwrap().output)
+	}
+
+	#[test]
+	fn test_combinators() {
+		let sha256 = H160::from_low_u64_be(2);
+		let identity = H160::from_low_u64_be(4);
+		let system = H160::from_low_u64_be(0x1000);
+
+		let calls = Cell::new(0u8);
+		let mut overrides = Overrides::new(StandardPrecompiles::new(&Config::istanbul()));
+		overrides
+			.insert(sha256, |_: &mut dyn PrecompileHandle| -> PrecompileResult {
+				Ok(PrecompileOutput {
+					exit_status: ExitSucceed::Returned,
+					output: vec![0xaa],
+				})
+			})
+			.remove(identity);
+
+		let prefixed = Prefixed::new(&[0; 18], |handle: &mut dyn PrecompileHandle| {
+			calls.set(calls.get() + 1);
+			Ok(PrecompileOutput {
+				exit_status: ExitSucceed::Returned,
+				output: handle.code_address()[18..].to_vec(),
+			})
+		});
+		let set = (overrides, prefixed);
+
+		assert_eq!(output(&set, sha256), Some(vec![0xaa]));
+		// Removed from the standard set, so the next set in the chain handles it.
+		assert_eq!(output(&set, identity), Some(vec![0, 4]));
+		assert_eq!(output(&set, system), Some(vec![0x10, 0]));
+		assert_eq!(output(&set, H160::repeat_byte(1)), None);
+		assert_eq!(calls.get(), 2);
+
+		assert!(set.is_precompile(H160::from_low_u64_be(1)));
+		assert!(!set.is_precompile(H160::repeat_byte(1)));
+	}
+}
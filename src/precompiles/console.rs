@@ -0,0 +1,252 @@
+use crate::abi::{self, ParamType, Token};
+use crate::executor::stack::{PrecompileHandle, PrecompileOutput, PrecompileResult, PrecompileSet};
+use crate::ExitSucceed;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use primitive_types::H160;
+use sha3::{Digest, Keccak256};
+
+/// Address called by Hardhat's `console.sol`, `0x000000000000000000636F6e736F6c652e6c6f67`.
+pub const CONSOLE_ADDRESS: H160 = H160([
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x63, 0x6f, 0x6e, 0x73, 0x6f, 0x6c, 0x65,
+	0x2e, 0x6c, 0x6f, 0x67,
+]);
+
+/// Receiver of `console.log` messages.
+pub trait ConsoleSink {
+	/// Handle one formatted message.
+	fn log(&self, message: &str);
+}
+
+impl<S: ConsoleSink + ?Sized> ConsoleSink for &S {
+	fn log(&self, message: &str) {
+		(**self).log(message)
+	}
+}
+
+/// Collects messages in order.
+impl ConsoleSink for RefCell<Vec<String>> {
+	fn log(&self, message: &str) {
+		self.borrow_mut().push(message.to_string());
+	}
+}
+
+/// Prints every message on its own line.
+#[cfg(feature = "std")]
+pub struct StdoutSink;
+
+#[cfg(feature = "std")]
+impl ConsoleSink for StdoutSink {
+	fn log(&self, message: &str) {
+		println!("{}", message);
+	}
+}
+
+/// Precompile set intercepting calls to [`CONSOLE_ADDRESS`] and delegating
+/// every other address to `inner`.
+///
+/// Calls with an unknown selector or malformed arguments are ignored, as they
+/// are by Hardhat. Console calls always succeed, cost no gas and return
+/// nothing.
+pub struct ConsoleLog<P, S> {
+	inner: P,
+	sink: S,
+	overloads: BTreeMap<[u8; 4], Vec<ParamType>>,
+}
+
+impl<P, S> ConsoleLog<P, S> {
+	/// Wrap `inner`, sending console messages to `sink`.
+	pub fn new(inner: P, sink: S) -> Self {
+		Self {
+			inner,
+			sink,
+			overloads: overloads(),
+		}
+	}
+
+	/// The message sink.
+	pub fn sink(&self) -> &S {
+		&self.sink
+	}
+
+	/// Decode a console call into its formatted message.
+	pub fn format(&self, input: &[u8]) -> Option<String> {
+		if input.len() < 4 {
+			return None;
+		}
+
+		let mut selector = [0u8; 4];
+		selector.copy_from_slice(&input[..4]);
+		let types = self.overloads.get(&selector)?;
+		let tokens = abi::decode(types, &input[4..]).ok()?;
+
+		Some(format(&tokens))
+	}
+}
+
+impl<P: PrecompileSet, S: ConsoleSink> PrecompileSet for ConsoleLog<P, S> {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		if handle.code_address() != CONSOLE_ADDRESS {
+			return self.inner.execute(handle);
+		}
+
+		if let Some(message) = self.format(handle.input()) {
+			self.sink.log(&message);
+		}
+
+		Some(Ok(PrecompileOutput {
+			exit_status: ExitSucceed::Returned,
+			output: Vec::new(),
+		}))
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		address == CONSOLE_ADDRESS || self.inner.is_precompile(address)
+	}
+}
+
+/// Selectors of every `console.sol` function, with their argument types.
+///
+/// Selectors are hashed from the signatures exactly as `console.sol` spells
+/// them, which for older releases means `uint` and `int` rather than the
+/// canonical `uint256` and `int256`.
+fn overloads() -> BTreeMap<[u8; 4], Vec<ParamType>> {
+	let mut signatures = vec![
+		"log()".to_string(),
+		"logInt(int)".to_string(),
+		"logInt(int256)".to_string(),
+		"logUint(uint)".to_string(),
+		"logUint(uint256)".to_string(),
+		"logString(string)".to_string(),
+		"logBool(bool)".to_string(),
+		"logAddress(address)".to_string(),
+		"logBytes(bytes)".to_string(),
+		"log(int)".to_string(),
+		"log(int256)".to_string(),
+		"log(bytes)".to_string(),
+	];
+	for n in 1..=32 {
+		signatures.push(format!("logBytes{}(bytes{})", n, n));
+	}
+
+	// `log` takes up to four arguments of these types in any combination.
+	for uint in &["uint", "uint256"] {
+		let kinds = [*uint, "string", "bool", "address"];
+		let mut lists = vec![String::new()];
+		for _ in 0..4 {
+			lists = lists
+				.iter()
+				.flat_map(|list| {
+					kinds.iter().map(move |kind| {
+						if list.is_empty() {
+							kind.to_string()
+						} else {
+							format!("{},{}", list, kind)
+						}
+					})
+				})
+				.collect();
+			signatures.extend(lists.iter().map(|list| format!("log({})", list)));
+		}
+	}
+
+	signatures
+		.into_iter()
+		.map(|signature| {
+			let args = &signature[signature.find('(').unwrap() + 1..signature.len() - 1];
+			let types = abi::parse_list(args).expect("console signatures are valid");
+
+			let mut selector = [0u8; 4];
+			selector.copy_from_slice(&Keccak256::digest(signature.as_bytes())[..4]);
+			(selector, types)
+		})
+		.collect()
+}
+
+/// Format arguments as Hardhat does: a leading string may contain `%s`, `%d`,
+/// `%i` and `%o` placeholders, each replaced by the next argument. Remaining
+/// arguments are appended, separated by spaces.
+fn format(tokens: &[Token]) -> String {
+	let values = tokens
+		.iter()
+		.map(|token| match token {
+			Token::String(s) => s.clone(),
+			token => token.to_string(),
+		})
+		.collect::<Vec<_>>();
+
+	let (first, rest) = match (tokens.first(), values.split_first()) {
+		(Some(Token::String(_)), Some((first, rest))) if !rest.is_empty() => (first, rest),
+		_ => return values.join(" "),
+	};
+
+	let mut rest = rest.iter();
+	let mut message = String::new();
+	let mut chars = first.chars().peekable();
+	while let Some(c) = chars.next() {
+		if c == '%' {
+			match chars.peek() {
+				Some('%') => {
+					chars.next();
+					message.push('%');
+					continue;
+				}
+				Some('s' | 'd' | 'i' | 'o') => {
+					if let Some(value) = rest.next() {
+						chars.next();
+						message.push_str(value);
+						continue;
+					}
+				}
+				_ => (),
+			}
+		}
+		message.push(c);
+	}
+
+	for value in rest {
+		message.push(' ');
+		message.push_str(value);
+	}
+	message
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::abi::Function;
+	use crate::precompiles::mock::MockHandle;
+
+	fn call(signature: &str, args: &[&str]) -> Vec<u8> {
+		let function = Function::parse(signature).unwrap();
+		let args = function.parse_inputs(args).unwrap();
+		function.encode_input(&args).unwrap()
+	}
+
+	#[test]
+	fn test_console_log() {
+		let console = ConsoleLog::new((), RefCell::new(Vec::new()));
+
+		for (signature, args) in &[
+			("log(string,uint256)", &["balance %s wei", "42"][..]),
+			("log(string,uint256,bool)", &["%d%%", "5", "true"][..]),
+			("logBytes2(bytes2)", &["0xbeef"][..]),
+			("log(uint256,uint256)", &["1", "2"][..]),
+		] {
+			let mut handle = MockHandle::new(CONSOLE_ADDRESS, call(signature, args));
+			assert!(matches!(console.execute(&mut handle), Some(Ok(_))));
+		}
+		// Unknown selectors are ignored.
+		let mut handle = MockHandle::new(CONSOLE_ADDRESS, vec![1, 2, 3, 4]);
+		assert!(console.execute(&mut handle).is_some());
+
+		assert_eq!(
+			console.sink().borrow().as_slice(),
+			&["balance 42 wei", "5% true", "0xbeef", "1 2"]
+		);
+		assert!(console.is_precompile(CONSOLE_ADDRESS));
+		assert!(!console.is_precompile(H160::repeat_byte(1)));
+	}
+}
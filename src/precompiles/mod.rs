@@ -0,0 +1,108 @@
+//! # Precompiles
+//!
+//! Precompile sets that can be passed to the stack executor.
+
+mod combinators;
+mod console;
+mod standard;
+
+pub use self::combinators::{Overrides, Prefixed};
+pub use self::console::{ConsoleLog, ConsoleSink, CONSOLE_ADDRESS};
+pub use self::standard::StandardPrecompiles;
+
+#[cfg(feature = "std")]
+pub use self::console::StdoutSink;
+
+#[cfg(test)]
+pub(crate) mod mock {
+	use crate::executor::stack::PrecompileHandle;
+	use crate::{Context, ExitError, ExitReason, ExitSucceed, Transfer};
+	use alloc::vec::Vec;
+	use primitive_types::{H160, H256, U256};
+
+	/// Handle for calling a precompile outside of an executor.
+	pub struct MockHandle {
+		pub code_address: H160,
+		pub input: Vec<u8>,
+		pub context: Context,
+		pub gas_used: u64,
+	}
+
+	impl MockHandle {
+		pub fn new(code_address: H160, input: Vec<u8>) -> Self {
+			Self {
+				code_address,
+				input,
+				context: Context {
+					address: code_address,
+					caller: H160::default(),
+					apparent_value: U256::zero(),
+				},
+				gas_used: 0,
+			}
+		}
+	}
+
+	impl PrecompileHandle for MockHandle {
+		fn call(
+			&mut self,
+			_: H160,
+			_: Option<Transfer>,
+			_: Vec<u8>,
+			_: Option<u64>,
+			_: bool,
+			_: &Context,
+		) -> (ExitReason, Vec<u8>) {
+			(ExitReason::Succeed(ExitSucceed::Returned), Vec::new())
+		}
+
+		fn record_cost(&mut self, cost: u64) -> Result<(), ExitError> {
+			self.gas_used += cost;
+			Ok(())
+		}
+
+		fn remaining_gas(&self) -> u64 {
+			u64::MAX - self.gas_used
+		}
+
+		fn log(&mut self, _: H160, _: Vec<H256>, _: Vec<u8>) -> Result<(), ExitError> {
+			Ok(())
+		}
+
+		fn balance(&self, _: H160) -> U256 {
+			U256::zero()
+		}
+
+		fn storage(&mut self, _: H160, _: H256) -> H256 {
+			H256::default()
+		}
+
+		fn set_storage(&mut self, _: H160, _: H256, _: H256) -> Result<(), ExitError> {
+			Ok(())
+		}
+
+		fn transfer(&mut self, _: Transfer) -> Result<(), ExitError> {
+			Ok(())
+		}
+
+		fn code_address(&self) -> H160 {
+			self.code_address
+		}
+
+		fn input(&self) -> &[u8] {
+			&self.input
+		}
+
+		fn context(&self) -> &Context {
+			&self.context
+		}
+
+		fn is_static(&self) -> bool {
+			false
+		}
+
+		fn gas_limit(&self) -> Option<u64> {
+			None
+		}
+	}
+}
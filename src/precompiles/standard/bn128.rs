@@ -0,0 +1,106 @@
+use super::{error, read_padded};
+use crate::ExitError;
+use alloc::vec::Vec;
+use bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+/// Bytes of one `(G1, G2)` pair in pairing input.
+const PAIR_LEN: usize = 192;
+
+pub fn add_cost(istanbul: bool) -> u64 {
+	if istanbul {
+		150
+	} else {
+		500
+	}
+}
+
+pub fn mul_cost(istanbul: bool) -> u64 {
+	if istanbul {
+		6_000
+	} else {
+		40_000
+	}
+}
+
+pub fn pairing_cost(input: &[u8], istanbul: bool) -> u64 {
+	let pairs = (input.len() / PAIR_LEN) as u64;
+	if istanbul {
+		45_000 + 34_000 * pairs
+	} else {
+		100_000 + 80_000 * pairs
+	}
+}
+
+/// Sum of two points on alt_bn128 (EIP-196).
+pub fn add(input: &[u8]) -> Result<Vec<u8>, ExitError> {
+	Ok(encode_g1(read_g1(input, 0)? + read_g1(input, 64)?))
+}
+
+/// Product of a point on alt_bn128 and a scalar (EIP-196).
+pub fn mul(input: &[u8]) -> Result<Vec<u8>, ExitError> {
+	let point = read_g1(input, 0)?;
+	let scalar =
+		Fr::from_slice(&read_padded(input, 64, 32)).map_err(|_| error("invalid scalar"))?;
+	Ok(encode_g1(point * scalar))
+}
+
+/// Whether the product of the pairings of each `(G1, G2)` pair is one
+/// (EIP-197).
+pub fn pairing(input: &[u8]) -> Result<Vec<u8>, ExitError> {
+	if input.len() % PAIR_LEN != 0 {
+		return Err(error("invalid pairing input length"));
+	}
+
+	let pairs = input
+		.chunks(PAIR_LEN)
+		.map(|pair| Ok((read_g1(pair, 0)?, read_g2(pair, 64)?)))
+		.collect::<Result<Vec<_>, ExitError>>()?;
+
+	let mut output = alloc::vec![0u8; 32];
+	if bn::pairing_batch(&pairs) == Gt::one() {
+		output[31] = 1;
+	}
+	Ok(output)
+}
+
+fn read_fq(input: &[u8], offset: usize) -> Result<Fq, ExitError> {
+	Fq::from_slice(&read_padded(input, offset, 32)).map_err(|_| error("invalid field element"))
+}
+
+fn read_g1(input: &[u8], offset: usize) -> Result<G1, ExitError> {
+	let x = read_fq(input, offset)?;
+	let y = read_fq(input, offset + 32)?;
+
+	if x.is_zero() && y.is_zero() {
+		Ok(G1::zero())
+	} else {
+		AffineG1::new(x, y)
+			.map(Into::into)
+			.map_err(|_| error("point not on curve"))
+	}
+}
+
+/// Read a point on the twisted curve, with coordinates encoded imaginary part
+/// first.
+fn read_g2(input: &[u8], offset: usize) -> Result<G2, ExitError> {
+	let x = Fq2::new(read_fq(input, offset + 32)?, read_fq(input, offset)?);
+	let y = Fq2::new(read_fq(input, offset + 96)?, read_fq(input, offset + 64)?);
+
+	if x.is_zero() && y.is_zero() {
+		Ok(G2::zero())
+	} else {
+		AffineG2::new(x, y)
+			.map(Into::into)
+			.map_err(|_| error("point not on curve"))
+	}
+}
+
+fn encode_g1(point: G1) -> Vec<u8> {
+	let mut output = alloc::vec![0u8; 64];
+	if let Some(point) = AffineG1::from_jacobian(point) {
+		// Both slices are exactly one field element long.
+		let _ = point.x().to_big_endian(&mut output[..32]);
+		let _ = point.y().to_big_endian(&mut output[32..]);
+	}
+	output
+}
@@ -0,0 +1,39 @@
+use super::read_padded;
+use alloc::vec::Vec;
+use libsecp256k1::{recover, Message, RecoveryId, Signature};
+use sha3::{Digest, Keccak256};
+
+pub const COST: u64 = 3000;
+
+/// Address of the key that signed a message hash, left padded to a word.
+/// Invalid signatures produce empty output rather than an error.
+pub fn ecrecover(input: &[u8]) -> Vec<u8> {
+	let input = read_padded(input, 0, 128);
+
+	// `v` is a full word that must be 27 or 28.
+	if input[32..63].iter().any(|b| *b != 0) || !(input[63] == 27 || input[63] == 28) {
+		return Vec::new();
+	}
+
+	let mut message = [0u8; 32];
+	message.copy_from_slice(&input[..32]);
+	let mut signature = [0u8; 64];
+	signature.copy_from_slice(&input[64..]);
+
+	let key = Signature::parse_standard(&signature)
+		.ok()
+		.and_then(|signature| {
+			let id = RecoveryId::parse(input[63] - 27).ok()?;
+			recover(&Message::parse(&message), &signature, &id).ok()
+		});
+
+	match key {
+		Some(key) => {
+			let hash = Keccak256::digest(&key.serialize()[1..]);
+			let mut output = alloc::vec![0u8; 32];
+			output[12..].copy_from_slice(&hash[12..]);
+			output
+		}
+		None => Vec::new(),
+	}
+}
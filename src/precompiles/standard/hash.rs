@@ -0,0 +1,15 @@
+use alloc::vec::Vec;
+use ripemd160::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// SHA-256 digest of the input.
+pub fn sha256(input: &[u8]) -> Vec<u8> {
+	Sha256::digest(input).to_vec()
+}
+
+/// RIPEMD-160 digest of the input, left padded to a word.
+pub fn ripemd160(input: &[u8]) -> Vec<u8> {
+	let mut output = alloc::vec![0u8; 32];
+	output[12..].copy_from_slice(&Ripemd160::digest(input));
+	output
+}
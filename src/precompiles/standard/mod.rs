@@ -0,0 +1,264 @@
+//! Precompiled contracts at addresses `0x01` to `0x09`, as specified by the
+//! Ethereum hard forks.
+
+mod blake2f;
+mod bn128;
+mod ecrecover;
+mod hash;
+mod modexp;
+
+use crate::executor::stack::{PrecompileHandle, PrecompileOutput, PrecompileResult, PrecompileSet};
+use crate::{Config, ExitError, ExitSucceed};
+use alloc::vec::Vec;
+use primitive_types::H160;
+
+/// Hard fork determining which precompiles exist and what they cost.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum Revision {
+	/// `ecrecover`, `sha256`, `ripemd160` and `identity`.
+	Frontier,
+	/// Adds `modexp` and the `bn128` curve operations.
+	Byzantium,
+	/// Adds `blake2f` and reprices `bn128` (EIP-1108).
+	Istanbul,
+	/// Reprices `modexp` (EIP-2565).
+	Berlin,
+}
+
+/// The standard precompiles of the hard fork described by a [`Config`].
+#[derive(Clone, Copy, Debug)]
+pub struct StandardPrecompiles {
+	revision: Revision,
+}
+
+impl StandardPrecompiles {
+	/// Precompiles active under `config`.
+	pub fn new(config: &Config) -> Self {
+		let revision = if config.increase_state_access_gas {
+			Revision::Berlin
+		} else if config.has_chain_id {
+			Revision::Istanbul
+		} else if config.has_revert {
+			Revision::Byzantium
+		} else {
+			Revision::Frontier
+		};
+
+		Self { revision }
+	}
+
+	/// Addresses of the active precompiles.
+	pub fn addresses(&self) -> Vec<H160> {
+		(1..=self.count()).map(H160::from_low_u64_be).collect()
+	}
+
+	fn count(&self) -> u64 {
+		match self.revision {
+			Revision::Frontier => 4,
+			Revision::Byzantium => 8,
+			Revision::Istanbul | Revision::Berlin => 9,
+		}
+	}
+
+	fn index(&self, address: H160) -> Option<u8> {
+		if address.as_bytes()[..19].iter().any(|b| *b != 0) {
+			return None;
+		}
+
+		let index = address.as_bytes()[19];
+		if index >= 1 && u64::from(index) <= self.count() {
+			Some(index)
+		} else {
+			None
+		}
+	}
+
+	fn cost(&self, index: u8, input: &[u8]) -> Result<u64, ExitError> {
+		let istanbul = self.revision >= Revision::Istanbul;
+
+		Ok(match index {
+			1 => ecrecover::COST,
+			2 => linear_cost(input, 60, 12),
+			3 => linear_cost(input, 600, 120),
+			4 => linear_cost(input, 15, 3),
+			5 => modexp::cost(input, self.revision >= Revision::Berlin),
+			6 => bn128::add_cost(istanbul),
+			7 => bn128::mul_cost(istanbul),
+			8 => bn128::pairing_cost(input, istanbul),
+			9 => blake2f::cost(input)?,
+			_ => unreachable!("precompile index is checked"),
+		})
+	}
+
+	fn call(&self, index: u8, input: &[u8]) -> Result<Vec<u8>, ExitError> {
+		match index {
+			1 => Ok(ecrecover::ecrecover(input)),
+			2 => Ok(hash::sha256(input)),
+			3 => Ok(hash::ripemd160(input)),
+			4 => Ok(input.to_vec()),
+			5 => modexp::modexp(input),
+			6 => bn128::add(input),
+			7 => bn128::mul(input),
+			8 => bn128::pairing(input),
+			9 => blake2f::blake2f(input),
+			_ => unreachable!("precompile index is checked"),
+		}
+	}
+}
+
+impl PrecompileSet for StandardPrecompiles {
+	fn execute(&self, handle: &mut dyn PrecompileHandle) -> Option<PrecompileResult> {
+		let index = self.index(handle.code_address())?;
+
+		let result = self.cost(index, handle.input()).and_then(|cost| {
+			handle.record_cost(cost)?;
+			self.call(index, handle.input())
+		});
+
+		Some(
+			result
+				.map(|output| PrecompileOutput {
+					exit_status: ExitSucceed::Returned,
+					output,
+				})
+				.map_err(Into::into),
+		)
+	}
+
+	fn is_precompile(&self, address: H160) -> bool {
+		self.index(address).is_some()
+	}
+}
+
+/// `base` plus `word` for every started 32 byte word of input.
+fn linear_cost(input: &[u8], base: u64, word: u64) -> u64 {
+	let words = input.len() / 32 + usize::from(input.len() % 32 != 0);
+	base.saturating_add(word.saturating_mul(words as u64))
+}
+
+/// `len` bytes of `input` from `offset`, right padded with zeros.
+fn read_padded(input: &[u8], offset: usize, len: usize) -> Vec<u8> {
+	let mut out = alloc::vec![0u8; len];
+	if offset < input.len() {
+		let available = &input[offset..];
+		let n = len.min(available.len());
+		out[..n].copy_from_slice(&available[..n]);
+	}
+	out
+}
+
+fn error(message: &'static str) -> ExitError {
+	ExitError::Other(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::precompiles::mock::MockHandle;
+
+	fn run(config: &Config, index: u64, input: &str) -> (u64, PrecompileResult) {
+		let mut handle = MockHandle::new(H160::from_low_u64_be(index), hex::decode(input).unwrap());
+		let result = StandardPrecompiles::new(config)
+			.execute(&mut handle)
+			.unwrap();
+		(handle.gas_used, result)
+	}
+
+	fn output(config: &Config, index: u64, input: &str) -> (u64, String) {
+		let (cost, result) = run(config, index, input);
+		(cost, hex::encode(result.ok().unwrap().output))
+	}
+
+	#[test]
+	fn test_fork_selection() {
+		assert_eq!(
+			StandardPrecompiles::new(&Config::frontier())
+				.addresses()
+				.len(),
+			4
+		);
+		assert_eq!(
+			StandardPrecompiles::new(&Config::istanbul())
+				.addresses()
+				.len(),
+			9
+		);
+		assert!(
+			!StandardPrecompiles::new(&Config::frontier()).is_precompile(H160::from_low_u64_be(5))
+		);
+		assert!(
+			!StandardPrecompiles::new(&Config::london()).is_precompile(H160::from_low_u64_be(10))
+		);
+	}
+
+	#[test]
+	fn test_hashes() {
+		let config = Config::istanbul();
+		assert_eq!(
+			output(&config, 1, "18c547e4f7b0f325ad1e56f57e26c745b09a3e503d86e00e5255ff7f715d3d1c000000000000000000000000000000000000000000000000000000000000001c73b1693892219d736caba55bdb67216e485557ea6b6af75f37096c9aa6a5a75feeb940b1d03b21e36b0e47e79769f095fe2ab855bd91e3a38756b7d75a9c4549"),
+			(3000, "000000000000000000000000a94f5374fce5edbc8e2a8697c15331677e6ebf0b".into())
+		);
+		assert_eq!(
+			output(&config, 2, ""),
+			(
+				60,
+				"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".into()
+			)
+		);
+		assert_eq!(
+			output(&config, 3, ""),
+			(
+				600,
+				"0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31".into()
+			)
+		);
+		assert_eq!(output(&config, 4, "abcdef"), (18, "abcdef".into()));
+	}
+
+	#[test]
+	fn test_modexp() {
+		// 3 ** 5 % 7
+		let input = "0000000000000000000000000000000000000000000000000000000000000001\
+			0000000000000000000000000000000000000000000000000000000000000001\
+			0000000000000000000000000000000000000000000000000000000000000001\
+			030507";
+		assert_eq!(output(&Config::istanbul(), 5, input), (0, "05".into()));
+		assert_eq!(output(&Config::berlin(), 5, input), (200, "05".into()));
+	}
+
+	#[test]
+	fn test_bn128() {
+		let g1 = "0000000000000000000000000000000000000000000000000000000000000001\
+			0000000000000000000000000000000000000000000000000000000000000002";
+		let double = "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+			15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4";
+		let two = "0000000000000000000000000000000000000000000000000000000000000002";
+
+		assert_eq!(
+			output(&Config::istanbul(), 6, &format!("{}{}", g1, g1)),
+			(150, double.into())
+		);
+		assert_eq!(
+			output(&Config::istanbul(), 7, &format!("{}{}", g1, two)),
+			(6000, double.into())
+		);
+		assert_eq!(
+			output(&Config::istanbul(), 8, ""),
+			(45000, format!("{:064x}", 1))
+		);
+		assert!(run(&Config::istanbul(), 6, &format!("{}{}", g1, two))
+			.1
+			.is_err());
+	}
+
+	#[test]
+	fn test_blake2f() {
+		// EIP-152 test vector 5.
+		let input = "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001";
+		assert_eq!(
+			output(&Config::istanbul(), 9, input),
+			(12, "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923".into())
+		);
+		assert!(run(&Config::istanbul(), 9, "00").1.is_err());
+	}
+}
@@ -0,0 +1,84 @@
+use super::{error, read_padded};
+use crate::ExitError;
+use alloc::vec::Vec;
+use core::cmp::max;
+use core::convert::TryFrom;
+use num_bigint::BigUint;
+use primitive_types::U256;
+
+/// Gas cost of `modexp`, per EIP-198, or EIP-2565 when `berlin` is set.
+pub fn cost(input: &[u8], berlin: bool) -> u64 {
+	let (base_len, exp_len, mod_len) = lengths(input);
+	let max_len = max(base_len, mod_len);
+	if max_len > U256::from(u32::MAX) {
+		return u64::MAX;
+	}
+
+	// Only the first word of the exponent affects the price.
+	let head_len = exp_len.min(U256::from(32)).as_usize();
+	let head = U256::from_big_endian(&read_padded(input, 96 + base_len.as_usize(), head_len));
+	let head_bits = U256::from(head.bits()).saturating_sub(U256::one());
+	let iterations = if exp_len <= U256::from(32) {
+		head_bits
+	} else {
+		(exp_len - 32)
+			.saturating_mul(U256::from(8))
+			.saturating_add(head_bits)
+	};
+	let iterations = max(iterations, U256::one());
+
+	let gas = if berlin {
+		let words = (max_len + 7) / 8;
+		max(
+			(words * words).saturating_mul(iterations) / 3,
+			U256::from(200),
+		)
+	} else {
+		let x = max_len;
+		let complexity = if x <= U256::from(64) {
+			x * x
+		} else if x <= U256::from(1024) {
+			x * x / 4 + x * 96 - 3072
+		} else {
+			x * x / 16 + x * 480 - 199_680
+		};
+		complexity.saturating_mul(iterations) / 20
+	};
+
+	u64::try_from(gas).unwrap_or(u64::MAX)
+}
+
+/// `base ** exp % modulus`, left padded to the length of the modulus.
+pub fn modexp(input: &[u8]) -> Result<Vec<u8>, ExitError> {
+	let (base_len, exp_len, mod_len) = lengths(input);
+	if mod_len.is_zero() {
+		return Ok(Vec::new());
+	}
+
+	let limit = U256::from(u32::MAX);
+	if base_len > limit || exp_len > limit || mod_len > limit {
+		return Err(error("modexp input too large"));
+	}
+	let (base_len, exp_len, mod_len) =
+		(base_len.as_usize(), exp_len.as_usize(), mod_len.as_usize());
+
+	let base = BigUint::from_bytes_be(&read_padded(input, 96, base_len));
+	let exp = BigUint::from_bytes_be(&read_padded(input, 96 + base_len, exp_len));
+	let modulus = BigUint::from_bytes_be(&read_padded(input, 96 + base_len + exp_len, mod_len));
+
+	let mut output = alloc::vec![0u8; mod_len];
+	if modulus != BigUint::from(0u8) {
+		let result = base.modpow(&exp, &modulus).to_bytes_be();
+		output[mod_len - result.len()..].copy_from_slice(&result);
+	}
+	Ok(output)
+}
+
+fn lengths(input: &[u8]) -> (U256, U256, U256) {
+	let header = read_padded(input, 0, 96);
+	(
+		U256::from_big_endian(&header[..32]),
+		U256::from_big_endian(&header[32..64]),
+		U256::from_big_endian(&header[64..]),
+	)
+}
@@ -1,36 +1,34 @@
-
-
+use evm::abi::{Contract, Function};
+use evm::backend::fork::{ForkBackend, Upstream, UpstreamError};
 use evm::backend::sql::{MemoryAccount, MemoryBackend, MemoryVicinity};
 // use evm::backend::memory::{MemoryAccount, MemoryBackend, MemoryVicinity};
 use std::fs;
-use evm::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata, StackState};
+use evm::executor::stack::{create_access_list, MemoryStackState, StackExecutor, StackSubstateMetadata, StackState};
 use evm::executor::{Executor};
-use evm::Config;
-use primitive_types::{H160, U256};
+use evm::precompiles::{ConsoleLog, ConsoleSink, StandardPrecompiles, StdoutSink};
+use evm::rpc::{self, Node};
+#[cfg(feature = "with-serde")]
+use evm::config;
+use evm::{Config, CreateScheme, ExitFatal, ExitReason};
+use primitive_types::{H160, H256, U256};
 use std::fmt::Debug;
 use std::{collections::BTreeMap, str::FromStr};
-use evm::backend::ApplyBackend;
+use evm::backend::{ApplyBackend, Backend, Log};
 use std::env;
 use std::path::Path;
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::BufReader;
+use std::cell::RefCell;
+use std::net::SocketAddr;
 
 // Backend
 const DATABASE_FILE: &str = "file:chain.sqlite?cache=shared";
 const VERSION: &str = "0.0.2";
 
 
-fn execute_in_vm(
-	params: SendTransactionParams,
-	write: bool,
-	output_file: &Path,
-	db_path: &Path,
-	state_leaves_file: &Path,
-) {
-
-	let config = Config::istanbul();
-
-	let vicinity = MemoryVicinity {
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
 		gas_price: U256::zero(),
 		origin: H160::default(),
 		block_hashes: Vec::new(),
@@ -38,11 +36,21 @@ fn execute_in_vm(
 		block_coinbase: Default::default(),
 		block_timestamp: Default::default(),
 		block_difficulty: Default::default(),
+		block_prev_randao: Default::default(),
 		block_gas_limit: Default::default(),
 		chain_id: U256::one(),
 		block_base_fee_per_gas: U256::zero(),
-	};
-	
+		block_blob_base_fee: U256::zero(),
+		blob_hashes: Vec::new(),
+	}
+}
+
+/// Account funded by `DB_GENESIS`, unlocked by `--serve`.
+fn genesis_account() -> H160 {
+	H160::from_str("0xf000000000000000000000000000000000000000").unwrap()
+}
+
+fn open_backend<'vicinity>(vicinity: &'vicinity MemoryVicinity, db_path: &Path) -> MemoryBackend<'vicinity> {
 	let mut bstate = BTreeMap::new();
 	
 	let db_genesis = match env::var("DB_GENESIS") {
@@ -55,7 +63,7 @@ fn execute_in_vm(
 
 	if db_genesis {
 		bstate.insert(
-			H160::from_str("0xf000000000000000000000000000000000000000").unwrap(),
+			genesis_account(),
 			MemoryAccount {
 				nonce: U256::one(),
 				balance: U256::from(10000000),
@@ -65,19 +73,95 @@ fn execute_in_vm(
 		);
 	}
 
-	println!("quarkevm version {}", VERSION);
+	MemoryBackend::new(vicinity, bstate, db_path.to_str().unwrap().to_string(), db_genesis)
+	// MemoryBackend::new(&vicinity, bstate)
+}
 
-	let mut backend = MemoryBackend::new(&vicinity, bstate, db_path.to_str().unwrap().to_string(), db_genesis);
-	// let mut backend = MemoryBackend::new(&vicinity, bstate);
-	let metadata = StackSubstateMetadata::new(u64::MAX, &config);
-	let state = MemoryStackState::new(metadata, &backend);
-	let precompiles = BTreeMap::new();
-	let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+/// The config of the chain: that of `--config`, which is stored in the DB for
+/// later runs, else that of `--hardfork`, else the one stored in the DB, else
+/// Istanbul.
+#[cfg(feature = "with-serde")]
+fn chain_config(args: &Args, vicinity: &MemoryVicinity) -> std::result::Result<Config, String> {
+	let backend = MemoryBackend::new(vicinity, BTreeMap::new(), args.db_path.to_str().unwrap().to_string(), false);
+	if let Some(path) = &args.config {
+		let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+		let config = match path.extension().and_then(|extension| extension.to_str()) {
+			Some("toml") => config::from_toml(&contents),
+			_ => config::from_json(&contents),
+		}
+		.map_err(|e| e.to_string())?;
+		config::store(&backend, &config);
+		return Ok(config);
+	}
+	if let Some(hardfork) = args.hardfork {
+		return Ok(hardfork.config());
+	}
+	let config = config::load(&backend).map_err(|e| e.to_string())?;
+	Ok(config.unwrap_or_else(Config::istanbul))
+}
+
+/// The config of `--hardfork`, else Istanbul. Reading configs from files and
+/// the DB requires the `with-serde` feature.
+#[cfg(not(feature = "with-serde"))]
+fn chain_config(args: &Args, _vicinity: &MemoryVicinity) -> std::result::Result<Config, String> {
+	Ok(args.hardfork.map_or_else(Config::istanbul, Hardfork::config))
+}
+
+/// A backend persisted in the SQLite database.
+trait Database: Backend + ApplyBackend {
+	fn sql(&self) -> &MemoryBackend<'_>;
+
+	/// Error of a fetch from the node the state is forked from, since the
+	/// last call.
+	fn take_upstream_error(&self) -> Option<UpstreamError> {
+		None
+	}
+}
+
+impl Database for MemoryBackend<'_> {
+	fn sql(&self) -> &MemoryBackend<'_> {
+		self
+	}
+}
 
-	if params.to == "" {
+impl Database for ForkBackend<'_> {
+	fn sql(&self) -> &MemoryBackend<'_> {
+		self.overlay()
+	}
+
+	fn take_upstream_error(&self) -> Option<UpstreamError> {
+		self.take_error()
+	}
+}
+
+/// Outcome of a single transaction.
+struct Outcome {
+	reason: ExitReason,
+	output: Vec<u8>,
+	contract_address: Option<H160>,
+	accessed_addresses: Vec<H160>,
+	accessed_storage: Vec<(H160, H256)>,
+	logs: Vec<Log>,
+}
+
+fn transact<B: Database>(
+	backend: &mut B,
+	config: &Config,
+	params: &SendTransactionParams,
+	write: bool,
+	console: &dyn ConsoleSink,
+) -> Outcome {
+	let metadata = StackSubstateMetadata::new(u64::MAX, config);
+	let state = MemoryStackState::new(metadata, backend);
+	let precompiles = ConsoleLog::new(StandardPrecompiles::new(config), console);
+	let mut executor = StackExecutor::new_with_precompiles(state, config, &precompiles);
+
+	let caller = H160::from_str(&params.from).unwrap();
+	let (mut reason, output, contract_address) = if params.to.is_empty() {
 		// Create call.
-		let _reason = executor.transact_create(
-			H160::from_str(&params.from).unwrap(),
+		let address = executor.create_address(CreateScheme::Legacy { caller });
+		let (reason, output) = executor.transact_create(
+			caller,
 			// H160::from_str(&params.value).unwrap(),
 			U256::zero(), // value
 			hex::decode(&params.data)
@@ -85,12 +169,11 @@ fn execute_in_vm(
 			100000000000u64, // gas limit
 			Vec::new(), // access list
 		);
-		
-		println!("{:?}", _reason);
-
+		let address = if reason.is_succeed() { Some(address) } else { None };
+		(reason, output, address)
 	} else {
-		let (_reason, output) = executor.transact_call(
-			H160::from_str(&params.from).unwrap(),
+		let (reason, output) = executor.transact_call(
+			caller,
 			H160::from_str(&params.to).unwrap(),
 			U256::zero(), // value
 			hex::decode(&params.data)
@@ -98,22 +181,79 @@ fn execute_in_vm(
 			100000000000u64, // gas limit
 			Vec::new(),
 		);
-		let mut file = File::create(output_file).unwrap();
-		file.write_all(&output).unwrap();
-	}
+		(reason, output, None)
+	};
 
 	let state = executor.into_state();
 	
 	let accessed = state.metadata().accessed().as_ref().unwrap();
-	
-	let ad = accessed
+	let accessed_addresses = accessed.accessed_addresses.iter().cloned().collect();
+	let accessed_storage = accessed.accessed_storage.iter().cloned().collect();
+
+	let (applies, logs) = state.deconstruct();
+	let logs = logs.into_iter().collect::<Vec<Log>>();
+
+	// Values that could not be fetched were read as zero, so the outcome is
+	// not applied.
+	match backend.take_upstream_error() {
+		Some(error) => {
+			let message = format!("failed to fetch from upstream: {:?}", error);
+			reason = ExitReason::Fatal(ExitFatal::Other(message.into()));
+		}
+		None if write => backend.apply(applies, logs.clone(), false),
+		None => (),
+	}
+
+	Outcome {
+		reason,
+		output,
+		contract_address,
+		accessed_addresses,
+		accessed_storage,
+		logs,
+	}
+}
+
+#[allow(clippy::too_many_arguments)]
+fn execute_in_vm<B: Database>(
+	backend: &mut B,
+	params: SendTransactionParams,
+	write: bool,
+	output_file: &Path,
+	state_leaves_file: &Path,
+	function: Option<&Function>,
+	contract: Option<&Contract>,
+	config: &Config,
+) {
+	println!("quarkevm version {}", VERSION);
+
+	let outcome = transact(backend, config, &params, write, &StdoutSink);
+
+	if params.to.is_empty() {
+		println!("contract created {:?}", outcome.contract_address);
+		println!("{:?}", (&outcome.reason, &outcome.output));
+	} else {
+		let mut file = File::create(output_file).unwrap();
+		file.write_all(&outcome.output).unwrap();
+
+		if let Some(function) = function.filter(|f| !f.outputs.is_empty()) {
+			if outcome.reason.is_succeed() {
+				match function.decode_output(&outcome.output) {
+					Ok(tokens) => tokens.iter().for_each(|t| println!("{}", t)),
+					Err(e) => println!("Failed to decode output: {}", e),
+				}
+			}
+		}
+	}
+
+	let ad = outcome
 		.accessed_addresses
 		.iter()
-		.map(|a| format!("address {:?}", H160(a.0)))
+		.map(|a| format!("address {:?}", a))
 		.collect::<Vec<String>>()
 		.join("\n");
 	
-	let st = accessed
+	let st = outcome
 		.accessed_storage
 		.iter()
 		.map(|a| format!("storage {:?} {:?}", a.0, a.1))
@@ -125,30 +265,163 @@ fn execute_in_vm(
 	let state_leaves_content = format!("{}\n{}", ad, st);
 	fs::write(state_leaves_file, state_leaves_content).expect("Unable to write file");
 
-	dbg!("{}", &accessed.accessed_addresses, &accessed.accessed_storage);
+	dbg!("{}", &outcome.accessed_addresses, &outcome.accessed_storage);
+
+	if let Some(contract) = contract {
+		for log in &outcome.logs {
+			match contract.decode_log(log) {
+				Some((event, values)) => {
+					let values = values
+						.iter()
+						.map(|(name, value)| format!("{}={}", name, value))
+						.collect::<Vec<String>>()
+						.join(", ");
+					println!("log {:?} {}({})", log.address, event.name, values);
+				}
+				None => println!("log {:?} {:?}", log.address, log.topics),
+			}
+		}
+	}
 
-	// let mut file = File::create(state_leaves_file).unwrap();
-	// file.write_all(&output).unwrap();
+	if write {
+		println!("Applying updates");
+	}
 
-	let (applies, logs) = state.deconstruct();
+	println!("Done!");
+}
 
-	// let mut file = File::create(output_file).unwrap();
-	// file.write_all(&output).unwrap();
+/// Print the EIP-2930 access list of a transaction as JSON, without executing
+/// it against the database. Returns `false` if state could not be fetched.
+fn print_access_list<B: Database>(backend: &B, params: &SendTransactionParams, config: &Config) -> bool {
+	let console = RefCell::new(Vec::new());
+	let precompiles = ConsoleLog::new(StandardPrecompiles::new(config), &console);
 
-	// dbg!(&logs.into_iter()
-    //     .map(|item| format!("{item}"))
-    //     .collect::<String>());
+	let to = if params.to.is_empty() {
+		None
+	} else {
+		Some(H160::from_str(&params.to).unwrap())
+	};
+	let result = create_access_list(
+		backend,
+		config,
+		&precompiles,
+		H160::from_str(&params.from).unwrap(),
+		to,
+		U256::zero(), // value
+		hex::decode(&params.data).unwrap(),
+		100000000000u64, // gas limit
+	);
+	if let Some(error) = backend.take_upstream_error() {
+		eprintln!("failed to fetch from upstream: {:?}", error);
+		return false;
+	}
 
-	if write {
-		println!("Applying updates");
-		backend.apply(applies, logs, false);
+	println!("{}", json!({
+		"status": format!("{:?}", result.exit_reason),
+		"success": result.exit_reason.is_succeed(),
+		"accessList": rpc::access_list_json(&result.access_list),
+		"gasUsed": result.gas_used,
+		"gasUsedWithoutAccessList": result.gas_used_without_access_list,
+	}));
+	true
+}
+
+/// The rules transactions are executed under.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum Hardfork {
+	Istanbul,
+	Berlin,
+	London,
+	/// Paris, where `DIFFICULTY` became `PREVRANDAO`.
+	Merge,
+	Shanghai,
+	Cancun,
+}
+
+impl Hardfork {
+	fn config(self) -> Config {
+		match self {
+			Hardfork::Istanbul => Config::istanbul(),
+			Hardfork::Berlin => Config::berlin(),
+			Hardfork::London => Config::london(),
+			Hardfork::Merge => Config::merge(),
+			Hardfork::Shanghai => Config::shanghai(),
+			Hardfork::Cancun => Config::cancun(),
+		}
 	}
+}
 
-	println!("Done!");
+/// When a batch persists its changes to the database.
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq)]
+enum CommitMode {
+	/// Commit after every transaction.
+	Each,
+	/// Commit once, after the last transaction.
+	End,
 }
 
+/// Execute one transaction per line of `input`, writing one JSON result per
+/// line to stdout. Later transactions see the changes of earlier ones. Without
+/// `write`, all changes are rolled back at the end.
+fn execute_batch<B: Database>(backend: &mut B, input: &mut dyn BufRead, write: bool, commit: CommitMode, config: &Config) {
+	let transactional = !write || commit == CommitMode::End;
+	if transactional {
+		backend.sql().begin();
+	}
+
+	let stdout = std::io::stdout();
+	let mut stdout = stdout.lock();
+	for line in input.lines() {
+		let line = line.unwrap();
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let result = match parse_params(&line) {
+			Ok(params) => {
+				let console = RefCell::new(Vec::new());
+				let outcome = transact(backend, config, &params, true, &console);
+				let mut result = outcome_json(&outcome);
+				result["console"] = json!(console.into_inner());
+				result
+			}
+			Err(e) => json!({ "error": e.to_string() }),
+		};
+		writeln!(stdout, "{}", result).unwrap();
+	}
 
+	if transactional {
+		if write {
+			backend.sql().commit();
+		} else {
+			backend.sql().rollback();
+		}
+	}
+}
 
+fn outcome_json(outcome: &Outcome) -> serde_json::Value {
+	let logs = outcome
+		.logs
+		.iter()
+		.map(|log| {
+			json!({
+				"address": format!("{:?}", log.address),
+				"topics": log.topics.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
+				"data": format!("0x{}", hex::encode(&log.data)),
+			})
+		})
+		.collect::<Vec<_>>();
+
+	json!({
+		"status": format!("{:?}", outcome.reason),
+		"success": outcome.reason.is_succeed(),
+		"output": format!("0x{}", hex::encode(&outcome.output)),
+		"contractAddress": outcome.contract_address.map(|a| format!("{:?}", a)),
+		"logs": logs,
+		"accessedAddresses": outcome.accessed_addresses.iter().map(|a| format!("{:?}", a)).collect::<Vec<_>>(),
+		"accessedStorage": outcome.accessed_storage.iter().map(|(a, i)| [format!("{:?}", a), format!("{:?}", i)]).collect::<Vec<_>>(),
+	})
+}
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -161,12 +434,14 @@ pub struct SendTransactionParams {
 	gas_price: Option<String>,
 	
 	value: Option<String>,
+
+	#[serde(default)]
 	data: String
 }
 
 use std::{path::PathBuf};
 use serde::{Deserialize, Serialize};
-use serde_json::{Result};
+use serde_json::{json, Result};
 use clap::{Parser, ValueHint};
 
 #[derive(Parser, Debug)]
@@ -182,16 +457,18 @@ struct Args {
     #[clap(
         help = "A path to write the raw output.",
         long,
-        value_hint = ValueHint::FilePath
+        value_hint = ValueHint::FilePath,
+        required_unless_present_any = &["batch", "serve", "access-list"]
     )]
-    pub output_file: PathBuf,
+    pub output_file: Option<PathBuf>,
 
     #[clap(
         help = "A path to write the state leaves accessed during a tx.",
         long,
-        value_hint = ValueHint::FilePath
+        value_hint = ValueHint::FilePath,
+        required_unless_present_any = &["batch", "serve", "access-list"]
     )]
-    pub state_leaves_file: PathBuf,
+    pub state_leaves_file: Option<PathBuf>,
 
     #[clap(
         help = "If present, will flush writes to the DB.",
@@ -199,25 +476,236 @@ struct Args {
     )]
     pub write: bool,
 
-    #[clap(short, long)]
-    pub data: String,
+    #[clap(
+        help = "Print the EIP-2930 access list of the transaction as JSON, with the gas used with and without it, instead of executing it. Nothing is written to the DB. Requires --hardfork berlin or later.",
+        long,
+        conflicts_with_all = &["batch", "write"]
+    )]
+    pub access_list: bool,
+
+    #[clap(short, long, required_unless_present_any = &["batch", "serve"])]
+    pub data: Option<String>,
+
+    #[clap(
+        help = "A function signature to encode calldata with, e.g. \"transfer(address,uint256)\". Return types may follow, as in \"balanceOf(address)(uint256)\". With --abi, a function name is enough. For contract creation, the encoded arguments are appended to the init code.",
+        long
+    )]
+    pub sig: Option<String>,
+
+    #[clap(
+        help = "The arguments for --sig.",
+        long,
+        multiple_values = true,
+        allow_hyphen_values = true
+    )]
+    pub args: Vec<String>,
+
+    #[clap(
+        help = "A path to a contract ABI JSON, used to decode return data and emitted logs.",
+        long,
+        value_hint = ValueHint::FilePath
+    )]
+    pub abi: Option<PathBuf>,
+
+    #[clap(
+        help = "A path to a file of transactions to execute in order, one JSON object per line, or - for stdin. Prints one JSON result per line.",
+        long,
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = &["data", "sig"]
+    )]
+    pub batch: Option<PathBuf>,
+
+    #[clap(
+        help = "When a batch commits to the DB. Without --write, the batch is rolled back at the end.",
+        long,
+        arg_enum,
+        default_value = "each"
+    )]
+    pub commit: CommitMode,
+
+    #[clap(
+        help = "The hardfork whose rules transactions are executed under. Defaults to the config stored in the DB, or istanbul.",
+        long,
+        arg_enum
+    )]
+    pub hardfork: Option<Hardfork>,
+
+    #[cfg(feature = "with-serde")]
+    #[clap(
+        help = "A path to a JSON or TOML config file, with a base hardfork and the fields it overrides. The config is stored in the DB and used by later runs.",
+        long,
+        value_hint = ValueHint::FilePath,
+        conflicts_with = "hardfork"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[clap(
+        help = "The randomness returned by block.prevrandao, as 32 bytes of hex. Defaults to zero. When serving, each mined block derives its randomness from the previous one. Requires --hardfork merge or later, before which the opcode returns the difficulty.",
+        long
+    )]
+    pub prevrandao: Option<H256>,
+
+    #[clap(
+        help = "The URL of a JSON-RPC node to fork state from. Fetched state is cached in the DB, and writes stay local.",
+        long,
+        value_hint = ValueHint::Url
+    )]
+    pub fork_url: Option<String>,
+
+    #[clap(
+        help = "The block number to fork at. Defaults to the latest block.",
+        long,
+        requires = "fork-url"
+    )]
+    pub fork_block: Option<u64>,
+
+    #[clap(
+        help = "Serve the JSON-RPC API at this address, e.g. 127.0.0.1:8545.",
+        long,
+        conflicts_with_all = &["data", "batch", "fork-url"]
+    )]
+    pub serve: Option<SocketAddr>,
+}
+
+fn parse_params(data: &str) -> Result<SendTransactionParams> {
+	let mut params: SendTransactionParams = serde_json::from_str(data)?;
+	params.from = params.from.strip_prefix("0x").unwrap_or(&params.from).to_string();
+	params.to = params.to.strip_prefix("0x").unwrap_or(&params.to).to_string();
+	params.data = params.data.strip_prefix("0x").unwrap_or(&params.data).to_string();
+	Ok(params)
 }
 
 fn run() -> Result<u8> {
 	let args = Args::parse();
+	let mut vicinity = vicinity();
+	let config = match chain_config(&args, &vicinity) {
+		Ok(config) => config,
+		Err(e) => {
+			eprintln!("{}", e);
+			return Ok(2);
+		}
+	};
+	if let Some(prevrandao) = args.prevrandao {
+		if !config.has_prevrandao {
+			eprintln!("--prevrandao requires --hardfork merge or later");
+			return Ok(2);
+		}
+		vicinity.block_prev_randao = prevrandao;
+	}
+
+	if let Some(address) = args.serve {
+		let node = Node::new(args.db_path.to_str().unwrap().to_string(), vicinity, config)
+			.with_accounts(vec![genesis_account()]);
+		let server = rpc::serve(node, &address).unwrap();
+		println!("Listening on {}", address);
+		server.wait();
+		return Ok(0);
+	}
+
+	match args.fork_url.clone() {
+		Some(url) => {
+			let upstream = Upstream::new(url);
+			let block = args.fork_block.unwrap_or_else(|| upstream.block_number().unwrap());
+			vicinity.chain_id = upstream.chain_id().unwrap();
+			vicinity.block_number = U256::from(block + 1);
+
+			let mut backend = ForkBackend::new(&vicinity, args.db_path.to_str().unwrap().to_string(), upstream, block);
+			run_with(&mut backend, args, &config)
+		}
+		None => {
+			let mut backend = open_backend(&vicinity, &args.db_path);
+			run_with(&mut backend, args, &config)
+		}
+	}
+}
+
+fn run_with<B: Database>(backend: &mut B, args: Args, config: &Config) -> Result<u8> {
+	if let Some(batch) = args.batch {
+		if batch.as_os_str() == "-" {
+			execute_batch(backend, &mut std::io::stdin().lock(), args.write, args.commit, config);
+		} else {
+			let mut input = BufReader::new(File::open(batch).unwrap());
+			execute_batch(backend, &mut input, args.write, args.commit, config);
+		}
+		return Ok(0);
+	}
 
 	// Decode args.
-	let mut params: SendTransactionParams = serde_json::from_str(&args.data)?;
-	params.from = params.from.strip_prefix("0x").unwrap().to_string();
-	params.to = params.to.strip_prefix("0x").unwrap().to_string();
-	params.data = params.data.strip_prefix("0x").unwrap().to_string();
+	let mut params = parse_params(&args.data.unwrap())?;
+
+	let contract = match args.abi {
+		Some(path) => {
+			let json = match fs::read_to_string(&path) {
+				Ok(json) => json,
+				Err(e) => {
+					eprintln!("{}: {}", path.display(), e);
+					return Ok(2);
+				}
+			};
+			match Contract::load(&json) {
+				Ok(contract) => Some(contract),
+				Err(e) => {
+					eprintln!("{}: {}", path.display(), e);
+					return Ok(2);
+				}
+			}
+		}
+		None => None,
+	};
+	// Encode calldata from the signature.
+	let function = match args.sig {
+		Some(sig) => Some(match (Function::parse(&sig), &contract) {
+			// Without return types, take them from the ABI entry of the
+			// same signature.
+			(Ok(function), Some(contract)) if function.outputs.is_empty() => contract
+				.function(&function.signature())
+				.cloned()
+				.unwrap_or(function),
+			(Ok(function), _) => function,
+			(Err(_), Some(contract)) => match contract.function(&sig) {
+				Some(function) => function.clone(),
+				None => {
+					eprintln!("function {} not found in ABI", sig);
+					return Ok(2);
+				}
+			},
+			(Err(e), None) => {
+				eprintln!("{}", e);
+				return Ok(2);
+			}
+		}),
+		None => None,
+	};
+	if let Some(function) = &function {
+		let tokens = match function.parse_inputs(&args.args) {
+			Ok(tokens) => tokens,
+			Err(e) => {
+				eprintln!("{}", e);
+				return Ok(2);
+			}
+		};
+		if params.to.is_empty() {
+			params.data += &hex::encode(evm::abi::encode(&tokens));
+		} else {
+			params.data = hex::encode(function.encode_input(&tokens).unwrap());
+		}
+	}
+
+	if args.access_list {
+		// Before Berlin, access lists have no effect on gas.
+		if !config.increase_state_access_gas {
+			eprintln!("--access-list requires --hardfork berlin or later");
+			return Ok(2);
+		}
+		return Ok(if print_access_list(backend, &params, config) { 0 } else { 2 });
+	}
 
 	// Execute.
-	execute_in_vm(params, args.write, &args.output_file, &args.db_path.into_boxed_path(), &args.state_leaves_file);
+	execute_in_vm(backend, params, args.write, &args.output_file.unwrap(), &args.state_leaves_file.unwrap(), function.as_ref(), contract.as_ref(), config);
 
 	Ok(0)
 }
 
 fn main() {
-    run().unwrap();
+    std::process::exit(run().unwrap().into());
 }
@@ -0,0 +1,892 @@
+//! # JSON-RPC server
+//!
+//! Ethereum JSON-RPC node over the SQLite backend, for use with the standard
+//! JS tooling.
+
+use crate::backend::sql::{MemoryBackend, MemoryVicinity};
+use crate::backend::{Apply, ApplyBackend, Backend, Basic, Log};
+use crate::executor::stack::{
+	create_access_list, AccessList, MemoryStackState, StackExecutor, StackSubstateMetadata,
+};
+use crate::executor::Executor;
+use crate::precompiles::{ConsoleLog, ConsoleSink, StandardPrecompiles, StdoutSink};
+use crate::{Config, CreateScheme, ExitReason};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::cmp::max;
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_http_server::{Server, ServerBuilder};
+use primitive_types::{H160, H256, U256};
+use serde_json::{json, Value};
+use sha3::{Digest, Keccak256};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Gas limit of transactions that do not specify one.
+const DEFAULT_GAS_LIMIT: u64 = 100_000_000_000;
+
+/// Ethereum JSON-RPC methods.
+#[rpc(server)]
+pub trait EthApi {
+	/// Chain ID.
+	#[rpc(name = "eth_chainId")]
+	fn chain_id(&self) -> Result<String>;
+
+	/// Current block number.
+	#[rpc(name = "eth_blockNumber")]
+	fn block_number(&self) -> Result<String>;
+
+	/// Unlocked and impersonated accounts, which can send transactions.
+	#[rpc(name = "eth_accounts")]
+	fn accounts(&self) -> Result<Vec<String>>;
+
+	/// Balance of an account.
+	#[rpc(name = "eth_getBalance")]
+	fn balance(&self, address: String, block: Option<String>) -> Result<String>;
+
+	/// Nonce of an account.
+	#[rpc(name = "eth_getTransactionCount")]
+	fn transaction_count(&self, address: String, block: Option<String>) -> Result<String>;
+
+	/// Code of an account.
+	#[rpc(name = "eth_getCode")]
+	fn code(&self, address: String, block: Option<String>) -> Result<String>;
+
+	/// Storage value of an account.
+	#[rpc(name = "eth_getStorageAt")]
+	fn storage_at(&self, address: String, index: String, block: Option<String>) -> Result<String>;
+
+	/// Execute a transaction without committing it, returning its output.
+	#[rpc(name = "eth_call")]
+	fn call(&self, transaction: Value, block: Option<String>) -> Result<String>;
+
+	/// Execute and commit a transaction from an unlocked or impersonated
+	/// account, returning its hash.
+	#[rpc(name = "eth_sendTransaction")]
+	fn send_transaction(&self, transaction: Value) -> Result<String>;
+
+	/// Receipt of a transaction sent with `eth_sendTransaction`.
+	#[rpc(name = "eth_getTransactionReceipt")]
+	fn transaction_receipt(&self, hash: String) -> Result<Option<Value>>;
+
+	/// Generate the access list of a transaction without committing it.
+	#[rpc(name = "eth_createAccessList")]
+	fn create_access_list(&self, transaction: Value, block: Option<String>) -> Result<Value>;
+}
+
+/// Development methods, compatible with Hardhat Network.
+#[rpc(server)]
+pub trait DevApi {
+	/// Record the current state, returning a snapshot ID.
+	#[rpc(name = "evm_snapshot")]
+	fn snapshot(&self) -> Result<String>;
+
+	/// Discard all changes made after the snapshot was taken.
+	#[rpc(name = "evm_revert")]
+	fn revert(&self, id: String) -> Result<bool>;
+
+	/// Advance to the next block, at the given timestamp or at the current
+	/// time plus the offset set by `evm_increaseTime` and earlier timestamps,
+	/// which is negative after a timestamp in the past.
+	#[rpc(name = "evm_mine")]
+	fn mine(&self, timestamp: Option<Value>) -> Result<String>;
+
+	/// Move the block timestamp forward, returning the total adjustment.
+	#[rpc(name = "evm_increaseTime")]
+	fn increase_time(&self, seconds: Value) -> Result<i64>;
+
+	/// Set the randomness returned by `PREVRANDAO` in the current block.
+	/// Each mined block derives its randomness from the previous one.
+	#[rpc(name = "hardhat_setPrevRandao")]
+	fn set_prev_randao(&self, prev_randao: String) -> Result<bool>;
+
+	/// Set the balance of an account.
+	#[rpc(name = "hardhat_setBalance")]
+	fn set_balance(&self, address: String, balance: Value) -> Result<bool>;
+
+	/// Set the code of an account.
+	#[rpc(name = "hardhat_setCode")]
+	fn set_code(&self, address: String, code: String) -> Result<bool>;
+
+	/// Set the nonce of an account.
+	#[rpc(name = "hardhat_setNonce")]
+	fn set_nonce(&self, address: String, nonce: Value) -> Result<bool>;
+
+	/// Set a storage value of an account.
+	#[rpc(name = "hardhat_setStorageAt")]
+	fn set_storage_at(&self, address: String, index: String, value: String) -> Result<bool>;
+
+	/// Execute a call like `eth_call`, returning its output along with the
+	/// messages logged through `console.sol`.
+	#[rpc(name = "quark_call")]
+	fn call_with_console(&self, transaction: Value) -> Result<Value>;
+
+	/// Allow sending transactions from an account without its key.
+	#[rpc(name = "hardhat_impersonateAccount")]
+	fn impersonate_account(&self, address: String) -> Result<bool>;
+
+	/// Stop listing an impersonated account.
+	#[rpc(name = "hardhat_stopImpersonatingAccount")]
+	fn stop_impersonating_account(&self, address: String) -> Result<bool>;
+}
+
+/// Node state restored by `evm_revert` along with the database.
+#[derive(Clone, Copy, Debug)]
+struct Snapshot {
+	block_number: U256,
+	block_timestamp: U256,
+	block_prev_randao: H256,
+	time_offset: i64,
+	/// Number of receipts.
+	receipts: usize,
+}
+
+/// Node state shared between requests.
+pub struct Node {
+	db_path: String,
+	vicinity: MemoryVicinity,
+	config: Config,
+	/// Accounts that can send transactions, listed by `eth_accounts`.
+	accounts: BTreeSet<H160>,
+	impersonated: BTreeSet<H160>,
+	/// Seconds from the current time to the timestamp of blocks.
+	time_offset: i64,
+	snapshots: BTreeMap<u64, Snapshot>,
+	/// Receipts of sent transactions, in order.
+	receipts: Vec<(H256, Value)>,
+}
+
+/// Result of a transaction executed by the node.
+struct Execution {
+	reason: ExitReason,
+	output: Vec<u8>,
+	contract_address: Option<H160>,
+	used_gas: u64,
+	logs: Vec<Log>,
+	console: Vec<String>,
+}
+
+impl Node {
+	/// Create a node over the database at `db_path`, creating the schema if it
+	/// does not exist yet.
+	pub fn new(db_path: String, vicinity: MemoryVicinity, config: Config) -> Self {
+		MemoryBackend::new(&vicinity, BTreeMap::new(), db_path.clone(), true);
+
+		Self {
+			db_path,
+			vicinity,
+			config,
+			accounts: BTreeSet::new(),
+			impersonated: BTreeSet::new(),
+			time_offset: 0,
+			snapshots: BTreeMap::new(),
+			receipts: Vec::new(),
+		}
+	}
+
+	/// Unlock `accounts`, so that they can send transactions.
+	pub fn with_accounts<I: IntoIterator<Item = H160>>(mut self, accounts: I) -> Self {
+		self.accounts.extend(accounts);
+		self
+	}
+
+	fn backend(&self) -> MemoryBackend<'_> {
+		self.backend_with(&self.vicinity)
+	}
+
+	/// Backend over the node's database seeing `vicinity`, such as the node's
+	/// vicinity with the origin of a transaction.
+	fn backend_with<'vicinity>(
+		&self,
+		vicinity: &'vicinity MemoryVicinity,
+	) -> MemoryBackend<'vicinity> {
+		MemoryBackend::new(vicinity, BTreeMap::new(), self.db_path.clone(), false)
+	}
+
+	/// The node's vicinity for a transaction sent by `origin`.
+	fn vicinity_for(&self, origin: H160) -> MemoryVicinity {
+		MemoryVicinity {
+			origin,
+			..self.vicinity.clone()
+		}
+	}
+
+	/// Apply a change to a single account through `ApplyBackend`.
+	fn modify(
+		&self,
+		address: H160,
+		f: impl FnOnce(&mut Basic),
+		code: Option<Vec<u8>>,
+		storage: Vec<(H256, H256)>,
+	) {
+		let mut backend = self.backend();
+		let mut basic = backend.basic(address);
+		f(&mut basic);
+
+		backend.apply(
+			vec![Apply::Modify {
+				address,
+				basic,
+				code,
+				storage,
+				reset_storage: false,
+			}],
+			Vec::new(),
+			false,
+		);
+	}
+
+	/// Execute a transaction, applying its changes if `commit` is set.
+	///
+	/// Messages logged through `console.sol` are printed and returned along
+	/// with the outcome.
+	fn transact(&self, transaction: &Value, commit: bool) -> Result<Execution> {
+		let Request {
+			from,
+			to,
+			value,
+			gas_limit,
+			data,
+		} = Request::parse(transaction)?;
+
+		let vicinity = self.vicinity_for(from);
+		let mut backend = self.backend_with(&vicinity);
+		let metadata = StackSubstateMetadata::new(gas_limit, &self.config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let console = RefCell::new(Vec::new());
+		let precompiles = ConsoleLog::new(StandardPrecompiles::new(&self.config), &console);
+		let mut executor = StackExecutor::new_with_precompiles(state, &self.config, &precompiles);
+
+		let (reason, output, contract_address) = match to {
+			Some(to) => {
+				let (reason, output) =
+					executor.transact_call(from, to, value, data, gas_limit, Vec::new());
+				(reason, output, None)
+			}
+			None => {
+				let address = executor.create_address(CreateScheme::Legacy { caller: from });
+				let (reason, _) =
+					executor.transact_create(from, value, data, gas_limit, Vec::new());
+				(reason, address.as_bytes().to_vec(), Some(address))
+			}
+		};
+		let used_gas = executor.used_gas();
+
+		let (applies, logs) = executor.into_state().deconstruct();
+		let logs = logs.into_iter().collect::<Vec<_>>();
+		if commit {
+			backend.apply(applies, logs.clone(), false);
+		}
+
+		let console = console.into_inner();
+		for message in &console {
+			StdoutSink.log(message);
+		}
+
+		Ok(Execution {
+			reason,
+			output,
+			contract_address,
+			used_gas,
+			logs,
+			console,
+		})
+	}
+
+	/// Receipt of a successful transaction sent in the current block.
+	fn receipt(&self, hash: H256, from: H160, to: Option<H160>, execution: &Execution) -> Value {
+		let block_number = format!("{:#x}", self.vicinity.block_number);
+		let block_hash = format!("{:?}", block_hash(self.vicinity.block_number));
+		let logs = execution
+			.logs
+			.iter()
+			.enumerate()
+			.map(|(i, log)| {
+				json!({
+					"address": format!("{:?}", log.address),
+					"topics": log.topics.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
+					"data": format!("0x{}", hex::encode(&log.data)),
+					"logIndex": format!("{:#x}", i),
+					"transactionIndex": "0x0",
+					"transactionHash": format!("{:?}", hash),
+					"blockNumber": block_number,
+					"blockHash": block_hash,
+					"removed": false,
+				})
+			})
+			.collect::<Vec<_>>();
+
+		json!({
+			"transactionHash": format!("{:?}", hash),
+			"transactionIndex": "0x0",
+			"blockNumber": block_number,
+			"blockHash": block_hash,
+			"from": format!("{:?}", from),
+			"to": to.map(|to| format!("{:?}", to)),
+			"contractAddress": execution.contract_address.map(|a| format!("{:?}", a)),
+			"cumulativeGasUsed": format!("{:#x}", execution.used_gas),
+			"gasUsed": format!("{:#x}", execution.used_gas),
+			"effectiveGasPrice": "0x0",
+			"logs": logs,
+			"logsBloom": format!("0x{}", hex::encode([0u8; 256])),
+			"console": execution.console,
+			"status": "0x1",
+			"type": "0x0",
+		})
+	}
+}
+
+/// Hash of a block of the node. Blocks are not stored, so this is derived
+/// from the block number alone.
+fn block_hash(number: U256) -> H256 {
+	H256::from_slice(&Keccak256::digest(&<[u8; 32]>::from(number)))
+}
+
+/// Current Unix time, in seconds.
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |d| d.as_secs())
+}
+
+/// Fields of a transaction object.
+struct Request {
+	from: H160,
+	to: Option<H160>,
+	value: U256,
+	gas_limit: u64,
+	data: Vec<u8>,
+}
+
+impl Request {
+	fn parse(transaction: &Value) -> Result<Self> {
+		let from = parse_address(&transaction["from"]).unwrap_or_default();
+		let to = match &transaction["to"] {
+			Value::Null => None,
+			to => Some(parse_address(to)?),
+		};
+		let value = match &transaction["value"] {
+			Value::Null => U256::zero(),
+			value => parse_quantity(value)?,
+		};
+		let gas_limit = match &transaction["gas"] {
+			Value::Null => DEFAULT_GAS_LIMIT,
+			gas => parse_quantity(gas)?.low_u64(),
+		};
+		let data = match (&transaction["data"], &transaction["input"]) {
+			(Value::Null, Value::Null) => Vec::new(),
+			(Value::Null, data) | (data, _) => parse_bytes(data)?,
+		};
+		Ok(Self {
+			from,
+			to,
+			value,
+			gas_limit,
+			data,
+		})
+	}
+}
+
+/// JSON-RPC server implementation over a [`Node`].
+#[derive(Clone)]
+pub struct NodeRpc {
+	node: Arc<Mutex<Node>>,
+}
+
+impl NodeRpc {
+	/// Create the server implementation.
+	pub fn new(node: Node) -> Self {
+		Self {
+			node: Arc::new(Mutex::new(node)),
+		}
+	}
+
+	fn node(&self) -> MutexGuard<'_, Node> {
+		self.node.lock().unwrap_or_else(|e| e.into_inner())
+	}
+}
+
+impl EthApi for NodeRpc {
+	fn chain_id(&self) -> Result<String> {
+		Ok(format!("{:#x}", self.node().vicinity.chain_id))
+	}
+
+	fn block_number(&self) -> Result<String> {
+		Ok(format!("{:#x}", self.node().vicinity.block_number))
+	}
+
+	fn accounts(&self) -> Result<Vec<String>> {
+		let node = self.node();
+		Ok(node
+			.accounts
+			.union(&node.impersonated)
+			.map(|a| format!("{:?}", a))
+			.collect())
+	}
+
+	fn balance(&self, address: String, _block: Option<String>) -> Result<String> {
+		let address = parse_address(&Value::String(address))?;
+		Ok(format!(
+			"{:#x}",
+			self.node().backend().basic(address).balance
+		))
+	}
+
+	fn transaction_count(&self, address: String, _block: Option<String>) -> Result<String> {
+		let address = parse_address(&Value::String(address))?;
+		Ok(format!("{:#x}", self.node().backend().basic(address).nonce))
+	}
+
+	fn code(&self, address: String, _block: Option<String>) -> Result<String> {
+		let address = parse_address(&Value::String(address))?;
+		Ok(format!(
+			"0x{}",
+			hex::encode(self.node().backend().code(address))
+		))
+	}
+
+	fn storage_at(&self, address: String, index: String, _block: Option<String>) -> Result<String> {
+		let address = parse_address(&Value::String(address))?;
+		let index = parse_word(&Value::String(index))?;
+		Ok(format!(
+			"{:?}",
+			self.node().backend().storage(address, index)
+		))
+	}
+
+	fn call(&self, transaction: Value, _block: Option<String>) -> Result<String> {
+		let execution = self.node().transact(&transaction, false)?;
+		match execution.reason {
+			ExitReason::Succeed(_) => Ok(format!("0x{}", hex::encode(execution.output))),
+			reason => Err(execution_error(reason, execution.output, execution.console)),
+		}
+	}
+
+	fn send_transaction(&self, transaction: Value) -> Result<String> {
+		let mut node = self.node();
+		let from = parse_address(&transaction["from"]).unwrap_or_default();
+		if !node.accounts.contains(&from) && !node.impersonated.contains(&from) {
+			return Err(Error {
+				code: ErrorCode::ServerError(-32000),
+				message: format!("unknown account {:?}", from),
+				data: None,
+			});
+		}
+		let to = Request::parse(&transaction)?.to;
+		let nonce = node.backend().basic(from).nonce;
+
+		let execution = node.transact(&transaction, true)?;
+		match execution.reason {
+			ExitReason::Succeed(_) => {
+				let mut hasher = Keccak256::new();
+				hasher.input(from.as_bytes());
+				hasher.input(<[u8; 32]>::from(nonce));
+				hasher.input(transaction.to_string().as_bytes());
+				let hash = H256::from_slice(hasher.result().as_slice());
+
+				let receipt = node.receipt(hash, from, to, &execution);
+				node.receipts.push((hash, receipt));
+				Ok(format!("{:?}", hash))
+			}
+			reason => Err(execution_error(reason, execution.output, execution.console)),
+		}
+	}
+
+	fn transaction_receipt(&self, hash: String) -> Result<Option<Value>> {
+		let hash = parse_word(&Value::String(hash))?;
+		Ok(self
+			.node()
+			.receipts
+			.iter()
+			.find(|(h, _)| *h == hash)
+			.map(|(_, receipt)| receipt.clone()))
+	}
+
+	fn create_access_list(&self, transaction: Value, _block: Option<String>) -> Result<Value> {
+		let request = Request::parse(&transaction)?;
+		let node = self.node();
+		// Before Berlin, access lists have no effect on gas.
+		if !node.config.increase_state_access_gas {
+			return Err(Error {
+				code: ErrorCode::ServerError(-32000),
+				message: "access lists require Berlin or later".into(),
+				data: None,
+			});
+		}
+		let vicinity = node.vicinity_for(request.from);
+		let backend = node.backend_with(&vicinity);
+		let console = RefCell::new(Vec::new());
+		let precompiles = ConsoleLog::new(StandardPrecompiles::new(&node.config), &console);
+		let result = create_access_list(
+			&backend,
+			&node.config,
+			&precompiles,
+			request.from,
+			request.to,
+			request.value,
+			request.data,
+			request.gas_limit,
+		);
+
+		let mut response = json!({
+			"accessList": access_list_json(&result.access_list),
+			"gasUsed": format!("{:#x}", result.gas_used),
+			"gasUsedWithoutAccessList": format!("{:#x}", result.gas_used_without_access_list),
+			"console": console.into_inner(),
+		});
+		if !result.exit_reason.is_succeed() {
+			response["error"] = json!(format!("execution failed: {:?}", result.exit_reason));
+		}
+		Ok(response)
+	}
+}
+
+impl DevApi for NodeRpc {
+	fn snapshot(&self) -> Result<String> {
+		let mut node = self.node();
+		let id = node.backend().snapshot();
+		let snapshot = Snapshot {
+			block_number: node.vicinity.block_number,
+			block_timestamp: node.vicinity.block_timestamp,
+			block_prev_randao: node.vicinity.block_prev_randao,
+			time_offset: node.time_offset,
+			receipts: node.receipts.len(),
+		};
+		node.snapshots.insert(id, snapshot);
+		Ok(format!("{:#x}", id))
+	}
+
+	fn revert(&self, id: String) -> Result<bool> {
+		let id = parse_quantity(&Value::String(id))?.low_u64();
+		let mut node = self.node();
+		if !node.backend().revert(id) {
+			return Ok(false);
+		}
+
+		if let Some(&snapshot) = node.snapshots.get(&id) {
+			node.vicinity.block_number = snapshot.block_number;
+			node.vicinity.block_timestamp = snapshot.block_timestamp;
+			node.vicinity.block_prev_randao = snapshot.block_prev_randao;
+			node.time_offset = snapshot.time_offset;
+			node.receipts.truncate(snapshot.receipts);
+		}
+		node.snapshots.split_off(&id);
+		Ok(true)
+	}
+
+	fn mine(&self, timestamp: Option<Value>) -> Result<String> {
+		let mut node = self.node();
+		node.vicinity.block_number += U256::one();
+		node.vicinity.block_prev_randao =
+			H256::from_slice(&Keccak256::digest(node.vicinity.block_prev_randao.as_bytes()));
+		let now = now();
+		node.vicinity.block_timestamp = match timestamp {
+			// Later blocks keep the same distance to the current time.
+			Some(timestamp) => {
+				let timestamp = parse_quantity(&timestamp)?;
+				node.time_offset = timestamp.low_u64() as i64 - now as i64;
+				timestamp
+			}
+			None => max(
+				U256::from(max(now as i64 + node.time_offset, 0)),
+				node.vicinity.block_timestamp + U256::one(),
+			),
+		};
+		Ok("0x0".into())
+	}
+
+	fn increase_time(&self, seconds: Value) -> Result<i64> {
+		let seconds = parse_quantity(&seconds)?.low_u64();
+		let mut node = self.node();
+		node.vicinity.block_timestamp += U256::from(seconds);
+		node.time_offset += seconds as i64;
+		Ok(node.time_offset)
+	}
+
+	fn set_prev_randao(&self, prev_randao: String) -> Result<bool> {
+		let prev_randao = parse_word(&Value::String(prev_randao))?;
+		self.node().vicinity.block_prev_randao = prev_randao;
+		Ok(true)
+	}
+
+	fn set_balance(&self, address: String, balance: Value) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		let balance = parse_quantity(&balance)?;
+		self.node()
+			.modify(address, |basic| basic.balance = balance, None, Vec::new());
+		Ok(true)
+	}
+
+	fn set_code(&self, address: String, code: String) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		let code = parse_bytes(&Value::String(code))?;
+		self.node().modify(address, |_| (), Some(code), Vec::new());
+		Ok(true)
+	}
+
+	fn set_nonce(&self, address: String, nonce: Value) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		let nonce = parse_quantity(&nonce)?;
+		self.node()
+			.modify(address, |basic| basic.nonce = nonce, None, Vec::new());
+		Ok(true)
+	}
+
+	fn set_storage_at(&self, address: String, index: String, value: String) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		let index = parse_word(&Value::String(index))?;
+		let value = parse_word(&Value::String(value))?;
+		self.node()
+			.modify(address, |_| (), None, vec![(index, value)]);
+		Ok(true)
+	}
+
+	fn call_with_console(&self, transaction: Value) -> Result<Value> {
+		let execution = self.node().transact(&transaction, false)?;
+		match execution.reason {
+			ExitReason::Succeed(_) => Ok(json!({
+				"output": format!("0x{}", hex::encode(execution.output)),
+				"console": execution.console,
+			})),
+			reason => Err(execution_error(reason, execution.output, execution.console)),
+		}
+	}
+
+	fn impersonate_account(&self, address: String) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		self.node().impersonated.insert(address);
+		Ok(true)
+	}
+
+	fn stop_impersonating_account(&self, address: String) -> Result<bool> {
+		let address = parse_address(&Value::String(address))?;
+		Ok(self.node().impersonated.remove(&address))
+	}
+}
+
+/// Start serving the JSON-RPC API of `node` over HTTP at `address`.
+pub fn serve(node: Node, address: &SocketAddr) -> std::io::Result<Server> {
+	let rpc = NodeRpc::new(node);
+	let mut io = IoHandler::new();
+	io.extend_with(EthApi::to_delegate(rpc.clone()));
+	io.extend_with(DevApi::to_delegate(rpc));
+
+	ServerBuilder::new(io).start_http(address)
+}
+
+/// An access list in the JSON format of EIP-2930 transactions.
+pub fn access_list_json(access_list: &AccessList) -> Value {
+	access_list
+		.iter()
+		.map(|(address, keys)| {
+			json!({
+				"address": format!("{:?}", address),
+				"storageKeys": keys.iter().map(|k| format!("{:?}", k)).collect::<Vec<_>>(),
+			})
+		})
+		.collect()
+}
+
+fn execution_error(reason: ExitReason, output: Vec<u8>, console: Vec<String>) -> Error {
+	let mut error = Error::internal_error();
+	error.message = format!("execution failed: {:?}", reason);
+	error.data = Some(json!({
+		"output": format!("0x{}", hex::encode(output)),
+		"console": console,
+	}));
+	error
+}
+
+fn invalid(value: &Value) -> Error {
+	Error::invalid_params(format!("invalid value: {}", value))
+}
+
+fn parse_bytes(value: &Value) -> Result<Vec<u8>> {
+	value
+		.as_str()
+		.and_then(|s| hex::decode(s.strip_prefix("0x").unwrap_or(s)).ok())
+		.ok_or_else(|| invalid(value))
+}
+
+fn parse_address(value: &Value) -> Result<H160> {
+	match parse_bytes(value)? {
+		bytes if bytes.len() == 20 => Ok(H160::from_slice(&bytes)),
+		_ => Err(invalid(value)),
+	}
+}
+
+fn parse_word(value: &Value) -> Result<H256> {
+	U256::from_str_radix(
+		value.as_str().unwrap_or_default().trim_start_matches("0x"),
+		16,
+	)
+	.map(|v| H256::from(<[u8; 32]>::from(v)))
+	.map_err(|_| invalid(value))
+}
+
+fn parse_quantity(value: &Value) -> Result<U256> {
+	match value {
+		Value::Number(n) => n.as_u64().map(U256::from).ok_or_else(|| invalid(value)),
+		Value::String(s) => match s.strip_prefix("0x") {
+			Some(hex) => U256::from_str_radix(hex, 16).map_err(|_| invalid(value)),
+			None => U256::from_dec_str(s).map_err(|_| invalid(value)),
+		},
+		_ => Err(invalid(value)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::precompiles::CONSOLE_ADDRESS;
+
+	fn node_rpc(name: &str) -> NodeRpc {
+		let db_path =
+			std::env::temp_dir().join(format!("rpc-{}-{}.sqlite", name, std::process::id()));
+		let _ = std::fs::remove_file(&db_path);
+		let vicinity = MemoryVicinity {
+			gas_price: U256::zero(),
+			origin: H160::default(),
+			chain_id: U256::one(),
+			block_hashes: Vec::new(),
+			block_number: U256::zero(),
+			block_coinbase: H160::default(),
+			block_timestamp: U256::zero(),
+			block_difficulty: U256::zero(),
+			block_prev_randao: H256::zero(),
+			block_gas_limit: U256::zero(),
+			block_base_fee_per_gas: U256::zero(),
+			block_blob_base_fee: U256::zero(),
+			blob_hashes: Vec::new(),
+		};
+		let node = Node::new(
+			db_path.to_str().unwrap().to_string(),
+			vicinity,
+			Config::istanbul(),
+		)
+		.with_accounts(vec![H160::repeat_byte(0xaa)]);
+		NodeRpc::new(node)
+	}
+
+	#[test]
+	fn test_send_transaction() {
+		let rpc = node_rpc("send");
+		let sender = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+		let target = "0xcccccccccccccccccccccccccccccccccccccccc";
+		// PUSH1 0, PUSH1 0, LOG0
+		assert!(rpc.set_code(target.into(), "0x60006000a0".into()).unwrap());
+		assert_eq!(rpc.code(target.into(), None).unwrap(), "0x60006000a0");
+
+		let transaction = json!({ "from": sender, "to": target });
+		let error = rpc.send_transaction(transaction.clone()).unwrap_err();
+		assert_eq!(error.code, ErrorCode::ServerError(-32000));
+
+		assert!(rpc.impersonate_account(sender.into()).unwrap());
+		assert_eq!(
+			rpc.accounts().unwrap(),
+			vec![format!("{:?}", H160::repeat_byte(0xaa)), sender.to_string()]
+		);
+		let hash = rpc.send_transaction(transaction.clone()).unwrap();
+		let receipt = rpc.transaction_receipt(hash.clone()).unwrap().unwrap();
+		assert_eq!(receipt["transactionHash"], json!(hash));
+		assert_eq!(receipt["status"], json!("0x1"));
+		assert_eq!(receipt["to"], json!(target));
+		assert_eq!(receipt["gasUsed"], json!("0x5385"));
+		assert_eq!(receipt["logs"][0]["address"], json!(target));
+		assert_eq!(rpc.transaction_count(sender.into(), None).unwrap(), "0x1");
+
+		let id = rpc.snapshot().unwrap();
+		assert!(rpc.set_balance(sender.into(), json!("0x64")).unwrap());
+		assert_eq!(rpc.balance(sender.into(), None).unwrap(), "0x64");
+		let reverted = rpc.send_transaction(transaction.clone()).unwrap();
+		assert!(rpc.revert(id).unwrap());
+		assert_eq!(rpc.balance(sender.into(), None).unwrap(), "0x0");
+		assert!(rpc.transaction_receipt(hash).unwrap().is_some());
+		assert!(rpc.transaction_receipt(reverted).unwrap().is_none());
+
+		assert!(rpc.stop_impersonating_account(sender.into()).unwrap());
+		assert!(rpc.send_transaction(transaction).is_err());
+	}
+
+	#[test]
+	fn test_origin() {
+		let rpc = node_rpc("origin");
+		let sender = format!("{:?}", H160::repeat_byte(0xaa));
+		let target = "0xcccccccccccccccccccccccccccccccccccccccc";
+		// ORIGIN, PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+		assert!(rpc.set_code(target.into(), "0x3260005260206000f3".into()).unwrap());
+		let transaction = json!({ "from": sender, "to": target });
+		assert_eq!(
+			rpc.call(transaction, None).unwrap(),
+			format!("0x{:0>64}", &sender[2..])
+		);
+	}
+
+	#[test]
+	fn test_console() {
+		let rpc = node_rpc("console");
+		// console.log("hi")
+		let data = format!(
+			"0x41304fac{:064x}{:064x}{}",
+			0x20,
+			2,
+			hex::encode([b"hi".as_ref(), &[0; 30]].concat())
+		);
+		let transaction = json!({
+			"from": format!("{:?}", H160::repeat_byte(0xaa)),
+			"to": format!("{:?}", CONSOLE_ADDRESS),
+			"data": data,
+		});
+		let result = rpc.call_with_console(transaction.clone()).unwrap();
+		assert_eq!(result, json!({ "output": "0x", "console": ["hi"] }));
+
+		let hash = rpc.send_transaction(transaction).unwrap();
+		let receipt = rpc.transaction_receipt(hash).unwrap().unwrap();
+		assert_eq!(receipt["console"], json!(["hi"]));
+	}
+
+	#[test]
+	fn test_create_access_list() {
+		let rpc = node_rpc("access-list");
+		let target = "0xcccccccccccccccccccccccccccccccccccccccc";
+		// PUSH20 0xdd..dd, BALANCE, STOP
+		let code = format!("0x73{}3100", "dd".repeat(20));
+		assert!(rpc.set_code(target.into(), code).unwrap());
+		let transaction = json!({ "from": format!("{:?}", H160::repeat_byte(0xaa)), "to": target });
+		let error = rpc.create_access_list(transaction.clone(), None).unwrap_err();
+		assert_eq!(error.code, ErrorCode::ServerError(-32000));
+
+		rpc.node().config = Config::berlin();
+		let result = rpc.create_access_list(transaction, None).unwrap();
+		assert_eq!(
+			result["accessList"],
+			json!([{ "address": format!("{:?}", H160::repeat_byte(0xdd)), "storageKeys": [] }])
+		);
+		assert_eq!(result["gasUsed"], "0x5bcf");
+		assert_eq!(result["gasUsedWithoutAccessList"], "0x5c33");
+	}
+
+	#[test]
+	fn test_mine() {
+		let rpc = node_rpc("mine");
+		rpc.mine(Some(json!("0x3e8"))).unwrap();
+		assert_eq!(rpc.block_number().unwrap(), "0x1");
+		assert_eq!(rpc.node().vicinity.block_timestamp, U256::from(1000));
+
+		// Mining again keeps timestamps increasing, at the same distance to
+		// the current time.
+		rpc.mine(None).unwrap();
+		let timestamp = rpc.node().vicinity.block_timestamp;
+		assert!(timestamp > U256::from(1000));
+		assert!(timestamp < U256::from(1000 + 60));
+
+		rpc.increase_time(json!(3600)).unwrap();
+		rpc.mine(None).unwrap();
+		assert!(rpc.node().vicinity.block_timestamp >= timestamp + U256::from(3600));
+		assert!(rpc.node().vicinity.block_timestamp < U256::from(now()));
+	}
+}